@@ -0,0 +1,218 @@
+use macroquad::{math::Vec2, rand::gen_range};
+
+use crate::input::PlayerInput;
+
+// horizontal gap between the two fighters' centers where attacks connect
+const ATTACK_RANGE: f32 = 70.0;
+// closer than this and we back off a little before swinging again
+const TOO_CLOSE: f32 = 25.0;
+// an opponent swinging inside this range is worth getting away from
+const DANGER_RANGE: f32 = 110.0;
+// further than this and we run instead of walk
+const RUN_DISTANCE: f32 = 300.0;
+// targets this far above us are on a platform we have to jump to
+const JUMP_HEIGHT: f32 = 40.0;
+const JUMP_REACH: f32 = 160.0;
+// attack buttons are held this many ticks, long enough for the character to pick them up
+const ATTACK_HOLD_TICKS: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AiDifficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl AiDifficulty {
+    pub const ALL: [AiDifficulty; 3] = [AiDifficulty::Easy, AiDifficulty::Normal, AiDifficulty::Hard];
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            AiDifficulty::Easy => "Easy",
+            AiDifficulty::Normal => "Normal",
+            AiDifficulty::Hard => "Hard",
+        }
+    }
+
+    /// Ticks between decisions, slower reactions are most of what makes a CPU easy
+    fn get_reaction_ticks(&self) -> u32 {
+        match self {
+            AiDifficulty::Easy => 24,
+            AiDifficulty::Normal => 12,
+            AiDifficulty::Hard => 5,
+        }
+    }
+
+    /// Chance of swinging when in range
+    fn get_aggression(&self) -> f32 {
+        match self {
+            AiDifficulty::Easy => 0.3,
+            AiDifficulty::Normal => 0.6,
+            AiDifficulty::Hard => 0.85,
+        }
+    }
+
+    /// Chance of getting out of the way of an attack
+    fn get_evasion(&self) -> f32 {
+        match self {
+            AiDifficulty::Easy => 0.1,
+            AiDifficulty::Normal => 0.35,
+            AiDifficulty::Hard => 0.7,
+        }
+    }
+}
+
+/// Another fighter as the AI sees them
+pub struct AiTarget {
+    /// Top left, like every other position
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Their attack is out right now
+    pub is_attacking: bool,
+}
+
+/// What the AI gets to know about its own fighter and the fight, built fresh every tick by whoever runs it
+pub struct AiView {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub is_grounded: bool,
+    /// Whether there's floor a step ahead in each direction, so we don't walk off ledges
+    pub ground_left: bool,
+    pub ground_right: bool,
+    pub targets: Vec<AiTarget>,
+}
+
+/// Turns an `AiView` into the same `PlayerInput` a keyboard would produce
+pub struct AiController {
+    difficulty: AiDifficulty,
+    /// Ticks until the next decision, the input in between stays held
+    ticks_until_decision: u32,
+    held_input: PlayerInput,
+    attack_ticks_left: u32,
+}
+
+impl AiController {
+    pub fn new(difficulty: AiDifficulty) -> Self {
+        Self {
+            difficulty,
+            ticks_until_decision: 0,
+            held_input: PlayerInput::default(),
+            attack_ticks_left: 0,
+        }
+    }
+
+    pub fn get_difficulty(&self) -> AiDifficulty {
+        self.difficulty
+    }
+
+    /// Call once per simulation tick
+    pub fn update(&mut self, view: &AiView) -> PlayerInput {
+        // attack buttons are let go on their own so the next swing is a fresh press
+        if self.attack_ticks_left > 0 {
+            self.attack_ticks_left -= 1;
+
+            if self.attack_ticks_left == 0 {
+                self.held_input.attack_1 = false;
+                self.held_input.attack_2 = false;
+                self.held_input.kick = false;
+            }
+        }
+
+        if self.ticks_until_decision > 0 {
+            self.ticks_until_decision -= 1;
+            return self.held_input;
+        }
+
+        self.ticks_until_decision = self.difficulty.get_reaction_ticks();
+        self.held_input = self.decide(view);
+
+        if self.held_input.attack_1 || self.held_input.attack_2 || self.held_input.kick {
+            self.attack_ticks_left = ATTACK_HOLD_TICKS;
+        }
+
+        self.held_input
+    }
+
+    fn decide(&self, view: &AiView) -> PlayerInput {
+        let mut input = PlayerInput::default();
+
+        let center = view.position + view.size / 2.0;
+        let Some(target) = view
+            .targets
+            .iter()
+            .min_by(|a, b| (a.position + a.size / 2.0).distance(center).total_cmp(&(b.position + b.size / 2.0).distance(center)))
+        else {
+            return input;
+        };
+
+        let target_center = target.position + target.size / 2.0;
+        let dx = target_center.x - center.x;
+        let dy = target_center.y - center.y;
+        let distance = dx.abs();
+        let towards_right = dx > 0.0;
+
+        // get out of the way, and off the ground if there's nowhere to back off to.
+        // fighters can't block yet, there's no block input or animation, so this is all the defence there is
+        if target.is_attacking && distance < DANGER_RANGE && gen_range(0.0, 1.0) < self.difficulty.get_evasion() {
+            let can_retreat = if towards_right { view.ground_left } else { view.ground_right };
+
+            if can_retreat {
+                press_direction(&mut input, !towards_right);
+            } else {
+                input.jump = view.is_grounded;
+            }
+            return input;
+        }
+
+        // they're up on a platform
+        if -dy > JUMP_HEIGHT && distance < JUMP_REACH && view.is_grounded {
+            input.jump = true;
+            press_direction(&mut input, towards_right);
+            return input;
+        }
+
+        if distance > ATTACK_RANGE {
+            let has_ground = if towards_right { view.ground_right } else { view.ground_left };
+
+            // a gap between us, jump it if they're across it, otherwise wait for them at the edge
+            if !has_ground {
+                if view.is_grounded && dy.abs() < JUMP_HEIGHT {
+                    input.jump = true;
+                    press_direction(&mut input, towards_right);
+                }
+                return input;
+            }
+
+            press_direction(&mut input, towards_right);
+            input.run = distance > RUN_DISTANCE;
+            return input;
+        }
+
+        if distance < TOO_CLOSE && gen_range(0.0, 1.0) > self.difficulty.get_aggression() {
+            let can_retreat = if towards_right { view.ground_left } else { view.ground_right };
+
+            if can_retreat {
+                press_direction(&mut input, !towards_right);
+            }
+            return input;
+        }
+
+        if gen_range(0.0, 1.0) < self.difficulty.get_aggression() {
+            // a tap towards them turns us around first, attacks go whichever way we face
+            press_direction(&mut input, towards_right);
+
+            match gen_range(0, 3) {
+                0 => input.attack_1 = true,
+                1 => input.attack_2 = true,
+                _ => input.kick = true,
+            }
+        }
+
+        input
+    }
+}
+
+fn press_direction(input: &mut PlayerInput, right: bool) {
+    input.left = !right;
+    input.right = right;
+}
@@ -1,10 +1,9 @@
 use std::rc::Rc;
 use bincode::{Decode, Encode};
 
-use macroquad::time::get_frame_time;
 use macroquad::texture::{load_texture, Texture2D};
 
-use crate::animation_deltas::UpdateDeltas;
+use crate::{animation_deltas::UpdateDeltas, animation_events::AnimationEvent, hit::HitProperties, simulation::ticks_per_frame, sound::SoundEvent};
 
 #[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
 pub enum Facing {
@@ -12,7 +11,7 @@ pub enum Facing {
     Right,
 }
 
-#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum AnimationType {
     Idle,
     Crouch,
@@ -29,7 +28,25 @@ pub enum AnimationType {
     SoaringKick,
 }
 
-#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+impl AnimationType {
+    pub const ALL: [AnimationType; 13] = [
+        AnimationType::Idle,
+        AnimationType::Crouch,
+        AnimationType::ForwardRun,
+        AnimationType::ReverseRun,
+        AnimationType::Jump,
+        AnimationType::JumpMoving,
+        AnimationType::Landing,
+        AnimationType::ForwardWalk,
+        AnimationType::ReverseWalk,
+        AnimationType::Attack1,
+        AnimationType::Attack2,
+        AnimationType::Attack3,
+        AnimationType::SoaringKick,
+    ];
+}
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CharacterType {
     Fighter,
     Shinobi,
@@ -39,7 +56,8 @@ pub enum CharacterType {
 pub struct PlayerAnimationState {
     pub anim_type: AnimationType,
     pub character_type: CharacterType,
-    pub time: f32,
+    /// Simulation ticks spent on the current frame
+    pub ticks: u32,
 
     /// Frame is the current frame of the spritesheetNOT the animation frame
     pub sprite_frame: usize,
@@ -57,12 +75,35 @@ pub struct PlayerAnimationState {
 
     /// For animations like Idle, we just want to lop them
     pub always_plays: bool,
+}
 
-    /// If an animation is interruptable, it means that it can be interrupted by another animation
-    pub is_interuptable: bool,
+/// Where an animation is at, enough to put it back exactly where it was
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationSnapshot {
+    pub anim_type: AnimationType,
+    pub ticks: u32,
+    pub sprite_frame: usize,
+    pub sequence_index: usize,
+    pub sequence_frame_index: usize,
+    pub actively_playing: bool,
 }
 
 impl PlayerAnimationState {
+    pub fn new(anim_type: AnimationType, character_type: CharacterType, animation_sequence: Vec<AnimationSequence>, always_plays: bool) -> Self {
+        Self {
+            anim_type,
+            character_type,
+            ticks: 0,
+            sprite_frame: 0,
+            animation_sequence,
+            sequence_index: 0,
+            sequence_frame_index: 0,
+            actively_playing: false,
+            always_plays,
+        }
+    }
+
+    /// Advances the animation by one simulation tick
     pub fn update(&mut self) -> UpdateDeltas {
         let sequence = &self.animation_sequence.get(self.sequence_index);
         let sequence = match sequence {
@@ -75,9 +116,14 @@ impl PlayerAnimationState {
         delta.width = sequence.width;
 
         if self.actively_playing || self.always_plays {
-            self.time += get_frame_time();
+            // a frame's events go out on its first tick
+            if self.ticks == 0 {
+                delta.events.extend(sequence.get_events(self.sequence_frame_index));
+            }
+
+            self.ticks += 1;
 
-            if self.time > 1. / sequence.fps {
+            if self.ticks >= ticks_per_frame(sequence.fps) {
                 // need to process our movement deltas no matter what
                 delta.pos_delta.0 = sequence.x_movement / sequence.frames as f32;
                 delta.pos_delta.1 = sequence.y_movement / sequence.frames as f32;
@@ -87,6 +133,10 @@ impl PlayerAnimationState {
                 let is_last_sequence = self.sequence_index == self.animation_sequence.len() - 1;
                 let is_last_sequence_frame = self.sequence_frame_index == sequence.frames - 1;
 
+                if is_last_sequence_frame {
+                    delta.events.extend(sequence.get_events(sequence.frames));
+                }
+
                 if !is_last_sequence_frame {
                     self.sprite_frame += 1;
                     self.sequence_frame_index += 1;
@@ -108,15 +158,124 @@ impl PlayerAnimationState {
                     }
                 }
 
-                self.time = 0.0;
+                self.ticks = 0;
             }
         }
 
         delta
     }
 
+    pub fn save(&self) -> AnimationSnapshot {
+        AnimationSnapshot {
+            anim_type: self.anim_type.clone(),
+            ticks: self.ticks,
+            sprite_frame: self.sprite_frame,
+            sequence_index: self.sequence_index,
+            sequence_frame_index: self.sequence_frame_index,
+            actively_playing: self.actively_playing,
+        }
+    }
+
+    /// Picks up from a snapshot of this same animation
+    pub fn load(&mut self, snapshot: &AnimationSnapshot) {
+        self.ticks = snapshot.ticks;
+        self.sprite_frame = snapshot.sprite_frame;
+        self.sequence_index = snapshot.sequence_index;
+        self.sequence_frame_index = snapshot.sequence_frame_index;
+        self.actively_playing = snapshot.actively_playing;
+    }
+
+    /// Plays from the start of `sprite_frame`, anything past the end lands on the last frame
+    pub fn seek(&mut self, sprite_frame: usize) {
+        self.reset();
+        self.actively_playing = true;
+
+        let mut first_sprite_frame = 0;
+        let last_sequence = self.animation_sequence.len().saturating_sub(1);
+
+        for (i, sequence) in self.animation_sequence.iter().enumerate() {
+            if sprite_frame < first_sprite_frame + sequence.frames || i == last_sequence {
+                self.sequence_index = i;
+                self.sequence_frame_index = (sprite_frame - first_sprite_frame).min(sequence.frames.saturating_sub(1));
+                self.sprite_frame = first_sprite_frame + self.sequence_frame_index;
+                return;
+            }
+
+            first_sprite_frame += sequence.frames;
+        }
+    }
+
+    /// The hit of the hitbox window we're in, if there's one open
+    pub fn get_active_hit(&self) -> Option<HitProperties> {
+        if !self.actively_playing {
+            return None;
+        }
+
+        let hit = self.get_passed_events().fold(None, |hit, event| match event {
+            AnimationEvent::HitboxOn(hit) => Some(hit),
+            AnimationEvent::HitboxOff => None,
+            _ => hit,
+        });
+
+        hit.cloned()
+    }
+
+    /// Whether we're in an invulnerability window
+    pub fn is_invulnerable(&self) -> bool {
+        self.actively_playing && self.is_window_open(AnimationEvent::InvulnerableOn, AnimationEvent::InvulnerableOff)
+    }
+
+    /// Whether we're in a cancel window, so another animation can take over before this one finishes
+    pub fn can_cancel(&self) -> bool {
+        self.actively_playing && self.is_window_open(AnimationEvent::CancelWindowOpen, AnimationEvent::CancelWindowClose)
+    }
+
+    /// Every event the sequences declare, keyed by the sprite frame they happen on
+    pub fn get_frame_events(&self) -> Vec<(usize, AnimationEvent)> {
+        let mut first_sprite_frame = 0;
+        let mut events = vec![];
+
+        for sequence in &self.animation_sequence {
+            for (frame, event) in &sequence.events {
+                events.push((first_sprite_frame + frame, event.clone()));
+            }
+            first_sprite_frame += sequence.frames;
+        }
+
+        events
+    }
+
+    /// Every event from the start of the animation up to and including the frame we're on, in order
+    fn get_passed_events(&self) -> impl Iterator<Item = &AnimationEvent> {
+        let sequence_index = self.sequence_index;
+        let frame_index = self.sequence_frame_index;
+
+        self.animation_sequence
+            .iter()
+            .take(sequence_index + 1)
+            .enumerate()
+            .flat_map(move |(i, sequence)| {
+                sequence.events
+                    .iter()
+                    .filter(move |(frame, _)| i < sequence_index || *frame <= frame_index)
+                    .map(|(_, event)| event)
+            })
+    }
+
+    fn is_window_open(&self, open: AnimationEvent, close: AnimationEvent) -> bool {
+        self.get_passed_events().fold(false, |is_open, event| {
+            if *event == open {
+                true
+            } else if *event == close {
+                false
+            } else {
+                is_open
+            }
+        })
+    }
+
     pub fn reset(&mut self) {
-        self.time = 0.0;
+        self.ticks = 0;
         self.sequence_frame_index = 0;
         self.sequence_index = 0;
         self.sprite_frame = 0;
@@ -137,6 +296,10 @@ pub struct AnimationSequence {
     pub height: i32,
     /// the difference in width during an animation
     pub width: i32,
+
+    /// What happens when the sequence reaches a frame, the frame counts from the start of this sequence.
+    /// Kept in frame order, events on frame `frames` go out as the sequence ends
+    pub events: Vec<(usize, AnimationEvent)>,
 }
 
 impl AnimationSequence {
@@ -159,8 +322,35 @@ impl AnimationSequence {
             y_accleration,
             height,
             width,
+            events: vec![],
         }
     }
+
+    pub fn with_event(mut self, frame: usize, event: AnimationEvent) -> Self {
+        // after anything already on the same frame, so events go out in the order they were declared
+        let index = self.events.partition_point(|(event_frame, _)| *event_frame <= frame);
+        self.events.insert(index, (frame, event));
+        self
+    }
+
+    /// The whole sequence is an active frame, the hitbox is on from its first frame until it ends
+    pub fn with_hit(self, hit: HitProperties) -> Self {
+        let frames = self.frames;
+        self.with_event(0, AnimationEvent::HitboxOn(hit))
+            .with_event(frames, AnimationEvent::HitboxOff)
+    }
+
+    pub fn with_sound(self, frame: usize, sound: SoundEvent) -> Self {
+        self.with_event(frame, AnimationEvent::Sound(sound))
+    }
+
+    /// Events declared on `frame`
+    pub fn get_events(&self, frame: usize) -> impl Iterator<Item = AnimationEvent> + '_ {
+        self.events
+            .iter()
+            .filter(move |(event_frame, _)| *event_frame == frame)
+            .map(|(_, event)| event.clone())
+    }
 }
 
 
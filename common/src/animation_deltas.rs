@@ -1,3 +1,5 @@
+use crate::animation_events::AnimationEvent;
+
 type PosDelta = (f32, f32);
 type VelDelta = (f32, f32);
 
@@ -6,11 +8,13 @@ pub struct UpdateDeltas {
     pub vel_delta: VelDelta,
     pub height: i32,
     pub width: i32,
+    /// Everything the frames we reached this tick declared, in order
+    pub events: Vec<AnimationEvent>,
 }
 
 impl Default for UpdateDeltas {
     fn default() -> Self {
-        Self { pos_delta: (0.0, 0.0), vel_delta: (0.0, 0.0), height: 0, width: 0 }
+        Self { pos_delta: (0.0, 0.0), vel_delta: (0.0, 0.0), height: 0, width: 0, events: vec![] }
     }
 }
 
@@ -0,0 +1,20 @@
+use crate::{hit::HitProperties, sound::SoundEvent};
+
+/// Something that happens when an animation reaches a frame.
+///
+/// The on/off pairs are windows, they stay open from the frame that opens them to the frame that closes them
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnimationEvent {
+    /// Anyone inside the hitbox gets hit until it's turned off
+    HitboxOn(HitProperties),
+    HitboxOff,
+    Sound(SoundEvent),
+    /// Named effect drawn where the fighter is
+    SpawnEffect(&'static str),
+    /// Hits go straight through us until it's turned off
+    InvulnerableOn,
+    InvulnerableOff,
+    /// The animation can be cut short by another one until the window closes
+    CancelWindowOpen,
+    CancelWindowClose,
+}
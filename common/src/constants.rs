@@ -2,9 +2,13 @@ pub const RUN_SPEED: f32 = 300.0;
 pub const WALK_SPEED: f32 = 150.0;
 pub const GRAVITY: f32 = 800.0;
 
+// the internal resolution the game is rendered at, the window itself can be resized
 pub const WINDOW_HEIGHT: i32 = 832;
 pub const WINDOW_WIDTH: i32 = 1280;
 
+pub const MAX_HEALTH: i32 = 100;
+pub const MAX_PLAYER_NAME_LENGTH: usize = 16;
+
 pub const DEFAULT_PLAYER_WIDTH: i32 = 28;
 pub const DEFAULT_PLAYER_HEIGHT: i32 = 93;
 
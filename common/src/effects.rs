@@ -0,0 +1,6 @@
+// names of effects in assets/effects/effects.json that aren't declared on animation frames
+
+/// Where a fighter took damage
+pub const HIT_EFFECT: &str = "hit_spark";
+/// Where a fighter was knocked out, the server sends it to everyone
+pub const DEATH_EFFECT: &str = "death_burst";
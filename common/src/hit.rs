@@ -0,0 +1,91 @@
+use bincode::{Decode, Encode};
+use macroquad::math::{Rect, Vec2};
+
+use crate::{animation::Facing, types::ServerClient};
+
+// hurtboxes get a little bigger on the server so small rounding differences don't eat hits
+const HIT_TOLERANCE: f32 = 8.0;
+
+/// A player's collider at some point in time, in world coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hurtbox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Hurtbox {
+    pub fn from_client(client: &ServerClient) -> Self {
+        Self {
+            x: client.x_pos,
+            y: client.y_pos,
+            width: client.width as f32,
+            height: client.height as f32,
+        }
+    }
+
+    pub fn get_rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+/// What an attack does to whoever its active frames connect with
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct HitProperties {
+    pub damage: i32,
+    /// Launch velocity, x points the way the attacker faces and y is up like `y_v`
+    pub knockback: (f32, f32),
+    /// Seconds the victim can't act for once the hit-stop is over
+    pub hit_stun: f32,
+    /// Seconds both fighters freeze for on impact
+    pub hit_stop: f32,
+    /// How far in front of the attacker's collider the hit reaches
+    pub range: f32,
+}
+
+impl HitProperties {
+    pub fn new(damage: i32, knockback: (f32, f32), hit_stun: f32, hit_stop: f32, range: f32) -> Self {
+        Self {
+            damage,
+            knockback,
+            hit_stun,
+            hit_stop,
+            range,
+        }
+    }
+
+    /// The area the hit covers, built off the attacker's collider
+    pub fn get_hitbox(&self, attacker_pos: Vec2, attacker_size: (i32, i32), facing: &Facing) -> Rect {
+        let (width, height) = (attacker_size.0 as f32, attacker_size.1 as f32);
+
+        match facing {
+            Facing::Right => Rect::new(attacker_pos.x + width, attacker_pos.y, self.range, height),
+            Facing::Left => Rect::new(attacker_pos.x - self.range, attacker_pos.y, self.range, height),
+        }
+    }
+
+    /// Whether this hit thrown by `attacker` reaches `hurtbox`, used by the server to check reported hits
+    pub fn connects(&self, attacker: &ServerClient, hurtbox: &Hurtbox) -> bool {
+        let attacker_pos = Vec2::new(attacker.x_pos, attacker.y_pos);
+        let hitbox = self.get_hitbox(attacker_pos, (attacker.width, attacker.height), &attacker.facing);
+
+        let rect = hurtbox.get_rect();
+        let padded = Rect::new(
+            rect.x - HIT_TOLERANCE,
+            rect.y - HIT_TOLERANCE,
+            rect.w + HIT_TOLERANCE * 2.0,
+            rect.h + HIT_TOLERANCE * 2.0,
+        );
+
+        hitbox.overlaps(&padded)
+    }
+
+    /// Knockback velocity for a victim hit by someone facing `attacker_facing`
+    pub fn get_knockback(&self, attacker_facing: &Facing) -> Vec2 {
+        match attacker_facing {
+            Facing::Right => Vec2::new(self.knockback.0, self.knockback.1),
+            Facing::Left => Vec2::new(-self.knockback.0, self.knockback.1),
+        }
+    }
+}
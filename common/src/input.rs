@@ -0,0 +1,41 @@
+use bincode::{Decode, Encode};
+use macroquad::input::{is_key_down, KeyCode};
+
+/// Everything a player can ask their fighter to do on one simulation tick.
+///
+/// Characters only ever look at this, never at the keyboard, so the same inputs always play out the same way.
+#[derive(Encode, Decode, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayerInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub run: bool,
+    pub jump: bool,
+    pub crouch: bool,
+    pub attack_1: bool,
+    pub attack_2: bool,
+    pub kick: bool,
+}
+
+impl PlayerInput {
+    pub fn from_keyboard() -> Self {
+        Self {
+            up: is_key_down(KeyCode::W),
+            down: is_key_down(KeyCode::S),
+            left: is_key_down(KeyCode::A),
+            right: is_key_down(KeyCode::D),
+            run: is_key_down(KeyCode::LeftShift),
+            jump: is_key_down(KeyCode::Space),
+            crouch: is_key_down(KeyCode::C),
+            attack_1: is_key_down(KeyCode::E),
+            attack_2: is_key_down(KeyCode::Q),
+            kick: is_key_down(KeyCode::R),
+        }
+    }
+
+    /// No movement keys held, this is what lets a fighter settle back into idle
+    pub fn is_idle(&self) -> bool {
+        !(self.up || self.down || self.left || self.right || self.jump)
+    }
+}
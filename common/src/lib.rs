@@ -1,4 +1,18 @@
+pub mod ai;
 pub mod animation;
 pub mod animation_deltas;
+pub mod animation_events;
 pub mod constants;
+pub mod effects;
+pub mod hit;
+pub mod input;
+pub mod link_conditioner;
+pub mod lobby;
+pub mod maps;
+pub mod match_rules;
+pub mod movesets;
+pub mod replay;
+pub mod simulation;
+pub mod sound;
+pub mod spawn;
 pub mod types;
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use macroquad::rand::gen_range;
+
+const MAX_PACKET_SIZE: usize = 1500;
+// netcode drops a connection after this long without hearing from the other side, so the proxy can forget it too
+const UPSTREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How badly to treat packets, every number applies to each packet on its way out
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkConditionerConfig {
+    /// One way delay, a round trip through two conditioned sockets takes twice this
+    pub latency_ms: u64,
+    /// Up to this much extra or less delay, picked per packet
+    pub jitter_ms: u64,
+    /// Chances between 0 and 1
+    pub loss: f32,
+    pub duplicate: f32,
+    /// Reordered packets get held back long enough for the next few to overtake them
+    pub reorder: f32,
+}
+
+impl LinkConditionerConfig {
+    /// `--latency 80 --jitter 20 --loss 5 --duplicate 1 --reorder 2`, the last three are percentages
+    pub fn from_args(args: &[String]) -> Self {
+        Self {
+            latency_ms: get_flag(args, "--latency").unwrap_or(0.0) as u64,
+            jitter_ms: get_flag(args, "--jitter").unwrap_or(0.0) as u64,
+            loss: get_flag(args, "--loss").unwrap_or(0.0) / 100.0,
+            duplicate: get_flag(args, "--duplicate").unwrap_or(0.0) / 100.0,
+            reorder: get_flag(args, "--reorder").unwrap_or(0.0) / 100.0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::default()
+    }
+
+    pub fn get_description(&self) -> String {
+        format!(
+            "{}ms +-{}ms, {:.0}% loss, {:.0}% duplicated, {:.0}% reordered",
+            self.latency_ms,
+            self.jitter_ms,
+            self.loss * 100.0,
+            self.duplicate * 100.0,
+            self.reorder * 100.0,
+        )
+    }
+
+    /// When a packet sent now should actually go out
+    fn get_send_time(&self, now: Instant) -> Instant {
+        let jitter = self.jitter_ms as f32;
+        let mut delay_ms = (self.latency_ms as f32 + gen_range(-jitter, jitter)).max(0.0);
+
+        if self.reorder > 0.0 && gen_range(0.0, 1.0) < self.reorder {
+            delay_ms += self.jitter_ms.max(20) as f32 * 2.0;
+        }
+
+        now + Duration::from_secs_f32(delay_ms / 1000.0)
+    }
+}
+
+fn get_flag(args: &[String], flag: &str) -> Option<f32> {
+    let i = args.iter().position(|arg| arg == flag)?;
+
+    match args.get(i + 1).map(|value| value.parse::<f32>()) {
+        Some(Ok(value)) => Some(value.max(0.0)),
+        _ => {
+            eprintln!("Missing or invalid value for {}", flag);
+            None
+        }
+    }
+}
+
+/// A non-blocking UDP socket that delays, drops, duplicates and reorders what it sends
+pub struct ConditionedSocket {
+    socket: UdpSocket,
+    config: LinkConditionerConfig,
+    /// Packets waiting for their send time, in no particular order
+    outgoing: Vec<(Instant, SocketAddr, Vec<u8>)>,
+}
+
+impl ConditionedSocket {
+    pub fn bind(addr: SocketAddr, config: LinkConditionerConfig) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            config,
+            outgoing: vec![],
+        })
+    }
+
+    pub fn send_to(&mut self, bytes: Vec<u8>, addr: SocketAddr) {
+        if self.config.loss > 0.0 && gen_range(0.0, 1.0) < self.config.loss {
+            return;
+        }
+
+        let now = Instant::now();
+
+        if self.config.duplicate > 0.0 && gen_range(0.0, 1.0) < self.config.duplicate {
+            self.outgoing.push((self.config.get_send_time(now), addr, bytes.clone()));
+        }
+
+        self.outgoing.push((self.config.get_send_time(now), addr, bytes));
+        self.flush();
+    }
+
+    /// Sends everything that has waited long enough, call this often
+    pub fn flush(&mut self) {
+        let now = Instant::now();
+
+        let (due, waiting): (Vec<_>, Vec<_>) = self.outgoing.drain(..).partition(|(send_at, _, _)| *send_at <= now);
+        self.outgoing = waiting;
+
+        let mut due = due;
+        due.sort_by_key(|(send_at, _, _)| *send_at);
+
+        for (_, addr, bytes) in due {
+            if let Err(e) = self.socket.send_to(&bytes, addr) {
+                eprintln!("Error sending packet to {}: {:?}", addr, e);
+            }
+        }
+    }
+
+    /// Sends right away without any conditioning, for a last packet before the socket is dropped along with anything still waiting
+    pub fn send_now(&self, bytes: &[u8], addr: SocketAddr) {
+        if let Err(e) = self.socket.send_to(bytes, addr) {
+            eprintln!("Error sending packet to {}: {:?}", addr, e);
+        }
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// The next packet that arrived, if there is one
+    pub fn recv_from(&self, buf: &mut [u8]) -> Option<(usize, SocketAddr)> {
+        match self.socket.recv_from(buf) {
+            Ok(received) => Some(received),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => None,
+            Err(e) => {
+                eprintln!("Error receiving packet: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Forwards packets between `listen_addr` and `upstream_addr` on a background thread, conditioning both ways.
+/// Returns the address it ended up listening on, for when `listen_addr` has port 0.
+///
+/// The server uses this in front of its real socket so every client gets the same bad connection, and clients
+/// put one between themselves and the server to get a bad connection of their own.
+/// Each sender gets its own upstream socket so the other side can still tell them apart.
+pub fn spawn_conditioned_proxy(listen_addr: SocketAddr, upstream_addr: SocketAddr, config: LinkConditionerConfig) -> std::io::Result<SocketAddr> {
+    let mut listen_socket = ConditionedSocket::bind(listen_addr, config.clone())?;
+    let listening_on = listen_socket.local_addr()?;
+
+    thread::spawn(move || {
+        // each sender's socket and when traffic last went through it either way
+        let mut upstreams: HashMap<SocketAddr, (ConditionedSocket, Instant)> = HashMap::new();
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+
+        loop {
+            while let Some((len, from)) = listen_socket.recv_from(&mut buf) {
+                if !upstreams.contains_key(&from) {
+                    // upstream can be another machine when a client is the one conditioning
+                    match ConditionedSocket::bind("0.0.0.0:0".parse().unwrap(), config.clone()) {
+                        Ok(upstream) => {
+                            upstreams.insert(from, (upstream, Instant::now()));
+                        }
+                        Err(e) => {
+                            eprintln!("Error binding proxy socket for {}: {:?}", from, e);
+                            continue;
+                        }
+                    }
+                }
+
+                if let Some((upstream, last_traffic)) = upstreams.get_mut(&from) {
+                    upstream.send_to(buf[..len].to_vec(), upstream_addr);
+                    *last_traffic = Instant::now();
+                }
+            }
+
+            for (client_addr, (upstream, last_traffic)) in upstreams.iter_mut() {
+                while let Some((len, _)) = upstream.recv_from(&mut buf) {
+                    listen_socket.send_to(buf[..len].to_vec(), *client_addr);
+                    *last_traffic = Instant::now();
+                }
+
+                upstream.flush();
+            }
+
+            // reconnects and load test clients each come from a new address, the old ones go quiet for good
+            upstreams.retain(|_, (_, last_traffic)| last_traffic.elapsed() < UPSTREAM_IDLE_TIMEOUT);
+
+            listen_socket.flush();
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+
+    Ok(listening_on)
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use bincode::{Decode, Encode};
+
+use crate::animation::CharacterType;
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct LobbyPlayer {
+    pub name: String,
+    pub character_type: CharacterType,
+    /// Index into the lobby's map rotation
+    pub map_vote: Option<usize>,
+    pub is_ready: bool,
+}
+
+impl LobbyPlayer {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            character_type: CharacterType::Fighter,
+            map_vote: None,
+            is_ready: false,
+        }
+    }
+}
+
+/// Everything the lobby screen shows, the server owns it and broadcasts it to every client
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct LobbyState {
+    pub players: HashMap<u64, LobbyPlayer>,
+    /// Names of the maps players can vote on, clients look their maps up by name
+    pub map_rotation: Vec<String>,
+    /// The map the current match is played on, set when the match starts
+    pub current_map: Option<String>,
+    /// The human who's been here longest, they pick the rules
+    pub host: Option<u64>,
+}
+
+impl LobbyState {
+    pub fn new(map_rotation: Vec<String>) -> Self {
+        Self {
+            players: HashMap::new(),
+            map_rotation,
+            current_map: None,
+            host: None,
+        }
+    }
+
+    pub fn get_vote_count(&self, map_index: usize) -> usize {
+        self.players
+            .values()
+            .filter(|player| player.map_vote == Some(map_index))
+            .count()
+    }
+
+    pub fn is_everyone_ready(&self) -> bool {
+        !self.players.is_empty() && self.players.values().all(|player| player.is_ready)
+    }
+
+    /// The map with the most votes, ties go to whichever comes first in the rotation
+    pub fn get_winning_map(&self) -> Option<String> {
+        let mut winner: Option<(usize, usize)> = None;
+
+        for i in 0..self.map_rotation.len() {
+            let votes = self.get_vote_count(i);
+            if winner.is_none_or(|(_, best_votes)| votes > best_votes) {
+                winner = Some((i, votes));
+            }
+        }
+
+        winner.map(|(i, _)| self.map_rotation[i].clone())
+    }
+}
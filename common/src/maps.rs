@@ -0,0 +1,12 @@
+/// Every map's name with its tiled file, relative to the folder the game runs from.
+///
+/// The client loads these to play on, the server only reads the spawn points out of them.
+pub const MAP_FILES: [(&str, &str); 3] = [
+    ("First Map", "assets/maps/map_01.json"),
+    ("Green Zone (Day)", "assets/maps/greenzone_map.json"),
+    ("Green Zone (Night)", "assets/maps/greenzone_map.json"),
+];
+
+pub fn get_map_file(name: &str) -> Option<&'static str> {
+    MAP_FILES.iter().find(|(map, _)| *map == name).map(|(_, file)| *file)
+}
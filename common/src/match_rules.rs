@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use bincode::{Decode, Encode};
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub enum MatchMode {
+    /// Everyone starts with `lives`, last one standing wins
+    Stock { lives: u32 },
+    /// Most KOs minus falls when the clock runs out wins
+    Timed { seconds: u32 },
+    /// First to knock out `kos` opponents wins
+    FirstToKos { kos: u32 },
+}
+
+impl Default for MatchMode {
+    fn default() -> Self {
+        MatchMode::Stock { lives: 3 }
+    }
+}
+
+impl MatchMode {
+    /// The modes offered when picking one before a match
+    pub fn presets() -> Vec<MatchMode> {
+        vec![
+            MatchMode::Stock { lives: 3 },
+            MatchMode::Stock { lives: 5 },
+            MatchMode::Timed { seconds: 120 },
+            MatchMode::Timed { seconds: 300 },
+            MatchMode::FirstToKos { kos: 5 },
+        ]
+    }
+
+    pub fn get_description(&self) -> String {
+        match self {
+            MatchMode::Stock { lives } => format!("Stock ({} lives)", lives),
+            MatchMode::Timed { seconds } => format!("Timed ({}:{:02})", seconds / 60, seconds % 60),
+            MatchMode::FirstToKos { kos } => format!("First to {} KOs", kos),
+        }
+    }
+}
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub enum MatchPhase {
+    /// No match running, players can run around freely
+    Lobby,
+    Countdown { seconds_left: f32 },
+    InProgress,
+    /// The clock ran out on a tie, the tied players are down to one life each
+    SuddenDeath,
+    /// Results are up, we go back to the lobby after `seconds_left`
+    Finished { seconds_left: f32 },
+}
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct PlayerScore {
+    pub name: String,
+    pub kos: u32,
+    pub falls: u32,
+    /// Only used in stock matches and sudden death
+    pub stocks_left: Option<u32>,
+    /// Out of lives, or joined after the match started
+    pub is_eliminated: bool,
+}
+
+impl PlayerScore {
+    pub fn new(name: String, mode: &MatchMode) -> Self {
+        let stocks_left = match mode {
+            MatchMode::Stock { lives } => Some(*lives),
+            _ => None,
+        };
+
+        Self {
+            name,
+            kos: 0,
+            falls: 0,
+            stocks_left,
+            is_eliminated: false,
+        }
+    }
+
+    /// Score used to rank timed matches
+    pub fn get_points(&self) -> i32 {
+        self.kos as i32 - self.falls as i32
+    }
+}
+
+/// Broadcast to every client so they can show the mode, clock and scores
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct MatchState {
+    pub mode: MatchMode,
+    pub phase: MatchPhase,
+    /// Seconds left on the clock, only set for timed matches
+    pub time_left: Option<f32>,
+    pub scores: HashMap<u64, PlayerScore>,
+}
+
+impl MatchState {
+    pub fn new(mode: MatchMode) -> Self {
+        Self {
+            mode,
+            phase: MatchPhase::Lobby,
+            time_left: None,
+            scores: HashMap::new(),
+        }
+    }
+
+    /// Whether players would see any difference between the two, clocks are only shown
+    /// to the second so one ticking down doesn't count until the second changes
+    pub fn looks_same(&self, other: &MatchState) -> bool {
+        self.with_whole_seconds() == other.with_whole_seconds()
+    }
+
+    fn with_whole_seconds(&self) -> MatchState {
+        let mut state = self.clone();
+
+        state.time_left = state.time_left.map(f32::ceil);
+        state.phase = match state.phase {
+            MatchPhase::Countdown { seconds_left } => MatchPhase::Countdown { seconds_left: seconds_left.ceil() },
+            MatchPhase::Finished { seconds_left } => MatchPhase::Finished { seconds_left: seconds_left.ceil() },
+            phase => phase,
+        };
+
+        state
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.phase, MatchPhase::InProgress | MatchPhase::SuddenDeath)
+    }
+
+    /// Whether a player can be given a spawn point, the countdown is when everyone gets placed
+    pub fn can_spawn(&self, client_id: u64) -> bool {
+        match self.phase {
+            MatchPhase::Countdown { .. } => true,
+            _ => self.can_play(client_id),
+        }
+    }
+
+    /// Whether a player is allowed to move and respawn right now
+    pub fn can_play(&self, client_id: u64) -> bool {
+        match self.phase {
+            MatchPhase::Lobby => true,
+            MatchPhase::Countdown { .. } | MatchPhase::Finished { .. } => false,
+            MatchPhase::InProgress | MatchPhase::SuddenDeath => self
+                .scores
+                .get(&client_id)
+                .is_some_and(|score| !score.is_eliminated),
+        }
+    }
+}
+
+/// Sent once when a match ends
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct MatchResults {
+    pub mode: MatchMode,
+    /// Best first
+    pub standings: Vec<(u64, PlayerScore)>,
+    pub winner: Option<u64>,
+}
@@ -0,0 +1,47 @@
+use crate::{
+    animation::{AnimationType, CharacterType, PlayerAnimationState},
+    hit::HitProperties,
+};
+
+pub mod fighter;
+pub mod samurai;
+pub mod shinobi;
+
+/// A fresh copy of one of a character's animations, everything that plays them builds them from here
+pub fn get_animation(character_type: &CharacterType, anim_type: &AnimationType) -> PlayerAnimationState {
+    match character_type {
+        CharacterType::Fighter => fighter::get_animation(anim_type),
+        CharacterType::Shinobi => shinobi::get_animation(anim_type),
+        CharacterType::Samurai => samurai::get_animation(anim_type),
+    }
+}
+
+/// The hit a fighter has out on `sprite_frame` of an animation, which is how the server
+/// works out what a reported hit does without taking the client's word for it
+pub fn get_hit_at(character_type: &CharacterType, anim_type: &AnimationType, sprite_frame: usize) -> Option<HitProperties> {
+    let mut animation = get_animation(character_type, anim_type);
+    animation.seek(sprite_frame);
+    animation.get_active_hit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits_only_come_out_on_active_frames() {
+        // the fighter's first attack winds up for two frames, hits on the third and recovers on the fourth
+        let hits: Vec<bool> = (0..4)
+            .map(|sprite_frame| get_hit_at(&CharacterType::Fighter, &AnimationType::Attack1, sprite_frame).is_some())
+            .collect();
+
+        assert_eq!(hits, vec![false, false, true, false]);
+    }
+
+    #[test]
+    fn hits_match_the_definitions() {
+        let hit = get_hit_at(&CharacterType::Samurai, &AnimationType::Attack3, 2);
+        assert_eq!(hit.map(|hit| hit.damage), Some(15));
+        assert_eq!(get_hit_at(&CharacterType::Shinobi, &AnimationType::Idle, 0), None);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    animation::{AnimationSequence, AnimationType, CharacterType, PlayerAnimationState},
+    animation_events::AnimationEvent,
+    hit::HitProperties,
+    sound::SoundEvent,
+};
+
+pub fn get_animation(anim_type: &AnimationType) -> PlayerAnimationState {
+    let (animation_sequence, always_plays) = match anim_type {
+        AnimationType::Idle => (vec![AnimationSequence::new(6, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)], true),
+        AnimationType::Crouch => (vec![AnimationSequence::new(1, 20.0, 0.0, 0.0, 0.0, 0.0, 60, 28)], false),
+        AnimationType::ForwardRun => (
+            vec![
+                AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_sound(2, SoundEvent::Footstep)
+                    .with_sound(6, SoundEvent::Footstep)
+                    .with_event(2, AnimationEvent::SpawnEffect("run_dust"))
+                    .with_event(6, AnimationEvent::SpawnEffect("run_dust")),
+            ],
+            true,
+        ),
+        AnimationType::ReverseRun => (
+            vec![
+                AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_sound(2, SoundEvent::Footstep)
+                    .with_sound(6, SoundEvent::Footstep)
+                    .with_event(2, AnimationEvent::SpawnEffect("run_dust"))
+                    .with_event(6, AnimationEvent::SpawnEffect("run_dust")),
+            ],
+            true,
+        ),
+        AnimationType::Jump => (
+            vec![
+                AnimationSequence::new(1, 20.0, 0.0, 0.0, 0.0, 500.0, 93, 28),
+                AnimationSequence::new(2, 20.0, 0.0, 0.0, 0.0, 0.0, 70, 28),
+                AnimationSequence::new(4, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
+            ],
+            false,
+        ),
+        AnimationType::JumpMoving => (
+            vec![
+                AnimationSequence::new(1, 20.0, 0.0, 0.0, 0.0, 500.0, 93, 28),
+                AnimationSequence::new(2, 20.0, 0.0, 0.0, 0.0, 0.0, 70, 28),
+                AnimationSequence::new(5, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
+            ],
+            false,
+        ),
+        AnimationType::Landing => (
+            vec![
+                AnimationSequence::new(2, 10.0, 0.0, 0.0, 0.0, 0.0, 70, 28)
+                    .with_sound(0, SoundEvent::Landing)
+                    .with_event(0, AnimationEvent::SpawnEffect("landing_dust")),
+            ],
+            false,
+        ),
+        AnimationType::ForwardWalk => (
+            vec![
+                AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_sound(1, SoundEvent::Footstep)
+                    .with_sound(5, SoundEvent::Footstep),
+            ],
+            true,
+        ),
+        AnimationType::ReverseWalk => (
+            vec![
+                AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_sound(1, SoundEvent::Footstep)
+                    .with_sound(5, SoundEvent::Footstep),
+            ],
+            true,
+        ),
+        AnimationType::Attack1 => (
+            vec![
+                AnimationSequence::new(2, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
+                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_hit(HitProperties::new(8, (250.0, 150.0), 0.25, 0.08, 40.0))
+                    .with_sound(0, SoundEvent::Whoosh)
+                    .with_event(0, AnimationEvent::SpawnEffect("slash_trail")),
+                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    // recovery, the next attack in the chain can cut it short
+                    .with_event(0, AnimationEvent::CancelWindowOpen),
+            ],
+            false,
+        ),
+        AnimationType::Attack2 => (
+            vec![
+                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
+                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_hit(HitProperties::new(12, (350.0, 200.0), 0.35, 0.1, 45.0))
+                    .with_sound(0, SoundEvent::Whoosh)
+                    .with_event(0, AnimationEvent::SpawnEffect("slash_trail")),
+                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    // recovery, the next attack in the chain can cut it short
+                    .with_event(0, AnimationEvent::CancelWindowOpen),
+            ],
+            false,
+        ),
+        AnimationType::Attack3 => (
+            vec![
+                AnimationSequence::new(2, 8.0, 75.0, 0.0, 0.0, 0.0, 93, 28),
+                AnimationSequence::new(1, 8.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_hit(HitProperties::new(15, (500.0, 300.0), 0.45, 0.12, 50.0))
+                    .with_sound(0, SoundEvent::Whoosh)
+                    .with_event(0, AnimationEvent::SpawnEffect("slash_trail")),
+                AnimationSequence::new(1, 8.0, 50.0, 0.0, 0.0, 0.0, 93, 28),
+            ],
+            false,
+        ),
+        AnimationType::SoaringKick => (
+            vec![
+                AnimationSequence::new(2, 8.0, 0.0, 0.0, 1250.0, -200.0, 93, 28),
+                AnimationSequence::new(2, 8.0, 0.0, 0.0, 0.0, 0.0, 93, 28)
+                    .with_hit(HitProperties::new(10, (450.0, -150.0), 0.4, 0.1, 35.0))
+                    .with_sound(0, SoundEvent::Whoosh)
+                    .with_event(0, AnimationEvent::SpawnEffect("slash_trail"))
+                    // touching down mid-kick lands straight away
+                    .with_event(0, AnimationEvent::CancelWindowOpen),
+            ],
+            false,
+        ),
+    };
+
+    PlayerAnimationState::new(anim_type.clone(), CharacterType::Samurai, animation_sequence, always_plays)
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use bincode::{Decode, Encode};
+
+use crate::{
+    match_rules::{MatchMode, MatchResults},
+    types::ServerClient,
+};
+
+// bumped whenever the file layout or anything inside `ServerClient` changes
+pub const REPLAY_VERSION: u32 = 1;
+pub const REPLAY_EXTENSION: &str = "replay";
+// loaded replays keep everyone's full state this often and rebuild the ticks in between from the deltas
+const KEYFRAME_INTERVAL: usize = 60;
+
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct ReplayHeader {
+    /// First in the file, so it can be read even when the rest is in a layout we don't know
+    pub version: u32,
+    /// Clients look the map up by name, same as in the lobby
+    pub map: String,
+    pub mode: MatchMode,
+    /// Time between two recorded ticks
+    pub tick_seconds: f32,
+}
+
+/// One tick of a match, players whose state didn't change since the last tick are left out
+#[derive(Encode, Decode, Clone, Debug)]
+struct ReplayDelta {
+    changed: Vec<(u64, ServerClient)>,
+    left: Vec<u64>,
+}
+
+/// The header is read on its own when loading, before the rest, so it always goes first
+#[derive(Encode, Debug)]
+struct ReplayFile {
+    header: ReplayHeader,
+    ticks: Vec<ReplayDelta>,
+    results: Option<MatchResults>,
+}
+
+/// Collects the server's view of everyone each tick while a match runs
+pub struct ReplayRecorder {
+    header: ReplayHeader,
+    ticks: Vec<ReplayDelta>,
+    last_clients: HashMap<u64, ServerClient>,
+}
+
+impl ReplayRecorder {
+    pub fn new(map: String, mode: MatchMode, tick_seconds: f32) -> Self {
+        Self {
+            header: ReplayHeader {
+                version: REPLAY_VERSION,
+                map,
+                mode,
+                tick_seconds,
+            },
+            ticks: vec![],
+            last_clients: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, clients: &HashMap<u64, ServerClient>) {
+        let changed = clients
+            .iter()
+            .filter(|(client_id, sc)| self.last_clients.get(client_id) != Some(sc))
+            .map(|(client_id, sc)| (*client_id, sc.clone()))
+            .collect();
+
+        let left = self
+            .last_clients
+            .keys()
+            .filter(|client_id| !clients.contains_key(client_id))
+            .copied()
+            .collect();
+
+        self.ticks.push(ReplayDelta { changed, left });
+        self.last_clients = clients.clone();
+    }
+
+    /// Writes the whole match out, `results` is None if the match never finished
+    pub fn save(self, path: &std::path::Path, results: Option<MatchResults>) -> Result<(), String> {
+        let file = ReplayFile {
+            header: self.header,
+            ticks: self.ticks,
+            results,
+        };
+
+        let encoded = bincode::encode_to_vec(&file, bincode::config::standard())
+            .map_err(|e| format!("Couldn't encode the replay: {:?}", e))?;
+
+        std::fs::write(path, encoded).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+    }
+}
+
+/// A recorded match, with everyone's full state kept every `KEYFRAME_INTERVAL` ticks and the deltas in between
+pub struct Replay {
+    header: ReplayHeader,
+    ticks: Vec<ReplayDelta>,
+    /// Everyone on ticks 0, `KEYFRAME_INTERVAL`, twice that and so on
+    keyframes: Vec<HashMap<u64, ServerClient>>,
+    results: Option<MatchResults>,
+}
+
+impl Replay {
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        let config = bincode::config::standard();
+
+        // the version decides how the rest is laid out, so it's checked before we try to read any of it
+        let (header, header_len): (ReplayHeader, usize) = bincode::decode_from_slice(&bytes, config)
+            .map_err(|e| format!("{} isn't a replay: {:?}", path.display(), e))?;
+
+        if header.version != REPLAY_VERSION {
+            return Err(format!("{} is replay version {}, we can only play {}", path.display(), header.version, REPLAY_VERSION));
+        }
+
+        let ((ticks, results), _): ((Vec<ReplayDelta>, Option<MatchResults>), usize) = bincode::decode_from_slice(&bytes[header_len..], config)
+            .map_err(|e| format!("{} is damaged: {:?}", path.display(), e))?;
+
+        let mut clients: HashMap<u64, ServerClient> = HashMap::new();
+        let mut keyframes = vec![];
+
+        for (tick, delta) in ticks.iter().enumerate() {
+            apply_delta(&mut clients, delta);
+
+            if tick % KEYFRAME_INTERVAL == 0 {
+                keyframes.push(clients.clone());
+            }
+        }
+
+        Ok(Self {
+            header,
+            ticks,
+            keyframes,
+            results,
+        })
+    }
+
+    pub fn get_header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    pub fn get_results(&self) -> Option<&MatchResults> {
+        self.results.as_ref()
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.ticks.len()
+    }
+
+    /// Everyone's state on tick `frame`, clamped to the end of the replay.
+    /// Rebuilt from the keyframe before it, so it's never more than `KEYFRAME_INTERVAL` deltas of work
+    pub fn get_frame(&self, frame: usize) -> Option<HashMap<u64, ServerClient>> {
+        let frame = frame.min(self.ticks.len().checked_sub(1)?);
+        let keyframe = frame / KEYFRAME_INTERVAL;
+
+        let mut clients = self.keyframes.get(keyframe)?.clone();
+        for delta in &self.ticks[keyframe * KEYFRAME_INTERVAL + 1..=frame] {
+            apply_delta(&mut clients, delta);
+        }
+
+        Some(clients)
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.ticks.len() as f32 * self.header.tick_seconds
+    }
+}
+
+fn apply_delta(clients: &mut HashMap<u64, ServerClient>, delta: &ReplayDelta) {
+    for client_id in &delta.left {
+        clients.remove(client_id);
+    }
+
+    clients.extend(delta.changed.iter().map(|(client_id, sc)| (*client_id, sc.clone())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.{}", name, std::process::id(), REPLAY_EXTENSION))
+    }
+
+    fn get_client(x_pos: f32) -> ServerClient {
+        ServerClient { x_pos, ..ServerClient::default() }
+    }
+
+    #[test]
+    fn frames_come_back_the_same_on_either_side_of_a_keyframe() {
+        let mut recorder = ReplayRecorder::new("First Map".to_string(), MatchMode::default(), 0.016);
+        let mut recorded = vec![];
+
+        // someone moving every tick, someone standing still and someone leaving halfway
+        for tick in 0..KEYFRAME_INTERVAL * 3 {
+            let mut clients = HashMap::from([(1, get_client(tick as f32)), (2, get_client(5.0))]);
+            if tick < KEYFRAME_INTERVAL * 2 - 7 {
+                clients.insert(3, get_client(-(tick as f32)));
+            }
+
+            recorder.record(&clients);
+            recorded.push(clients);
+        }
+
+        let path = get_path("keyframes");
+        recorder.save(&path, None).unwrap();
+        let replay = Replay::load(&path);
+        std::fs::remove_file(&path).ok();
+        let replay = replay.unwrap();
+
+        assert_eq!(replay.get_frame_count(), recorded.len());
+        for (frame, clients) in recorded.iter().enumerate() {
+            assert_eq!(replay.get_frame(frame).as_ref(), Some(clients), "frame {}", frame);
+        }
+        assert_eq!(replay.get_frame(recorded.len() + 10).as_ref(), recorded.last());
+    }
+
+    #[test]
+    fn other_versions_are_turned_away_by_their_version() {
+        let header = ReplayHeader {
+            version: REPLAY_VERSION + 1,
+            map: "First Map".to_string(),
+            mode: MatchMode::default(),
+            tick_seconds: 0.016,
+        };
+
+        // a newer layout we can't make sense of after the header
+        let mut bytes = bincode::encode_to_vec(&header, bincode::config::standard()).unwrap();
+        bytes.extend([0xff; 16]);
+
+        let path = get_path("version");
+        std::fs::write(&path, bytes).unwrap();
+        let replay = Replay::load(&path);
+        std::fs::remove_file(&path).ok();
+
+        let error = replay.err().unwrap();
+        assert!(error.contains(&format!("replay version {}", REPLAY_VERSION + 1)), "{}", error);
+    }
+}
@@ -0,0 +1,19 @@
+// the game simulates at a fixed rate no matter how fast it renders
+pub const SIM_TICK_RATE: u32 = 60;
+pub const SIM_DT: f32 = 1.0 / SIM_TICK_RATE as f32;
+
+/// Positions are tracked in whole pixels plus a remainder in 1/SUBPIXELS of a pixel
+pub const SUBPIXELS: i32 = 256;
+
+/// Adds `delta` pixels to a sub-pixel remainder.
+///
+/// Returns the whole pixels to move now and what's left over, both always come out the same for the same inputs.
+pub fn accumulate_subpixels(remainder: i32, delta: f32) -> (i32, i32) {
+    let total = remainder + (delta * SUBPIXELS as f32).round() as i32;
+    (total / SUBPIXELS, total % SUBPIXELS)
+}
+
+/// Animation frames are counted in simulation ticks, a frame at `fps` lasts this many
+pub fn ticks_per_frame(fps: f32) -> u32 {
+    (SIM_TICK_RATE as f32 / fps).round().max(1.0) as u32
+}
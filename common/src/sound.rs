@@ -0,0 +1,13 @@
+/// Something a fighter does that makes a noise.
+///
+/// Animation sequences declare these on their frames, so anyone who knows a player's animation and sprite
+/// frame can play them, networked players included
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    Footstep,
+    /// An attack swinging through the air
+    Whoosh,
+    Landing,
+    /// Taking a hit, this one comes from health going down rather than an animation
+    Hit,
+}
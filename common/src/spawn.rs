@@ -0,0 +1,69 @@
+use nanoserde::DeJson;
+
+use crate::constants::{DEFAULT_PLAYER_HEIGHT, DEFAULT_PLAYER_WIDTH};
+
+/// A position an actor can be placed at, top left corner in world pixels
+pub type SpawnPoint = (f32, f32);
+
+// object layer in the tiled map that holds the spawn points
+const SPAWN_LAYER: &str = "Spawns";
+/// Where players go if a map doesn't have any spawn points
+pub const FALLBACK_SPAWN_POINT: SpawnPoint = (300.0, 50.0);
+
+/// Just the parts of a tiled map the spawn points are in
+#[derive(DeJson)]
+struct TiledMap {
+    layers: Vec<TiledLayer>,
+}
+
+#[derive(DeJson)]
+struct TiledLayer {
+    name: String,
+    /// Only object layers have these
+    #[nserde(default)]
+    objects: Vec<TiledObject>,
+}
+
+#[derive(DeJson)]
+struct TiledObject {
+    x: f32,
+    y: f32,
+}
+
+/// The points in a tiled map's "Spawns" layer, which mark where a fighter's feet go.
+///
+/// Actors are positioned from the top left, so they're moved up and left by the default player size.
+pub fn parse_spawn_points(tiled_json: &str) -> Result<Vec<SpawnPoint>, String> {
+    let map = TiledMap::deserialize_json(tiled_json).map_err(|e| format!("{}", e))?;
+
+    let spawn_points = map
+        .layers
+        .iter()
+        .filter(|layer| layer.name == SPAWN_LAYER)
+        .flat_map(|layer| &layer.objects)
+        .map(|o| (o.x - DEFAULT_PLAYER_WIDTH as f32 / 2.0, o.y - DEFAULT_PLAYER_HEIGHT as f32))
+        .collect();
+
+    Ok(spawn_points)
+}
+
+/// Picks the spawn point furthest away from every occupied position.
+///
+/// This is shared so the server and single player pick spawns the same way.
+pub fn choose_spawn_point(spawn_points: &[SpawnPoint], occupied: &[SpawnPoint]) -> Option<SpawnPoint> {
+    let mut best: Option<(SpawnPoint, f32)> = None;
+
+    for spawn in spawn_points {
+        // distance to the closest player, nobody around means it's wide open
+        let closest = occupied
+            .iter()
+            .map(|(x, y)| (spawn.0 - x).powi(2) + (spawn.1 - y).powi(2))
+            .fold(f32::MAX, f32::min);
+
+        if best.is_none_or(|(_, best_distance)| closest > best_distance) {
+            best = Some((*spawn, closest));
+        }
+    }
+
+    best.map(|(spawn, _)| spawn)
+}
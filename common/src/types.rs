@@ -1,6 +1,13 @@
 use std::collections::HashMap;
 
-use crate::{animation::{AnimationType, CharacterType, Facing}, constants::{DEFAULT_PLAYER_HEIGHT, DEFAULT_PLAYER_WIDTH}};
+use crate::{
+    animation::{AnimationType, CharacterType, Facing},
+    constants::{DEFAULT_PLAYER_HEIGHT, DEFAULT_PLAYER_WIDTH, MAX_HEALTH, MAX_PLAYER_NAME_LENGTH},
+    hit::HitProperties,
+    lobby::LobbyState,
+    match_rules::{MatchMode, MatchResults, MatchState},
+    spawn::SpawnPoint,
+};
 use bincode::{Decode, Encode};
 
 /// This is what gets sent to the server AND the client.
@@ -14,11 +21,58 @@ use bincode::{Decode, Encode};
 /// The server then sends ClientServerEvent to all clients.
 #[derive(Encode, Decode, PartialEq, Debug)]
 pub enum ClientEventType {
-    ClientCharacterUpdate(HashMap<u64, ServerClient>),
+    /// Everyone's state as of server tick `tick`.
+    ///
+    /// `tick_ms` is how long the server worked on the tick before this one, its sleep between ticks left out
+    ClientCharacterUpdate { tick: u64, tick_ms: f32, clients: HashMap<u64, ServerClient> },
+    /// Where the server decided this client should (re)spawn
+    SpawnAssigned(SpawnPoint),
+    MatchStateUpdate(MatchState),
+    /// Sent once when a match ends
+    MatchFinished(MatchResults),
+    LobbyUpdate(LobbyState),
+    /// Someone hit this client, `is_fatal` means it took the last of their health
+    Hit {
+        attacker: u64,
+        hit: HitProperties,
+        attacker_facing: Facing,
+        is_fatal: bool,
+    },
+    /// Someone connected, or came back after dropping
+    PlayerJoined { client_id: u64, name: String },
+    /// Someone disconnected, their character should go away
+    PlayerLeft { client_id: u64, name: String },
+    /// Draws the named effect at a point in the world, for things only the server sees happen
+    Effect { name: String, position: (f32, f32) },
+}
+
+/// This is what clients send to the server.
+#[derive(Encode, Decode, PartialEq, Debug)]
+pub enum ServerEventType {
+    ClientCharacterUpdate(ServerClient),
+    /// Asks the server to pick one of the current map's spawn points that isn't occupied
+    RequestSpawn,
+    /// This client fell off the map or ran out of health
+    Died,
+    /// Picks the rules for the next match, only the lobby's host can and only between matches
+    SelectMatchMode(MatchMode),
+    LobbySelectCharacter(CharacterType),
+    /// Index into the lobby's map rotation
+    LobbyVoteMap(usize),
+    LobbySetReady(bool),
+    /// One of our attacks connected with `victim` on our screen.
+    ///
+    /// `view_tick` is the server tick we were showing `victim` at, the server rewinds them to it to check the hit.
+    /// What the hit does comes from the animation the server last saw us in, not from us.
+    Hit { victim: u64, view_tick: u64 },
 }
 
 #[derive(Encode, Decode, PartialEq, Debug, Clone)]
 pub struct ServerClient {
+    /// Set by the server from the connect handshake, clients can't change it
+    pub name: String,
+    pub health: i32,
+
     pub x_pos: f32,
     pub y_pos: f32,
 
@@ -40,11 +94,16 @@ pub struct ServerClient {
 
     /// Frame is the current frame of the spritesheetNOT the animation frame
     pub sprite_frame: usize,
+
+    /// Just respawned and can't be hurt yet
+    pub is_invulnerable: bool,
 }
 
 impl Default for ServerClient {
     fn default() -> Self {
         Self {
+            name: String::new(),
+            health: MAX_HEALTH,
             x_pos: 0.0,
             y_pos: 0.0,
             prev_x_pos: 0.0,
@@ -55,6 +114,59 @@ impl Default for ServerClient {
             anim_type: AnimationType::Idle,
             character_type: CharacterType::Fighter,
             sprite_frame: 0,
+            is_invulnerable: false,
         }
     }
 }
+
+/// Size of the netcode connect token user data, matches renet_netcode's NETCODE_USER_DATA_BYTES
+pub const USER_DATA_BYTES: usize = 256;
+
+// the last byte of the user data says how we're joining, the name can't reach it
+const JOIN_TYPE_BYTE: usize = USER_DATA_BYTES - 1;
+
+/// Whether a connection gets a character or only watches
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JoinType {
+    Player,
+    /// Gets every update but has no character and doesn't take a player slot
+    Spectator,
+}
+
+/// Packs a player name and how they're joining into the user data sent with the connect handshake.
+///
+/// Names longer than the user data get cut off at the last whole character that fits.
+pub fn player_name_to_user_data(name: &str, join_type: JoinType) -> [u8; USER_DATA_BYTES] {
+    let mut user_data = [0u8; USER_DATA_BYTES];
+
+    user_data[JOIN_TYPE_BYTE] = match join_type {
+        JoinType::Player => 0,
+        JoinType::Spectator => 1,
+    };
+
+    let mut len = name.len().min(JOIN_TYPE_BYTE);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    user_data[..len].copy_from_slice(&name.as_bytes()[..len]);
+    user_data
+}
+
+/// Reads the name back out of the handshake user data, trimmed to MAX_PLAYER_NAME_LENGTH characters
+pub fn player_name_from_user_data(user_data: &[u8; USER_DATA_BYTES]) -> String {
+    let len = user_data[..JOIN_TYPE_BYTE].iter().position(|b| *b == 0).unwrap_or(JOIN_TYPE_BYTE);
+    String::from_utf8_lossy(&user_data[..len])
+        .trim()
+        .chars()
+        .take(MAX_PLAYER_NAME_LENGTH)
+        .collect()
+}
+
+/// Anything we don't recognize joins as a player
+pub fn join_type_from_user_data(user_data: &[u8; USER_DATA_BYTES]) -> JoinType {
+    match user_data[JOIN_TYPE_BYTE] {
+        1 => JoinType::Spectator,
+        _ => JoinType::Player,
+    }
+}
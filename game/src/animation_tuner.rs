@@ -0,0 +1,333 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use macroquad::{
+    color::{Color, GRAY, RED, WHITE, YELLOW},
+    input::{is_key_down, is_key_pressed, KeyCode},
+    math::{vec2, Rect, Vec2},
+    shapes::{draw_line, draw_rectangle, draw_rectangle_lines},
+    text::draw_text,
+    ui::{hash, root_ui, widgets},
+    window::{screen_height, screen_width},
+};
+
+use common::{
+    animation::{AnimationType, CharacterType, Facing, PlayerAnimationState},
+    constants::{WINDOW_HEIGHT, WINDOW_WIDTH},
+    movesets,
+};
+
+use crate::characters::tuning::{get_anim_type_name, save_moveset};
+
+const CHARACTERS: [CharacterType; 3] = [CharacterType::Fighter, CharacterType::Shinobi, CharacterType::Samurai];
+// where the fighter's feet rest on the preview stage, in world pixels
+const STAGE_FLOOR: f32 = WINDOW_HEIGHT as f32 * 0.6;
+// the preview is zoomed in this much so single pixels of the collider can be seen
+const STAGE_ZOOM: f32 = 2.5;
+// stepping a frame gives up after this many ticks, in case the animation isn't playing
+const MAX_STEP_TICKS: u32 = 1000;
+// acceleration is drawn as an arrow this many pixels long per pixel a second squared
+const ACCELERATION_ARROW_SCALE: f32 = 0.05;
+const HITBOX_COLOR: Color = Color::new(1.0, 0.5, 0.0, 0.4);
+const RECENT_EVENT_COUNT: usize = 6;
+
+/// Plays any character's animation on a loop with its collider, hitbox and movement drawn over it,
+/// and edits the numbers of its sequences live
+pub struct AnimationTuner {
+    /// Every character's animations in `AnimationType::ALL` order, the characters in `CHARACTERS` order
+    characters: Vec<Vec<Rc<RefCell<PlayerAnimationState>>>>,
+    character_index: usize,
+    anim_index: usize,
+    /// The sequence the sliders edit
+    edit_sequence: usize,
+    facing: Facing,
+    is_paused: bool,
+    /// How far the animation's movement carried the fighter this time through, it's put back when it loops
+    offset: Vec2,
+    /// Velocity the animation added this time through, nothing moves with it since there's no physics here
+    added_velocity: Vec2,
+    /// The newest fired events, newest last
+    recent_events: Vec<String>,
+    /// What saving said, shown until the next save
+    status: Option<String>,
+    /// Where the moveset sources saving writes to are
+    movesets_dir: PathBuf,
+}
+
+impl AnimationTuner {
+    pub fn new(movesets_dir: PathBuf) -> Self {
+        // copies of the definitions, nothing the tuner changes reaches gameplay until it's saved and rebuilt
+        let characters = CHARACTERS
+            .iter()
+            .map(|character_type| {
+                AnimationType::ALL
+                    .iter()
+                    .map(|anim_type| Rc::new(RefCell::new(movesets::get_animation(character_type, anim_type))))
+                    .collect()
+            })
+            .collect();
+
+        let mut tuner = Self {
+            characters,
+            character_index: 0,
+            anim_index: 0,
+            edit_sequence: 0,
+            facing: Facing::Right,
+            is_paused: false,
+            offset: Vec2::ZERO,
+            added_velocity: Vec2::ZERO,
+            recent_events: vec![],
+            status: None,
+            movesets_dir,
+        };
+
+        tuner.restart();
+        tuner
+    }
+
+    /// Tab switches character, left/right the animation, up/down the sequence being edited,
+    /// space pauses, period steps a frame, f flips, r restarts and ctrl+s saves
+    pub fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::Tab) {
+            self.character_index = (self.character_index + 1) % CHARACTERS.len();
+            self.restart();
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.anim_index = (self.anim_index + 1) % AnimationType::ALL.len();
+            self.restart();
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.anim_index = (self.anim_index + AnimationType::ALL.len() - 1) % AnimationType::ALL.len();
+            self.restart();
+        }
+
+        let sequence_count = self.get_state().borrow().animation_sequence.len();
+
+        if is_key_pressed(KeyCode::Down) {
+            self.edit_sequence = (self.edit_sequence + 1).min(sequence_count - 1);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.edit_sequence = self.edit_sequence.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::Space) {
+            self.is_paused = !self.is_paused;
+        }
+        if is_key_pressed(KeyCode::Period) && self.is_paused {
+            self.step_frame();
+        }
+        if is_key_pressed(KeyCode::F) {
+            self.facing = if self.facing == Facing::Right { Facing::Left } else { Facing::Right };
+        }
+        if is_key_pressed(KeyCode::R) {
+            self.restart();
+        }
+
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if ctrl && is_key_pressed(KeyCode::S) {
+            self.save();
+        }
+    }
+
+    /// Advances the animation by one simulation tick, looping it once it's done
+    pub fn update(&mut self) {
+        if !self.is_paused {
+            self.tick();
+        }
+    }
+
+    fn tick(&mut self) {
+        let state = self.get_state();
+        let deltas = state.borrow_mut().update();
+        let direction = if self.facing == Facing::Left { -1.0 } else { 1.0 };
+
+        self.offset += vec2(deltas.pos_delta.0 * direction, deltas.pos_delta.1);
+        self.added_velocity += vec2(deltas.vel_delta.0 * direction, deltas.vel_delta.1);
+
+        for event in deltas.events {
+            self.recent_events.push(format!("{:?}", event));
+        }
+
+        let excess = self.recent_events.len().saturating_sub(RECENT_EVENT_COUNT);
+        self.recent_events.drain(..excess);
+
+        let is_finished = {
+            let state = state.borrow();
+            !state.actively_playing && !state.always_plays
+        };
+
+        if is_finished {
+            self.restart();
+        }
+    }
+
+    /// Ticks until the sprite frame changes
+    fn step_frame(&mut self) {
+        let sprite_frame = self.get_state().borrow().sprite_frame;
+
+        for _ in 0..MAX_STEP_TICKS {
+            self.tick();
+
+            if self.get_state().borrow().sprite_frame != sprite_frame {
+                return;
+            }
+        }
+    }
+
+    fn restart(&mut self) {
+        let state = self.get_state();
+        let mut state = state.borrow_mut();
+        state.reset();
+        state.actively_playing = !state.always_plays;
+
+        self.edit_sequence = self.edit_sequence.min(state.animation_sequence.len() - 1);
+        self.offset = Vec2::ZERO;
+        self.added_velocity = Vec2::ZERO;
+    }
+
+    fn save(&mut self) {
+        let character_type = self.get_character_type();
+
+        self.status = Some(match save_moveset(&self.movesets_dir, &character_type, &self.characters[self.character_index]) {
+            Ok(path) => format!("Saved {}, rebuild to play with it", path),
+            Err(e) => e,
+        });
+    }
+
+    fn get_state(&self) -> Rc<RefCell<PlayerAnimationState>> {
+        Rc::clone(&self.characters[self.character_index][self.anim_index])
+    }
+
+    pub fn get_character_type(&self) -> CharacterType {
+        CHARACTERS[self.character_index].clone()
+    }
+
+    pub fn get_anim_type(&self) -> AnimationType {
+        AnimationType::ALL[self.anim_index].clone()
+    }
+
+    pub fn get_facing(&self) -> Facing {
+        self.facing.clone()
+    }
+
+    pub fn get_sprite_frame(&self) -> usize {
+        self.get_state().borrow().sprite_frame
+    }
+
+    /// The collider's position and size, standing on the stage's floor and carried along by the animation
+    pub fn get_collider(&self) -> (Vec2, (i32, i32)) {
+        let state = self.get_state();
+        let state = state.borrow();
+        let (width, height) = state
+            .animation_sequence
+            .get(state.sequence_index)
+            .map_or((0, 0), |sequence| (sequence.width, sequence.height));
+
+        let feet = vec2(WINDOW_WIDTH as f32 / 2.0, STAGE_FLOOR) + self.offset;
+        (vec2(feet.x - width as f32 / 2.0, feet.y - height as f32), (width, height))
+    }
+
+    /// The part of the world the preview camera shows
+    pub fn get_stage_view(&self) -> Rect {
+        let size = vec2(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32) / STAGE_ZOOM;
+        let center = vec2(WINDOW_WIDTH as f32 / 2.0, STAGE_FLOOR - size.y * 0.3);
+        Rect::new(center.x - size.x / 2.0, center.y - size.y / 2.0, size.x, size.y)
+    }
+
+    /// Floor, collider, hitbox and acceleration, in world coordinates on top of the sprite
+    pub fn draw_overlays(&self) {
+        let view = self.get_stage_view();
+        draw_line(view.left(), STAGE_FLOOR, view.right(), STAGE_FLOOR, 1.0, GRAY);
+
+        let (position, size) = self.get_collider();
+        let state = self.get_state();
+        let state = state.borrow();
+
+        if let Some(hit) = state.get_active_hit() {
+            let hitbox = hit.get_hitbox(position, size, &self.facing);
+            draw_rectangle(hitbox.x, hitbox.y, hitbox.w, hitbox.h, HITBOX_COLOR);
+        }
+
+        draw_rectangle_lines(position.x, position.y, size.0 as f32, size.1 as f32, 1.0, RED);
+
+        if let Some(sequence) = state.animation_sequence.get(state.sequence_index) {
+            let direction = if self.facing == Facing::Left { -1.0 } else { 1.0 };
+            let center = position + vec2(size.0 as f32, size.1 as f32) / 2.0;
+            // y acceleration is up like `y_v`
+            let arrow = vec2(sequence.x_accleration * direction, -sequence.y_accleration) * ACCELERATION_ARROW_SCALE;
+
+            if arrow != Vec2::ZERO {
+                draw_line(center.x, center.y, center.x + arrow.x, center.y + arrow.y, 1.0, YELLOW);
+            }
+        }
+    }
+
+    /// The readout and the sliders for the sequence being edited, in screen space
+    pub fn draw_ui(&mut self) {
+        let state = self.get_state();
+
+        let readout = {
+            let state = state.borrow();
+            let sequence_count = state.animation_sequence.len();
+            let frames = state.animation_sequence.get(state.sequence_index).map_or(0, |sequence| sequence.frames);
+
+            vec![
+                format!("{:?} - {}", self.get_character_type(), get_anim_type_name(&state.anim_type)),
+                format!("sequence {} / {}   frame {} / {}   sprite frame {}   tick {}", state.sequence_index + 1, sequence_count, state.sequence_frame_index + 1, frames, state.sprite_frame, state.ticks),
+                format!("moved ({:.1}, {:.1})   velocity added ({:.1}, {:.1})", self.offset.x, self.offset.y, self.added_velocity.x, self.added_velocity.y),
+                format!("hitbox {}   invulnerable {}   can cancel {}", state.get_active_hit().is_some(), state.is_invulnerable(), state.can_cancel()),
+                format!("events: {}", self.recent_events.join(", ")),
+            ]
+        };
+
+        for (i, line) in readout.iter().enumerate() {
+            draw_text(line, 16.0, 30.0 + i as f32 * 22.0, 20.0, WHITE);
+        }
+
+        let paused = if self.is_paused { "   paused" } else { "" };
+        draw_text(
+            &format!("tab character   left/right animation   up/down sequence   space pause   . step   f flip   r restart   ctrl+s save{}", paused),
+            16.0,
+            screen_height() - 16.0,
+            18.0,
+            WHITE,
+        );
+
+        if let Some(status) = &self.status {
+            draw_text(status, 16.0, screen_height() - 40.0, 18.0, YELLOW);
+        }
+
+        let mut save_pressed = false;
+
+        {
+            let mut state = state.borrow_mut();
+            let sequence_count = state.animation_sequence.len();
+            let Some(sequence) = state.animation_sequence.get_mut(self.edit_sequence) else {
+                return;
+            };
+
+            let mut height = sequence.height as f32;
+            let mut width = sequence.width as f32;
+
+            widgets::Window::new(hash!(), vec2(screen_width() - 330.0, 16.0), vec2(310.0, 250.0))
+                .label(&format!("Sequence {} / {} ({} frames)", self.edit_sequence + 1, sequence_count, sequence.frames))
+                .titlebar(true)
+                .ui(&mut *root_ui(), |ui| {
+                    ui.slider(hash!(), "fps", 1.0..60.0, &mut sequence.fps);
+                    ui.slider(hash!(), "x movement", -300.0..300.0, &mut sequence.x_movement);
+                    ui.slider(hash!(), "y movement", -300.0..300.0, &mut sequence.y_movement);
+                    ui.slider(hash!(), "x acceleration", -2000.0..2000.0, &mut sequence.x_accleration);
+                    ui.slider(hash!(), "y acceleration", -2000.0..2000.0, &mut sequence.y_accleration);
+                    ui.slider(hash!(), "height", 16.0..128.0, &mut height);
+                    ui.slider(hash!(), "width", 8.0..128.0, &mut width);
+
+                    save_pressed = ui.button(None, "Save");
+                });
+
+            sequence.height = height.round() as i32;
+            sequence.width = width.round() as i32;
+        }
+
+        if save_pressed {
+            self.save();
+        }
+    }
+}
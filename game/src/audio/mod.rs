@@ -0,0 +1 @@
+pub mod sound_manager;
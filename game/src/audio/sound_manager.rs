@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use macroquad::{
+    audio::{load_sound, play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound},
+    math::Vec2,
+};
+
+use common::{animation_events::AnimationEvent, sound::SoundEvent};
+
+use crate::characters::frame_events::FighterFrameEvents;
+
+const SOUND_FILES: [(SoundEvent, &str); 4] = [
+    (SoundEvent::Footstep, "assets/sounds/footstep.wav"),
+    (SoundEvent::Whoosh, "assets/sounds/whoosh.wav"),
+    (SoundEvent::Landing, "assets/sounds/landing.wav"),
+    (SoundEvent::Hit, "assets/sounds/hit.wav"),
+];
+// fighters further than this from the middle of the screen can't be heard
+const HEARING_DISTANCE: f32 = 1400.0;
+
+/// Master, music and effects volume, 0 to 1, changed in the settings menu
+#[derive(Clone, Copy, PartialEq)]
+pub struct VolumeSettings {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+}
+
+impl VolumeSettings {
+    pub fn new() -> Self {
+        Self {
+            master: 0.8,
+            music: 0.5,
+            effects: 0.8,
+        }
+    }
+}
+
+/// Plays the sounds declared on animation frames for every fighter on screen, and the map's music
+pub struct SoundManager {
+    sounds: HashMap<SoundEvent, Sound>,
+    music: HashMap<String, Sound>,
+    playing_music: Option<String>,
+    volume: VolumeSettings,
+    /// The middle of the screen, sounds get quieter further from it
+    listener: Vec2,
+}
+
+impl SoundManager {
+    /// Missing sound files are reported and then left silent
+    pub async fn load() -> Self {
+        let mut sounds = HashMap::new();
+
+        for (event, path) in SOUND_FILES {
+            match load_sound(path).await {
+                Ok(sound) => {
+                    sounds.insert(event, sound);
+                }
+                Err(e) => eprintln!("Couldn't load {}: {:?}", path, e),
+            }
+        }
+
+        Self {
+            sounds,
+            music: HashMap::new(),
+            playing_music: None,
+            volume: VolumeSettings::new(),
+            listener: Vec2::ZERO,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: VolumeSettings) {
+        if self.volume == volume {
+            return;
+        }
+
+        self.volume = volume;
+
+        if let Some(music) = self.playing_music.as_ref().and_then(|path| self.music.get(path)) {
+            set_sound_volume(music, self.get_music_volume());
+        }
+    }
+
+    pub fn set_listener(&mut self, position: Vec2) {
+        self.listener = position;
+    }
+
+    /// Loops `path` if it isn't playing already, None stops the music
+    pub async fn play_music(&mut self, path: Option<&str>) {
+        if self.playing_music.as_deref() == path {
+            return;
+        }
+
+        if let Some(music) = self.playing_music.take().and_then(|playing| self.music.get(&playing)) {
+            stop_sound(music);
+        }
+
+        let Some(path) = path else {
+            return;
+        };
+
+        if !self.music.contains_key(path) {
+            match load_sound(path).await {
+                Ok(sound) => {
+                    self.music.insert(path.to_string(), sound);
+                }
+                Err(e) => eprintln!("Couldn't load {}: {:?}", path, e),
+            }
+        }
+
+        // it's marked as playing even if it didn't load so we don't try again every frame
+        self.playing_music = Some(path.to_string());
+
+        if let Some(music) = self.music.get(path) {
+            play_sound(music, PlaySoundParams {
+                looped: true,
+                volume: self.get_music_volume(),
+            });
+        }
+    }
+
+    /// Plays the sounds a fighter's animation reached, and a hit if they lost health
+    pub fn play_fighter_events(&self, fighter: &FighterFrameEvents) {
+        if fighter.damage_taken > 0 {
+            self.play(SoundEvent::Hit, fighter.feet);
+        }
+
+        for event in &fighter.events {
+            if let AnimationEvent::Sound(sound) = event {
+                self.play(*sound, fighter.feet);
+            }
+        }
+    }
+
+    fn play(&self, event: SoundEvent, position: Vec2) {
+        let Some(sound) = self.sounds.get(&event) else {
+            return;
+        };
+
+        let falloff = 1.0 - (position.distance(self.listener) / HEARING_DISTANCE).min(1.0);
+        let volume = self.volume.master * self.volume.effects * falloff;
+
+        if volume > 0.0 {
+            play_sound(sound, PlaySoundParams { looped: false, volume });
+        }
+    }
+
+    fn get_music_volume(&self) -> f32 {
+        self.volume.master * self.volume.music
+    }
+}
@@ -0,0 +1,136 @@
+use macroquad::{
+    camera::{set_camera, Camera2D},
+    math::{vec2, Rect, Vec2},
+    texture::RenderTarget,
+};
+
+use common::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+// how quickly the camera catches up to its target, higher is snappier
+const CAMERA_SMOOTHING: f32 = 8.0;
+// extra space kept around the fighters when framing all of them
+const FRAME_ALL_PADDING: f32 = 160.0;
+// how far the frame all camera is allowed to zoom in, relative to the window size
+const FRAME_ALL_MIN_SCALE: f32 = 0.75;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Keeps the local player in the middle of the screen
+    FollowPlayer,
+    /// Zooms out to keep every fighter on screen, smash style
+    FrameAll,
+    /// Stays wherever it's panned to, for watching replays
+    Free,
+}
+
+pub struct GameCamera {
+    pub mode: CameraMode,
+    /// Center of the view in world coordinates
+    center: Vec2,
+    /// Size of the view in world pixels, the window size means no zoom
+    view_size: Vec2,
+}
+
+impl GameCamera {
+    pub fn new(mode: CameraMode) -> Self {
+        let window_size = vec2(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32);
+
+        Self {
+            mode,
+            center: window_size / 2.0,
+            view_size: window_size,
+        }
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::FollowPlayer => CameraMode::FrameAll,
+            CameraMode::FrameAll | CameraMode::Free => CameraMode::FollowPlayer,
+        };
+    }
+
+    /// Jumps straight to the target without smoothing, used when entering a map
+    pub fn snap_to(&mut self, focus: Vec2, bounds: Rect) {
+        self.center = focus;
+        self.view_size = vec2(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32);
+        self.clamp_to_bounds(bounds);
+    }
+
+    /// `focus` is the local player and `fighters` is everyone else on the map
+    pub fn update(&mut self, dt: f32, focus: Vec2, fighters: &[Vec2], bounds: Rect) {
+        let window_size = vec2(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32);
+
+        let (target_center, target_size) = match self.mode {
+            CameraMode::FollowPlayer => (focus, window_size),
+            CameraMode::Free => (self.center, self.view_size),
+            CameraMode::FrameAll => {
+                let mut min = focus;
+                let mut max = focus;
+                for fighter in fighters {
+                    min = min.min(*fighter);
+                    max = max.max(*fighter);
+                }
+
+                let padded = (max - min) + Vec2::splat(FRAME_ALL_PADDING * 2.0);
+
+                // keep the window aspect ratio so nothing gets stretched
+                let scale = (padded.x / window_size.x)
+                    .max(padded.y / window_size.y)
+                    .max(FRAME_ALL_MIN_SCALE);
+
+                ((min + max) / 2.0, window_size * scale)
+            }
+        };
+
+        let t = (CAMERA_SMOOTHING * dt).clamp(0.0, 1.0);
+        self.center = self.center.lerp(target_center, t);
+        self.view_size = self.view_size.lerp(target_size, t);
+
+        self.clamp_to_bounds(bounds);
+    }
+
+    /// Moves a free camera by `offset` world pixels and scales the view by `zoom`, above 1 zooms out
+    pub fn pan(&mut self, offset: Vec2, zoom: f32, bounds: Rect) {
+        self.center += offset;
+        self.view_size *= zoom;
+        self.clamp_to_bounds(bounds);
+    }
+
+    /// Keeps the view inside the map, if the map is smaller than the view we center on it
+    fn clamp_to_bounds(&mut self, bounds: Rect) {
+        // never show more than the whole map
+        let fit = (bounds.w / self.view_size.x).min(bounds.h / self.view_size.y).min(1.0);
+        self.view_size *= fit;
+
+        let half = self.view_size / 2.0;
+
+        self.center.x = if self.view_size.x >= bounds.w {
+            bounds.x + bounds.w / 2.0
+        } else {
+            self.center.x.clamp(bounds.left() + half.x, bounds.right() - half.x)
+        };
+
+        self.center.y = if self.view_size.y >= bounds.h {
+            bounds.y + bounds.h / 2.0
+        } else {
+            self.center.y.clamp(bounds.top() + half.y, bounds.bottom() - half.y)
+        };
+    }
+
+    /// The part of the world currently on screen, used to cull drawing
+    pub fn get_view_rect(&self) -> Rect {
+        Rect::new(
+            self.center.x - self.view_size.x / 2.0,
+            self.center.y - self.view_size.y / 2.0,
+            self.view_size.x,
+            self.view_size.y,
+        )
+    }
+
+    /// Makes every following draw call use world coordinates and land in `render_target`
+    pub fn set_active(&self, render_target: RenderTarget) {
+        let mut camera = Camera2D::from_display_rect(self.get_view_rect());
+        camera.render_target = Some(render_target);
+        set_camera(&camera);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod game_camera;
+pub mod screen;
@@ -0,0 +1,104 @@
+use macroquad::{
+    camera::set_default_camera,
+    color::{BLACK, WHITE},
+    input::{is_key_down, is_key_pressed, KeyCode},
+    math::{vec2, Rect},
+    texture::{draw_texture_ex, render_target, DrawTextureParams, FilterMode, RenderTarget},
+    window::{clear_background, request_new_screen_size, screen_height, screen_width, set_fullscreen},
+};
+
+use common::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// The game is always rendered at WINDOW_WIDTH x WINDOW_HEIGHT into a render target,
+/// which then gets scaled up to whatever size the window actually is with black bars
+/// on the sides that don't fit.
+pub struct Screen {
+    render_target: RenderTarget,
+    is_fullscreen: bool,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        let render_target = render_target(WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32);
+        // same as the tiles, so the pixel art stays crisp when it's scaled up
+        render_target.texture.set_filter(FilterMode::Nearest);
+
+        Self {
+            render_target,
+            is_fullscreen: false,
+        }
+    }
+
+    /// F11 or Alt+Enter toggles fullscreen
+    pub fn handle_input(&mut self) {
+        let alt_enter = is_key_pressed(KeyCode::Enter)
+            && (is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt));
+
+        if is_key_pressed(KeyCode::F11) || alt_enter {
+            self.toggle_fullscreen();
+        }
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.is_fullscreen = !self.is_fullscreen;
+        set_fullscreen(self.is_fullscreen);
+
+        // leaving fullscreen should put us back at the default window size
+        if !self.is_fullscreen {
+            request_new_screen_size(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32);
+        }
+    }
+
+    pub fn get_render_target(&self) -> RenderTarget {
+        self.render_target.clone()
+    }
+
+    /// How much the internal resolution is scaled up to fit in the window.
+    ///
+    /// Whole numbers once the window is at least twice the size, so every game pixel covers the same
+    /// number of window pixels, the bars just get a bit wider
+    pub fn get_scale(&self) -> f32 {
+        let scale = (screen_width() / WINDOW_WIDTH as f32).min(screen_height() / WINDOW_HEIGHT as f32);
+
+        if scale >= 2.0 {
+            scale.floor()
+        } else {
+            scale
+        }
+    }
+
+    /// The letterboxed area of the window the game is drawn into, in window pixels
+    pub fn get_viewport(&self) -> Rect {
+        let scale = self.get_scale();
+        let width = WINDOW_WIDTH as f32 * scale;
+        let height = WINDOW_HEIGHT as f32 * scale;
+
+        Rect::new(
+            (screen_width() - width) / 2.0,
+            (screen_height() - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    /// Draws the render target to the window, call this after all the world drawing is done
+    pub fn present(&self) {
+        set_default_camera();
+        clear_background(BLACK);
+
+        let viewport = self.get_viewport();
+
+        draw_texture_ex(
+            &self.render_target.texture,
+            viewport.x,
+            viewport.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(viewport.w, viewport.h)),
+                // render targets come out upside down
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+    }
+}
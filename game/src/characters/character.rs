@@ -3,11 +3,19 @@ use std::rc::Rc;
 use macroquad::{math::Vec2, texture::Texture2D};
 use macroquad_platformer::Actor;
 
-use common::animation::{AnimationType, CharacterTextures, CharacterType, Facing};
+use common::{
+    animation::{AnimationType, CharacterTextures, CharacterType, Facing},
+    animation_events::AnimationEvent,
+    hit::HitProperties,
+    input::PlayerInput,
+};
+
+use super::snapshot::CharacterSnapshot;
 
 #[allow(dead_code)]
 pub trait CharacterTrait {
-    fn update(&mut self, dt: f32);
+    /// Steps the fighter forward by one simulation tick, returns the events of the animation frames it reached
+    fn update(&mut self, input: &PlayerInput) -> Vec<AnimationEvent>;
     fn get_anim_type(&self) -> AnimationType;
     fn get_character_type(&self) -> CharacterType;
     fn get_position(&self) -> Vec2;
@@ -19,4 +27,17 @@ pub trait CharacterTrait {
     fn set_client_id(&mut self);
     fn get_size(&self) -> (i32, i32);
     fn get_velocity(&self) -> Vec2;
+    /// Teleports the character to `pos` with no velocity and back to idle
+    fn respawn(&mut self, pos: Vec2);
+    /// The hit dealt by the attack frame we're on, if any
+    fn get_active_hit(&self) -> Option<HitProperties>;
+    /// The animation we're playing has an invulnerability window open
+    fn is_invulnerable(&self) -> bool;
+    /// We got hit, the knockback and stun kick in once the hit-stop is over
+    fn apply_hit(&mut self, hit: &HitProperties, attacker_facing: &Facing);
+    /// Our attack connected, we freeze for the hit-stop too
+    fn on_hit_landed(&mut self, hit: &HitProperties);
+    fn save_snapshot(&self) -> CharacterSnapshot;
+    /// Puts the fighter back exactly how it was when the snapshot was saved
+    fn load_snapshot(&mut self, snapshot: &CharacterSnapshot);
 }
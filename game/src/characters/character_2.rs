@@ -1,5 +1,4 @@
 use macroquad::{
-    input::{KeyCode, is_key_down},
     math::{Vec2, vec2},
     texture::Texture2D,
 };
@@ -8,11 +7,21 @@ use std::{cell::RefCell, rc::Rc};
 
 use common::constants::*;
 use common::animation_deltas::UpdateDeltas;
+use common::animation_events::AnimationEvent;
+use common::hit::HitProperties;
+use common::input::PlayerInput;
+use common::movesets;
+use common::simulation::{accumulate_subpixels, SIM_DT};
 
 use common::animation::{
-        AnimationSequence, AnimationType, CharacterTextures, CharacterType, PlayerAnimationState, Facing
+        AnimationType, CharacterTextures, CharacterType, PlayerAnimationState, Facing
+};
+use super::{
+    character::CharacterTrait,
+    hit_reaction::HitReaction,
+    snapshot::CharacterSnapshot,
+    transitions::{choose_transition, get_rules, TransitionContext},
 };
-use super::character::CharacterTrait;
 
 pub struct Character2 {
     x_v: f32,
@@ -22,13 +31,23 @@ pub struct Character2 {
     state: Rc<RefCell<PlayerAnimationState>>,
     actor: Actor,
     world: Rc<RefCell<World>>,
-    client_id: Option<u64>
+    client_id: Option<u64>,
+    hit_reaction: HitReaction,
+    /// Movement that hasn't added up to a whole pixel yet, in sub-pixels
+    subpixel_remainder: (i32, i32),
 }
 
 impl CharacterTrait for Character2 {
-    fn update(&mut self, dt: f32) {
-        self.update_physics(dt);
-        self.update_animation();
+    fn update(&mut self, input: &PlayerInput) -> Vec<AnimationEvent> {
+        self.hit_reaction.update(SIM_DT);
+
+        // hit-stop freezes us in place, animation included
+        if self.hit_reaction.is_frozen() {
+            return vec![];
+        }
+
+        self.update_physics(input);
+        self.update_animation()
     }
 
     fn set_client_id(&mut self) {
@@ -80,11 +99,76 @@ impl CharacterTrait for Character2 {
     fn get_velocity(&self) -> Vec2 {
         Vec2::new(self.x_v, self.y_v)
     }
+
+    fn respawn(&mut self, pos: Vec2) {
+        self.x_v = 0.0;
+        self.y_v = 0.0;
+        self.facing = Facing::Right;
+
+        self.state.borrow_mut().reset();
+        self.state = Rc::clone(&self.animations.idle_anim);
+
+        self.hit_reaction.reset();
+        self.subpixel_remainder = (0, 0);
+
+        self.world.borrow_mut().set_actor_position(self.actor, pos);
+    }
+
+    fn save_snapshot(&self) -> CharacterSnapshot {
+        CharacterSnapshot {
+            position: self.get_position(),
+            size: self.get_size(),
+            subpixel_remainder: self.subpixel_remainder,
+            x_v: self.x_v,
+            y_v: self.y_v,
+            facing: self.facing.clone(),
+            animation: self.state.borrow().save(),
+            hit_reaction: self.hit_reaction.clone(),
+        }
+    }
+
+    fn load_snapshot(&mut self, snapshot: &CharacterSnapshot) {
+        {
+            let mut world = self.world.borrow_mut();
+            world.set_actor_position(self.actor, snapshot.position);
+            world.set_actor_size(self.actor, snapshot.size.0, snapshot.size.1);
+        }
+
+        self.subpixel_remainder = snapshot.subpixel_remainder;
+        self.x_v = snapshot.x_v;
+        self.y_v = snapshot.y_v;
+        self.facing = snapshot.facing.clone();
+        self.hit_reaction = snapshot.hit_reaction.clone();
+
+        self.state.borrow_mut().reset();
+        self.state = self.animations.get_state(&snapshot.animation.anim_type);
+        self.state.borrow_mut().load(&snapshot.animation);
+    }
+
+    fn get_active_hit(&self) -> Option<HitProperties> {
+        self.state.borrow().get_active_hit()
+    }
+
+    fn is_invulnerable(&self) -> bool {
+        self.state.borrow().is_invulnerable()
+    }
+
+    fn apply_hit(&mut self, hit: &HitProperties, attacker_facing: &Facing) {
+        self.hit_reaction.on_hit(hit, attacker_facing);
+
+        // getting hit cancels whatever we were doing
+        self.state.borrow_mut().reset();
+        self.state = Rc::clone(&self.animations.idle_anim);
+    }
+
+    fn on_hit_landed(&mut self, hit: &HitProperties) {
+        self.hit_reaction.on_hit_landed(hit);
+    }
 }
 
 impl Character2 {
-    pub async fn new(x: f32, y: f32, width: i32, height: i32, world: Rc<RefCell<World>>, client_id: Option<u64>) -> Self {
-        let animation_bank = Character2Animations::load().await;
+    pub fn new(x: f32, y: f32, width: i32, height: i32, world: Rc<RefCell<World>>, client_id: Option<u64>) -> Self {
+        let animation_bank = Character2Animations::load();
         let state = animation_bank.idle_anim.clone();
         let collider = world
             .borrow_mut()
@@ -99,25 +183,16 @@ impl Character2 {
             actor: collider,
             world,
             client_id,
+            hit_reaction: HitReaction::new(),
+            subpixel_remainder: (0, 0),
         }
     }
 
-    fn update_physics(&mut self, dt: f32) {
-        let wants_crouch = is_key_down(KeyCode::C);
-
-        let wants_walk_left = is_key_down(KeyCode::A);
-        let wants_walk_right = is_key_down(KeyCode::D);
-
-        let wants_run_left = is_key_down(KeyCode::A) && is_key_down(KeyCode::LeftShift);
-        let wants_run_right = is_key_down(KeyCode::D) && is_key_down(KeyCode::LeftShift);
+    fn update_physics(&mut self, input: &PlayerInput) {
+        let dt = SIM_DT;
 
-        let wants_jump = is_key_down(KeyCode::Space);
-
-        let wants_attack_1 = is_key_down(KeyCode::E);
-        let wants_attack_2 = is_key_down(KeyCode::Q);
-        let wants_kick = is_key_down(KeyCode::R);
-
-        let wants_nothing = !is_any_key_down();
+        // stunned fighters can't act, they just get carried by the knockback
+        let can_act = !self.hit_reaction.is_stunned();
 
         let pos = self.world.borrow().actor_pos(self.actor);
         let is_grounded = self
@@ -134,29 +209,22 @@ impl Character2 {
             .collide_check(self.actor, pos - vec2(1., 0.));
 
         let is_airborn = !is_grounded;
-        let is_actively_playing = self.state.borrow().actively_playing;
-        let is_interuptable = self.state.borrow().is_interuptable;
+        let can_steer = get_rules(&self.state.borrow().anim_type).can_steer;
         let was_just_airborn = is_grounded && self.y_v < 0.0;
 
-        let mut next_animation_state = self.state.borrow().anim_type.clone();
-
         if is_airborn {
             self.y_v -= GRAVITY * dt;
-
-            if wants_kick {
-                next_animation_state = AnimationType::SoaringKick;
-            }
         }
 
         // this represents when a player WAS jumping but just touched the ground
         // they have negative y velocity because they were falling back down
         if was_just_airborn {
             self.y_v = 0.0;
-            next_animation_state = AnimationType::Landing;
         }
 
         // if grounded the we have friction and reset to 0.0
-        if is_grounded {
+        // knockback keeps sliding us along the ground until the stun wears off
+        if is_grounded && can_act {
             self.x_v = 0.0;
         }
 
@@ -169,94 +237,49 @@ impl Character2 {
             self.x_v = 0.0;
         }
 
-        if wants_nothing {
-            if is_grounded && !was_just_airborn {
-                next_animation_state = AnimationType::Idle;
-            }
+        // the knockback from a hit replaces whatever velocity we had
+        if let Some(knockback) = self.hit_reaction.take_knockback() {
+            self.x_v = knockback.x;
+            self.y_v = knockback.y;
         }
 
-        if is_interuptable {
-            if wants_walk_left {
-                if !wants_crouch {
-                    self.x_v = WALK_SPEED * -1.0;
-                    self.facing = Facing::Left;
-                    if is_grounded {
-                        next_animation_state = AnimationType::ReverseWalk;
-                    }
-                }
-            }
-
-            if wants_walk_right {
-                if !wants_crouch {
-                    self.x_v = WALK_SPEED;
-                    self.facing = Facing::Right;
-                    if is_grounded {
-                        next_animation_state = AnimationType::ForwardWalk;
-                    }
-                }
-            }
-
-            if wants_run_left {
-                if !wants_crouch {
-                    self.x_v = RUN_SPEED * -1.0;
-                    self.facing = Facing::Left;
-                    if is_grounded {
-                        next_animation_state = AnimationType::ReverseRun;
-                    }
-                }
-            }
-
-            if wants_run_right {
-                if !wants_crouch {
-                    self.x_v = RUN_SPEED;
-                    self.facing = Facing::Right;
-                    if is_grounded {
-                        next_animation_state = AnimationType::ForwardRun;
-                    }
-                }
+        if can_steer && can_act && !input.crouch {
+            if input.left {
+                self.x_v = WALK_SPEED * -1.0;
+                self.facing = Facing::Left;
             }
 
-            if wants_jump {
-                if is_grounded && !wants_crouch {
-                    if self.x_v == 0.0 {
-                        next_animation_state = AnimationType::Jump;
-                    } else {
-                        next_animation_state = AnimationType::JumpMoving;
-                    }
-                }
+            if input.right {
+                self.x_v = WALK_SPEED;
+                self.facing = Facing::Right;
             }
 
-            if wants_attack_1 {
-                if is_grounded {
-                    next_animation_state = AnimationType::Attack1;
-                }
+            if input.left && input.run {
+                self.x_v = RUN_SPEED * -1.0;
+                self.facing = Facing::Left;
             }
 
-            if wants_attack_2 {
-                if is_grounded {
-                    next_animation_state = AnimationType::Attack2;
-                }
+            if input.right && input.run {
+                self.x_v = RUN_SPEED;
+                self.facing = Facing::Right;
             }
+        }
 
-            if wants_kick {
-                if is_grounded {
-                    next_animation_state = AnimationType::Attack3;
-                }
-            }
+        self.move_h(self.x_v * dt);
+        self.move_v((self.y_v * -1.0) * dt);
 
-            if wants_crouch {
-                next_animation_state = AnimationType::Crouch;
-            }
-        }
+        let context = TransitionContext {
+            input,
+            is_grounded,
+            has_landed: was_just_airborn,
+            is_moving: self.x_v != 0.0,
+            can_act,
+        };
 
-        self.world.borrow_mut().move_h(self.actor, self.x_v * dt);
-        self.world
-            .borrow_mut()
-            .move_v(self.actor, (self.y_v * -1.0) * dt);
+        // the transition table decides if we change animations, and if so the current one stops
+        let next_animation_state = choose_transition(&self.state.borrow(), &context);
 
-        if next_animation_state != self.state.borrow().anim_type && !is_actively_playing {
-            // we decided above if we want to change animations or not
-            // if we want to change animations, we need to stop the current animation
+        if let Some(next_animation_state) = next_animation_state {
             self.state.borrow_mut().reset();
 
             match next_animation_state {
@@ -311,38 +334,54 @@ impl Character2 {
         }
     }
 
-    fn update_animation(&mut self) {
+    fn update_animation(&mut self) -> Vec<AnimationEvent> {
         let deltas = self.state.borrow_mut().update();
         self.apply_animation_deltas(&deltas);
+        deltas.events
     }
 
     fn apply_animation_deltas(&mut self, delta: &UpdateDeltas) {
-        let world = Rc::clone(&self.world);
-
         if self.facing == Facing::Left {
-            world
-                .borrow_mut()
-                .move_h(self.actor, delta.pos_delta.0 * -1.0);
+            self.move_h(delta.pos_delta.0 * -1.0);
             if delta.vel_delta.0 != 0.0 {
                 self.x_v -= delta.vel_delta.0;
             }
         } else {
-            world.borrow_mut().move_h(self.actor, delta.pos_delta.0);
+            self.move_h(delta.pos_delta.0);
             if delta.vel_delta.0 != 0.0 {
                 self.x_v += delta.vel_delta.0;
             }
         }
 
-        world.borrow_mut().move_v(self.actor, delta.pos_delta.1);
+        self.move_v(delta.pos_delta.1);
         if delta.vel_delta.1 != 0.0 {
             self.y_v += delta.vel_delta.1;
         }
 
         // set actor size
-        world
+        self.world
             .borrow_mut()
             .set_actor_size(self.actor, delta.width, delta.height);
     }
+
+    /// The world only ever moves us by whole pixels, the rest waits in our fixed-point remainder
+    fn move_h(&mut self, dx: f32) {
+        let (pixels, remainder) = accumulate_subpixels(self.subpixel_remainder.0, dx);
+        self.subpixel_remainder.0 = remainder;
+
+        if pixels != 0 {
+            self.world.borrow_mut().move_h(self.actor, pixels as f32);
+        }
+    }
+
+    fn move_v(&mut self, dy: f32) {
+        let (pixels, remainder) = accumulate_subpixels(self.subpixel_remainder.1, dy);
+        self.subpixel_remainder.1 = remainder;
+
+        if pixels != 0 {
+            self.world.borrow_mut().move_v(self.actor, pixels as f32);
+        }
+    }
 }
 
 pub struct Character2Animations {
@@ -362,198 +401,40 @@ pub struct Character2Animations {
 }
 
 impl Character2Animations {
-    pub async fn load() -> Self {
-        let idle_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::Idle,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            animation_sequence: vec![AnimationSequence::new(6, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)],
-            sprite_frame: 0,
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: true,
-            is_interuptable: true,
-        }));
-
-        let crouch_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::Crouch,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            animation_sequence: vec![AnimationSequence::new(1, 20.0, 0.0, 0.0, 0.0, 0.0, 60, 28)],
-            sprite_frame: 0,
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: true,
-        }));
-
-        let fwd_run_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::ForwardRun,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: true,
-            is_interuptable: true,
-        }));
-
-        let rev_run_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::ReverseRun,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: true,
-            is_interuptable: true,
-        }));
-
-        let jump_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::Jump,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![
-                AnimationSequence::new(1, 20.0, 0.0, 0.0, 0.0, 500.0, 93, 28),
-                AnimationSequence::new(2, 20.0, 0.0, 0.0, 0.0, 0.0, 70, 28),
-                AnimationSequence::new(4, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-            ],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: true,
-        }));
-
-        let jump_anim_moving = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::JumpMoving,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![
-                AnimationSequence::new(1, 20.0, 0.0, 0.0, 0.0, 500.0, 93, 28),
-                AnimationSequence::new(2, 20.0, 0.0, 0.0, 0.0, 0.0, 70, 28),
-                AnimationSequence::new(5, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-            ],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: true,
-        }));
-
-        let landing_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::Landing,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![AnimationSequence::new(2, 10.0, 0.0, 0.0, 0.0, 0.0, 70, 28)],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: true,
-        }));
-
-        let fwd_walk_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::ForwardWalk,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: true,
-            is_interuptable: true,
-        }));
-
-        let rev_walk_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::ReverseWalk,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: true,
-            is_interuptable: true,
-        }));
-
-        let attack_1_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::Attack1,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![
-                AnimationSequence::new(2, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-            ],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: true,
-        }));
-
-        let attack_2_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::Attack2,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![
-                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-                AnimationSequence::new(1, 3.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-            ],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: true,
-        }));
-
-        let attack_3_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::Attack3,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![
-                AnimationSequence::new(2, 8.0, 75.0, 0.0, 0.0, 0.0, 93, 28),
-                AnimationSequence::new(1, 8.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-                AnimationSequence::new(1, 8.0, 50.0, 0.0, 0.0, 0.0, 93, 28),
-            ],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: true,
-        }));
-
-        let soaring_kick_anim = Rc::new(RefCell::new(PlayerAnimationState {
-            anim_type: AnimationType::SoaringKick,
-            character_type: CharacterType::Shinobi,
-            time: 0.0,
-            sprite_frame: 0,
-            animation_sequence: vec![
-                AnimationSequence::new(2, 8.0, 0.0, 0.0, 1250.0, -200.0, 93, 28),
-                AnimationSequence::new(2, 8.0, 0.0, 0.0, 0.0, 0.0, 93, 28),
-            ],
-            sequence_index: 0,
-            sequence_frame_index: 0,
-            actively_playing: false,
-            always_plays: false,
-            is_interuptable: false,
-        }));
+    pub fn get_state(&self, anim_type: &AnimationType) -> Rc<RefCell<PlayerAnimationState>> {
+        match anim_type {
+            AnimationType::Idle => Rc::clone(&self.idle_anim),
+            AnimationType::Crouch => Rc::clone(&self.crouch_anim),
+            AnimationType::ForwardRun => Rc::clone(&self.fwd_run_anim),
+            AnimationType::ReverseRun => Rc::clone(&self.rev_run_anim),
+            AnimationType::Jump => Rc::clone(&self.jump_anim),
+            AnimationType::JumpMoving => Rc::clone(&self.jump_anim_moving),
+            AnimationType::Landing => Rc::clone(&self.landing_anim),
+            AnimationType::ForwardWalk => Rc::clone(&self.fwd_walk_anim),
+            AnimationType::ReverseWalk => Rc::clone(&self.rev_walk_anim),
+            AnimationType::Attack1 => Rc::clone(&self.attack_1_anim),
+            AnimationType::Attack2 => Rc::clone(&self.attack_2_anim),
+            AnimationType::Attack3 => Rc::clone(&self.attack_3_anim),
+            AnimationType::SoaringKick => Rc::clone(&self.soaring_kick_anim),
+        }
+    }
+
+    pub fn load() -> Self {
+        let load = |anim_type: AnimationType| Rc::new(RefCell::new(movesets::get_animation(&CharacterType::Shinobi, &anim_type)));
+
+        let idle_anim = load(AnimationType::Idle);
+        let crouch_anim = load(AnimationType::Crouch);
+        let fwd_run_anim = load(AnimationType::ForwardRun);
+        let rev_run_anim = load(AnimationType::ReverseRun);
+        let jump_anim = load(AnimationType::Jump);
+        let jump_anim_moving = load(AnimationType::JumpMoving);
+        let landing_anim = load(AnimationType::Landing);
+        let fwd_walk_anim = load(AnimationType::ForwardWalk);
+        let rev_walk_anim = load(AnimationType::ReverseWalk);
+        let attack_1_anim = load(AnimationType::Attack1);
+        let attack_2_anim = load(AnimationType::Attack2);
+        let attack_3_anim = load(AnimationType::Attack3);
+        let soaring_kick_anim = load(AnimationType::SoaringKick);
 
         Self {
             idle_anim,
@@ -572,22 +453,3 @@ impl Character2Animations {
         }
     }
 }
-
-fn is_any_key_down() -> bool {
-    if is_key_down(KeyCode::W) {
-        return true;
-    }
-    if is_key_down(KeyCode::S) {
-        return true;
-    }
-    if is_key_down(KeyCode::A) {
-        return true;
-    }
-    if is_key_down(KeyCode::D) {
-        return true;
-    }
-    if is_key_down(KeyCode::Space) {
-        return true;
-    }
-    false
-}
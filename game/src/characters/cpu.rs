@@ -0,0 +1,39 @@
+use std::{cell::RefCell, rc::Rc};
+
+use macroquad::math::vec2;
+use macroquad_platformer::World;
+
+use common::ai::{AiTarget, AiView};
+
+use super::character::CharacterTrait;
+
+/// What a CPU controlling `fighter` gets to see, `opponents` are everyone it could go after
+pub fn get_ai_view(fighter: &dyn CharacterTrait, opponents: &[&dyn CharacterTrait], world: &Rc<RefCell<World>>) -> AiView {
+    let world = world.borrow();
+    let actor = fighter.get_actor();
+    let position = fighter.get_position();
+    let (width, height) = fighter.get_size();
+
+    // a step ahead and a little down, colliding there means there's floor to walk onto
+    let step = width as f32;
+
+    AiView {
+        position,
+        size: vec2(width as f32, height as f32),
+        is_grounded: world.collide_check(actor, position + vec2(0., 1.)),
+        ground_left: world.collide_check(actor, position + vec2(-step, 4.)),
+        ground_right: world.collide_check(actor, position + vec2(step, 4.)),
+        targets: opponents
+            .iter()
+            .map(|opponent| {
+                let (width, height) = opponent.get_size();
+
+                AiTarget {
+                    position: opponent.get_position(),
+                    size: vec2(width as f32, height as f32),
+                    is_attacking: opponent.get_active_hit().is_some(),
+                }
+            })
+            .collect(),
+    }
+}
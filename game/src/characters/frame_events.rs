@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use macroquad::math::{vec2, Vec2};
+
+use common::{
+    animation::{AnimationType, CharacterType, Facing},
+    animation_events::AnimationEvent,
+    movesets,
+    types::ServerClient,
+};
+
+use super::character::CharacterTrait;
+
+const CHARACTERS: [CharacterType; 3] = [CharacterType::Fighter, CharacterType::Shinobi, CharacterType::Samurai];
+
+// a remote player's frame can jump a few ahead between updates, anything past that was a seek or a respawn
+const MAX_CATCH_UP_FRAMES: usize = 3;
+
+/// What a fighter's animation reached since we last looked at them
+pub struct FighterFrameEvents {
+    /// Bottom middle of the fighter, effects are placed from here
+    pub feet: Vec2,
+    pub facing: Facing,
+    /// The sound and effect events of the frames they reached, in order
+    pub events: Vec<AnimationEvent>,
+    /// Health they lost since we last looked
+    pub damage_taken: i32,
+}
+
+/// Where a fighter's animation was last time we saw them
+struct WatchedFighter {
+    anim_type: AnimationType,
+    sprite_frame: usize,
+    health: i32,
+    /// The frame count of when we last saw them, fighters we stop seeing get forgotten
+    last_seen: u64,
+}
+
+/// Works out which frames every fighter on screen passed from just their animation and sprite frame,
+/// so sounds and effects play the same for local, networked and replayed fighters
+pub struct FrameEventWatcher {
+    /// Sound and effect events of every character's animations, keyed by sprite frame
+    frame_events: HashMap<(CharacterType, AnimationType), Vec<(usize, AnimationEvent)>>,
+    fighters: HashMap<u64, WatchedFighter>,
+    frame: u64,
+}
+
+impl FrameEventWatcher {
+    pub fn new() -> Self {
+        let mut frame_events = HashMap::new();
+
+        for character_type in CHARACTERS {
+            for anim_type in AnimationType::ALL {
+                // the gameplay windows are the simulation's business
+                let events = movesets::get_animation(&character_type, &anim_type)
+                    .get_frame_events()
+                    .into_iter()
+                    .filter(|(_, event)| matches!(event, AnimationEvent::Sound(_) | AnimationEvent::SpawnEffect(_)))
+                    .collect();
+
+                frame_events.insert((character_type.clone(), anim_type.clone()), events);
+            }
+        }
+
+        Self {
+            frame_events,
+            fighters: HashMap::new(),
+            frame: 0,
+        }
+    }
+
+    /// The events reached and health lost since last time, None the first time we see a fighter
+    /// since their animation could be anywhere so nothing is reached yet
+    fn watch(&mut self, id: u64, character_type: &CharacterType, anim_type: &AnimationType, sprite_frame: usize, health: i32) -> Option<(Vec<AnimationEvent>, i32)> {
+        let frame = self.frame;
+
+        let Some(watched) = self.fighters.get_mut(&id) else {
+            self.fighters.insert(id, WatchedFighter {
+                anim_type: anim_type.clone(),
+                sprite_frame,
+                health,
+                last_seen: frame,
+            });
+            return None;
+        };
+
+        let reached = |event_frame: usize| {
+            if watched.anim_type != *anim_type {
+                return event_frame == sprite_frame;
+            }
+
+            match sprite_frame.cmp(&watched.sprite_frame) {
+                std::cmp::Ordering::Equal => false,
+                // the animation looped
+                std::cmp::Ordering::Less => event_frame > watched.sprite_frame || event_frame <= sprite_frame,
+                std::cmp::Ordering::Greater if sprite_frame - watched.sprite_frame > MAX_CATCH_UP_FRAMES => event_frame == sprite_frame,
+                std::cmp::Ordering::Greater => event_frame > watched.sprite_frame && event_frame <= sprite_frame,
+            }
+        };
+
+        let events = self
+            .frame_events
+            .get(&(character_type.clone(), anim_type.clone()))
+            .map(|events| events.iter().filter(|(event_frame, _)| reached(*event_frame)).map(|(_, event)| event.clone()).collect())
+            .unwrap_or_default();
+
+        let damage_taken = (watched.health - health).max(0);
+
+        watched.anim_type = anim_type.clone();
+        watched.sprite_frame = sprite_frame;
+        watched.health = health;
+        watched.last_seen = frame;
+
+        Some((events, damage_taken))
+    }
+
+    /// `watch` for a fighter simulated on this machine
+    pub fn watch_character(&mut self, id: u64, character: &dyn CharacterTrait, health: i32) -> Option<FighterFrameEvents> {
+        let position = character.get_position();
+        let (width, height) = character.get_size();
+        let (events, damage_taken) = self.watch(id, &character.get_character_type(), &character.get_anim_type(), character.get_sprite_frame(), health)?;
+
+        Some(FighterFrameEvents {
+            feet: position + vec2(width as f32 / 2.0, height as f32),
+            facing: character.get_facing(),
+            events,
+            damage_taken,
+        })
+    }
+
+    /// `watch` for everyone the server told us about, `skip` is the local player who's watched separately
+    pub fn watch_remote_fighters(&mut self, clients: &HashMap<u64, ServerClient>, skip: Option<u64>) -> Vec<FighterFrameEvents> {
+        clients
+            .iter()
+            .filter(|(client_id, _)| Some(**client_id) != skip)
+            .filter_map(|(client_id, sc)| {
+                let (events, damage_taken) = self.watch(*client_id, &sc.character_type, &sc.anim_type, sc.sprite_frame, sc.health)?;
+
+                Some(FighterFrameEvents {
+                    feet: vec2(sc.x_pos + sc.width as f32 / 2.0, sc.y_pos + sc.height as f32),
+                    facing: sc.facing.clone(),
+                    events,
+                    damage_taken,
+                })
+            })
+            .collect()
+    }
+
+    /// Call once a frame after every fighter was watched, anyone who wasn't is forgotten
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        self.fighters.retain(|_, watched| watched.last_seen == frame);
+        self.frame += 1;
+    }
+}
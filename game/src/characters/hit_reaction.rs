@@ -0,0 +1,66 @@
+use macroquad::math::Vec2;
+
+use common::{animation::Facing, hit::HitProperties};
+
+/// Tracks hit-stop, hit-stun and pending knockback for one fighter
+#[derive(Clone, Debug, PartialEq)]
+pub struct HitReaction {
+    hit_stop_left: f32,
+    hit_stun_left: f32,
+    /// Applied to the fighter's velocity once the hit-stop ends
+    pending_knockback: Option<Vec2>,
+}
+
+impl HitReaction {
+    pub fn new() -> Self {
+        Self {
+            hit_stop_left: 0.0,
+            hit_stun_left: 0.0,
+            pending_knockback: None,
+        }
+    }
+
+    /// We got hit by someone facing `attacker_facing`
+    pub fn on_hit(&mut self, hit: &HitProperties, attacker_facing: &Facing) {
+        self.hit_stop_left = self.hit_stop_left.max(hit.hit_stop);
+        self.hit_stun_left = hit.hit_stun;
+        self.pending_knockback = Some(hit.get_knockback(attacker_facing));
+    }
+
+    /// We hit someone, the attacker freezes for the same hit-stop as the victim
+    pub fn on_hit_landed(&mut self, hit: &HitProperties) {
+        self.hit_stop_left = self.hit_stop_left.max(hit.hit_stop);
+    }
+
+    /// Counts down the timers, stun only starts once the hit-stop is over
+    pub fn update(&mut self, dt: f32) {
+        if self.hit_stop_left > 0.0 {
+            self.hit_stop_left = (self.hit_stop_left - dt).max(0.0);
+            return;
+        }
+
+        self.hit_stun_left = (self.hit_stun_left - dt).max(0.0);
+    }
+
+    /// Frozen fighters don't move or animate at all
+    pub fn is_frozen(&self) -> bool {
+        self.hit_stop_left > 0.0
+    }
+
+    /// Stunned fighters still fly and fall but ignore input
+    pub fn is_stunned(&self) -> bool {
+        self.hit_stun_left > 0.0
+    }
+
+    pub fn take_knockback(&mut self) -> Option<Vec2> {
+        if self.is_frozen() {
+            return None;
+        }
+
+        self.pending_knockback.take()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
@@ -2,4 +2,11 @@ pub mod character;
 pub mod character_1;
 pub mod character_2;
 pub mod character_3;
+pub mod cpu;
+pub mod frame_events;
+pub mod hit_reaction;
+pub mod respawn;
 pub mod server_character;
+pub mod snapshot;
+pub mod transitions;
+pub mod tuning;
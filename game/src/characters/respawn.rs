@@ -0,0 +1,73 @@
+use macroquad::math::{Rect, Vec2};
+
+// how long a player stays dead after falling off the map
+const RESPAWN_DELAY_SECONDS: f32 = 3.0;
+// how long a player can't be hurt after respawning
+const SPAWN_INVULNERABILITY_SECONDS: f32 = 2.0;
+// how far past the edge of the map a player can go before they count as out
+const OUT_OF_BOUNDS_MARGIN: f32 = 200.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LifeState {
+    Alive,
+    /// Fell off the map and is waiting out the respawn delay
+    Dead { respawn_timer: f32 },
+    /// Respawn delay is over and we're waiting to be told where to go
+    WaitingForSpawn,
+}
+
+/// Tracks the local player dying, coming back and the invulnerability after that
+pub struct RespawnState {
+    pub life_state: LifeState,
+    invulnerable_timer: f32,
+}
+
+impl RespawnState {
+    pub fn new() -> Self {
+        Self {
+            life_state: LifeState::Alive,
+            invulnerable_timer: 0.0,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.life_state == LifeState::Alive
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_timer > 0.0
+    }
+
+    pub fn kill(&mut self) {
+        self.life_state = LifeState::Dead { respawn_timer: RESPAWN_DELAY_SECONDS };
+        self.invulnerable_timer = 0.0;
+    }
+
+    /// Counts down the timers, returns true on the frame the player needs a spawn point
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.invulnerable_timer = (self.invulnerable_timer - dt).max(0.0);
+
+        if let LifeState::Dead { respawn_timer } = &mut self.life_state {
+            *respawn_timer -= dt;
+
+            if *respawn_timer <= 0.0 {
+                self.life_state = LifeState::WaitingForSpawn;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn on_respawned(&mut self) {
+        self.life_state = LifeState::Alive;
+        self.invulnerable_timer = SPAWN_INVULNERABILITY_SECONDS;
+    }
+}
+
+/// Anything past the sides or bottom of the map is out, going above the top is fine
+pub fn is_out_of_bounds(pos: Vec2, bounds: Rect) -> bool {
+    pos.x < bounds.left() - OUT_OF_BOUNDS_MARGIN
+        || pos.x > bounds.right() + OUT_OF_BOUNDS_MARGIN
+        || pos.y > bounds.bottom() + OUT_OF_BOUNDS_MARGIN
+}
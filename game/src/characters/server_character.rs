@@ -1,11 +1,15 @@
 use std::{cell::RefCell, rc::Rc};
 
-use common::animation::{AnimationType, CharacterTextures, CharacterType, Facing};
-use macroquad::{math::vec2, texture::Texture2D};
+use common::{animation::{AnimationType, CharacterTextures, CharacterType, Facing}, constants::MAX_HEALTH};
+use macroquad::{math::{vec2, Vec2}, texture::Texture2D};
 use macroquad_platformer::{Actor, World};
 
+// nowhere near any map, so nothing ever touches a parked actor
+const PARKED_ACTOR_POSITION: Vec2 = Vec2::new(-100_000.0, -100_000.0);
 
 pub struct ServerCharacter {
+    pub name: String,
+    pub health: i32,
     pub x_pos: f32,
     pub y_pos: f32,
     pub height: i32,
@@ -14,6 +18,7 @@ pub struct ServerCharacter {
     pub anim_type: AnimationType,
     pub character_type: CharacterType,
     pub sprite_frame: usize,
+    pub is_invulnerable: bool,
     pub actor: Actor,
     pub world: Rc<RefCell<World>>,
 }
@@ -29,13 +34,25 @@ impl ServerCharacter {
         anim_type: AnimationType,
         character_type: CharacterType,
         sprite_frame: usize,
+        is_invulnerable: bool,
+        spare_actor: Option<Actor>,
         world: Rc<RefCell<World>>
     ) -> Self {
-        let actor = world
-            .borrow_mut()
-            .add_actor(vec2(x_pos, y_pos), width as i32, height as i32);
+        // the world can't remove actors, so ones left behind by departed players get reused
+        let actor = match spare_actor {
+            Some(actor) => {
+                world.borrow_mut().set_actor_position(actor, vec2(x_pos, y_pos));
+                world.borrow_mut().set_actor_size(actor, width, height);
+                actor
+            }
+            None => world
+                .borrow_mut()
+                .add_actor(vec2(x_pos, y_pos), width as i32, height as i32),
+        };
 
         Self {
+            name: String::new(),
+            health: MAX_HEALTH,
             x_pos,
             y_pos,
             height,
@@ -44,6 +61,7 @@ impl ServerCharacter {
             anim_type,
             character_type,
             sprite_frame,
+            is_invulnerable,
             actor,
             world,
         }
@@ -63,6 +81,13 @@ impl ServerCharacter {
         Rc::clone(&texture)
     }
 
+    /// Parks the actor far off the map and hands it back so the next player to join can use it
+    pub fn remove(self) -> Actor {
+        self.world.borrow_mut().set_actor_position(self.actor, PARKED_ACTOR_POSITION);
+        self.world.borrow_mut().set_actor_size(self.actor, 0, 0);
+        self.actor
+    }
+
     pub fn get_actor(&self) -> Actor {
         self.actor
     }
@@ -74,4 +99,8 @@ impl ServerCharacter {
     pub fn get_sprite_frame(&self) -> usize {
         self.sprite_frame
     }
+
+    pub fn get_is_invulnerable(&self) -> bool {
+        self.is_invulnerable
+    }
 }
@@ -0,0 +1,122 @@
+use macroquad::math::Vec2;
+
+use common::animation::{AnimationSnapshot, Facing};
+
+use super::hit_reaction::HitReaction;
+
+/// Everything that makes a fighter behave the way it does, cheap to copy so it can be saved every tick
+#[derive(Clone, Debug, PartialEq)]
+pub struct CharacterSnapshot {
+    /// Whole pixels, the actor never sits between pixels
+    pub position: Vec2,
+    pub size: (i32, i32),
+    pub subpixel_remainder: (i32, i32),
+    pub x_v: f32,
+    pub y_v: f32,
+    pub facing: Facing,
+    pub animation: AnimationSnapshot,
+    pub hit_reaction: HitReaction,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use macroquad::math::{vec2, Rect};
+    use macroquad_platformer::{Tile, World};
+
+    use common::{
+        constants::{DEFAULT_PLAYER_HEIGHT, DEFAULT_PLAYER_WIDTH},
+        input::PlayerInput,
+    };
+
+    use crate::{
+        characters::{character::CharacterTrait, character_1::Character1, character_3::Character3},
+        rollback::duel::{Duel, DuelState},
+    };
+
+    const TILE_SIZE: f32 = 32.0;
+    const ROOM_WIDTH: usize = 40;
+    const ROOM_HEIGHT: usize = 20;
+
+    /// An empty room with a floor along the bottom row
+    fn new_world() -> Rc<RefCell<World>> {
+        let floor_start = ROOM_WIDTH * (ROOM_HEIGHT - 1);
+        let tiles = (0..ROOM_WIDTH * ROOM_HEIGHT)
+            .map(|i| if i >= floor_start { Tile::Solid } else { Tile::Empty })
+            .collect();
+
+        let mut world = World::new();
+        world.add_static_tiled_layer(tiles, TILE_SIZE, TILE_SIZE, ROOM_WIDTH, 1);
+        Rc::new(RefCell::new(world))
+    }
+
+    fn repeat<T: Clone>(input: T, ticks: usize) -> Vec<T> {
+        vec![input; ticks]
+    }
+
+    /// The two fighters walk up to each other, swing, jump and kick, one pair of inputs per tick
+    fn input_script() -> Vec<[PlayerInput; 2]> {
+        let idle = PlayerInput::default();
+        let walk_right = PlayerInput { right: true, ..idle };
+        let walk_left = PlayerInput { left: true, ..idle };
+
+        let player_0 = [
+            repeat(PlayerInput { run: true, ..walk_right }, 20),
+            repeat(PlayerInput { attack_1: true, ..idle }, 30),
+            repeat(idle, 10),
+            repeat(PlayerInput { jump: true, ..walk_right }, 15),
+            repeat(PlayerInput { kick: true, ..walk_right }, 30),
+            repeat(idle, 40),
+        ]
+        .concat();
+
+        let player_1 = [
+            repeat(walk_left, 35),
+            repeat(PlayerInput { attack_2: true, ..idle }, 30),
+            repeat(PlayerInput { crouch: true, ..idle }, 20),
+            repeat(PlayerInput { attack_1: true, ..walk_left }, 30),
+            repeat(idle, 30),
+        ]
+        .concat();
+
+        player_0.into_iter().zip(player_1).map(|(input_0, input_1)| [input_0, input_1]).collect()
+    }
+
+    /// Everything the duel was in after every tick
+    fn play(duel: &mut Duel, script: &[[PlayerInput; 2]]) -> Vec<DuelState> {
+        script
+            .iter()
+            .map(|inputs| {
+                duel.step(inputs);
+                duel.save()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_inputs_from_the_same_snapshot_play_out_the_same() {
+        let world = new_world();
+        let spawns = [vec2(400.0, 400.0), vec2(560.0, 400.0)];
+        let fighters: [Box<dyn CharacterTrait>; 2] = [
+            Box::new(Character1::new(spawns[0].x, spawns[0].y, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(&world), None)),
+            Box::new(Character3::new(spawns[1].x, spawns[1].y, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(&world), None)),
+        ];
+        let bounds = Rect::new(0.0, 0.0, ROOM_WIDTH as f32 * TILE_SIZE, ROOM_HEIGHT as f32 * TILE_SIZE);
+        let mut duel = Duel::new(fighters, spawns, bounds);
+
+        // land on the floor first so the snapshot is from the middle of things, not a fresh spawn
+        play(&mut duel, &repeat([PlayerInput::default(); 2], 30));
+
+        let start = duel.save();
+        let script = input_script();
+
+        let first = play(&mut duel, &script);
+        duel.load(&start);
+        let second = play(&mut duel, &script);
+
+        assert_eq!(first, second);
+        // a script that left everything where it was wouldn't prove much
+        assert_ne!(first.last(), Some(&start));
+    }
+}
@@ -0,0 +1,160 @@
+use common::{
+    animation::{AnimationType, PlayerAnimationState},
+    input::PlayerInput,
+};
+
+/// How much an animation matters, a playing animation only gives way to something more important
+/// unless it's finished or in a cancel window
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Idle,
+    Movement,
+    Jump,
+    Attack,
+    Special,
+}
+
+/// How an animation behaves while it's the one playing
+pub struct AnimationRules {
+    pub priority: Priority,
+    /// What it can cancel into during its cancel windows, whatever their priority
+    pub cancels_into: &'static [AnimationType],
+    /// Whether walking and running still move the fighter while it plays
+    pub can_steer: bool,
+}
+
+/// Everything the transition table looks at on one simulation tick
+pub struct TransitionContext<'a> {
+    pub input: &'a PlayerInput,
+    pub is_grounded: bool,
+    /// We were falling and touched the ground this tick
+    pub has_landed: bool,
+    pub is_moving: bool,
+    /// Stunned fighters can't start anything themselves
+    pub can_act: bool,
+}
+
+/// One row of the transition table, go to `to` whenever `when` holds and the playing animation allows it
+struct Transition {
+    to: AnimationType,
+    priority: Priority,
+    when: fn(&TransitionContext) -> bool,
+}
+
+// higher priorities are picked first, rows with the same priority are picked in order
+const TRANSITIONS: [Transition; 13] = [
+    Transition {
+        to: AnimationType::SoaringKick,
+        priority: Priority::Special,
+        when: |c| c.can_act && !c.is_grounded && c.input.kick,
+    },
+    Transition {
+        to: AnimationType::Attack3,
+        priority: Priority::Attack,
+        when: |c| c.can_act && c.is_grounded && c.input.kick,
+    },
+    Transition {
+        to: AnimationType::Attack2,
+        priority: Priority::Attack,
+        when: |c| c.can_act && c.is_grounded && c.input.attack_2,
+    },
+    Transition {
+        to: AnimationType::Attack1,
+        priority: Priority::Attack,
+        when: |c| c.can_act && c.is_grounded && c.input.attack_1,
+    },
+    Transition {
+        to: AnimationType::JumpMoving,
+        priority: Priority::Jump,
+        when: |c| c.can_act && c.is_grounded && c.input.jump && !c.input.crouch && c.is_moving,
+    },
+    Transition {
+        to: AnimationType::Jump,
+        priority: Priority::Jump,
+        when: |c| c.can_act && c.is_grounded && c.input.jump && !c.input.crouch && !c.is_moving,
+    },
+    Transition {
+        to: AnimationType::Crouch,
+        priority: Priority::Movement,
+        when: |c| c.can_act && c.input.crouch,
+    },
+    Transition {
+        to: AnimationType::ForwardRun,
+        priority: Priority::Movement,
+        when: |c| c.can_act && c.is_grounded && c.input.right && c.input.run,
+    },
+    Transition {
+        to: AnimationType::ReverseRun,
+        priority: Priority::Movement,
+        when: |c| c.can_act && c.is_grounded && c.input.left && c.input.run,
+    },
+    Transition {
+        to: AnimationType::ForwardWalk,
+        priority: Priority::Movement,
+        when: |c| c.can_act && c.is_grounded && c.input.right,
+    },
+    Transition {
+        to: AnimationType::ReverseWalk,
+        priority: Priority::Movement,
+        when: |c| c.can_act && c.is_grounded && c.input.left,
+    },
+    Transition {
+        to: AnimationType::Landing,
+        priority: Priority::Movement,
+        when: |c| c.has_landed,
+    },
+    Transition {
+        to: AnimationType::Idle,
+        priority: Priority::Idle,
+        when: |c| c.is_grounded && !c.has_landed && c.input.is_idle(),
+    },
+];
+
+pub fn get_rules(anim_type: &AnimationType) -> AnimationRules {
+    match anim_type {
+        AnimationType::Idle => AnimationRules { priority: Priority::Idle, cancels_into: &[], can_steer: true },
+        AnimationType::Crouch
+        | AnimationType::ForwardRun
+        | AnimationType::ReverseRun
+        | AnimationType::ForwardWalk
+        | AnimationType::ReverseWalk
+        | AnimationType::Landing => AnimationRules { priority: Priority::Movement, cancels_into: &[], can_steer: true },
+        AnimationType::Jump | AnimationType::JumpMoving => AnimationRules { priority: Priority::Jump, cancels_into: &[], can_steer: true },
+        // the basic attacks chain into each other during their recovery
+        AnimationType::Attack1 => AnimationRules { priority: Priority::Attack, cancels_into: &[AnimationType::Attack2, AnimationType::Attack3], can_steer: true },
+        AnimationType::Attack2 => AnimationRules { priority: Priority::Attack, cancels_into: &[AnimationType::Attack3], can_steer: true },
+        AnimationType::Attack3 => AnimationRules { priority: Priority::Attack, cancels_into: &[], can_steer: true },
+        // the kick is committed to in the air, but touching down during it lands right away
+        AnimationType::SoaringKick => AnimationRules { priority: Priority::Special, cancels_into: &[AnimationType::Landing], can_steer: false },
+    }
+}
+
+/// The animation to switch to this tick, if any
+pub fn choose_transition(state: &PlayerAnimationState, context: &TransitionContext) -> Option<AnimationType> {
+    let rules = get_rules(&state.anim_type);
+
+    let can_take = |transition: &Transition| {
+        // looping animations are never actively playing, so they always give way
+        !state.actively_playing
+            || transition.priority > rules.priority
+            || (state.can_cancel() && rules.cancels_into.contains(&transition.to))
+    };
+
+    let mut best: Option<&Transition> = None;
+
+    for transition in &TRANSITIONS {
+        // the playing animation's own row competes too, so holding run doesn't drop us into a walk
+        let is_playing = transition.to == state.anim_type;
+
+        if !(transition.when)(context) || !(is_playing || can_take(transition)) {
+            continue;
+        }
+
+        if best.is_none_or(|best| transition.priority > best.priority) {
+            best = Some(transition);
+        }
+    }
+
+    best.map(|transition| transition.to.clone())
+        .filter(|anim_type| *anim_type != state.anim_type)
+}
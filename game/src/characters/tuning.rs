@@ -0,0 +1,187 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use common::animation::{AnimationSequence, AnimationType, CharacterType, PlayerAnimationState};
+
+// where the moveset sources are when the game is run from the repo, like the assets are
+const DEFAULT_MOVESETS_DIR: &str = "common/src/movesets";
+const SEQUENCE_START: &str = "AnimationSequence::new(";
+const ARM_START: &str = "AnimationType::";
+
+/// `--movesets <dir>` is where the tuner saves to, the folder with `fighter.rs` and the others in it
+pub fn parse_movesets_dir(args: &[String]) -> PathBuf {
+    args.iter()
+        .position(|arg| arg == "--movesets")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_MOVESETS_DIR))
+}
+
+/// Writes the numbers of every sequence in `states` over the ones in the character's moveset file,
+/// they take effect in the game, the server and duels once they're rebuilt.
+///
+/// Each animation's sequences are looked for under its own `AnimationType::X =>` arm. Frame counts and events stay as they are
+pub fn save_moveset(movesets_dir: &Path, character_type: &CharacterType, states: &[Rc<RefCell<PlayerAnimationState>>]) -> Result<String, String> {
+    let path = get_moveset_path(movesets_dir, character_type);
+    let mut source = std::fs::read_to_string(&path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+
+    for state in states {
+        let state = state.borrow();
+        source = replace_sequences(&source, &state.anim_type, &state.animation_sequence)
+            .map_err(|e| format!("{} in {}, not saving", e, path.display()))?;
+    }
+
+    std::fs::write(&path, source).map_err(|e| format!("Couldn't save {}: {}", path.display(), e))?;
+
+    Ok(path.display().to_string())
+}
+
+/// `source` with the arguments of every `AnimationSequence::new` in `anim_type`'s arm swapped for the numbers in `sequences`,
+/// they have to line up one to one with the same frame counts
+fn replace_sequences(source: &str, anim_type: &AnimationType, sequences: &[AnimationSequence]) -> Result<String, String> {
+    let name = get_anim_type_name(anim_type);
+    let (arm_start, arm_end) = find_arm(source, &name).ok_or_else(|| format!("No {} arm", name))?;
+
+    let mut replaced = String::with_capacity(source.len());
+    replaced.push_str(&source[..arm_start]);
+
+    let mut rest = &source[arm_start..arm_end];
+    let mut sequences = sequences.iter();
+
+    while let Some(start) = rest.find(SEQUENCE_START) {
+        let args_start = start + SEQUENCE_START.len();
+        let args_end = args_start + find_closing_paren(&rest[args_start..]).ok_or_else(|| format!("Unclosed sequence in {}", name))?;
+        let sequence = sequences.next().ok_or_else(|| format!("{} has more sequences than the tuner", name))?;
+
+        let frames = rest[args_start..args_end].split(',').next().and_then(|frames| frames.trim().parse::<usize>().ok());
+        if frames != Some(sequence.frames) {
+            return Err(format!("{}'s frame counts don't match", name));
+        }
+
+        replaced.push_str(&rest[..args_start]);
+        replaced.push_str(&format_sequence(sequence));
+        rest = &rest[args_end..];
+    }
+
+    if sequences.next().is_some() {
+        return Err(format!("{} has fewer sequences than the tuner", name));
+    }
+
+    replaced.push_str(rest);
+    replaced.push_str(&source[arm_end..]);
+    Ok(replaced)
+}
+
+/// Where `AnimationType::name =>` starts and the next arm does, there has to be exactly one
+fn find_arm(source: &str, name: &str) -> Option<(usize, usize)> {
+    let arms = get_arm_starts(source);
+    let mut matching = arms.iter().enumerate().filter(|(_, (arm_name, _))| *arm_name == name);
+
+    let (i, (_, start)) = matching.next()?;
+    if matching.next().is_some() {
+        return None;
+    }
+
+    let end = arms.get(i + 1).map(|(_, start)| *start).unwrap_or(source.len());
+    Some((*start, end))
+}
+
+/// Every `AnimationType::X =>` in `source`, with where it starts
+fn get_arm_starts(source: &str) -> Vec<(&str, usize)> {
+    let mut arms = vec![];
+    let mut from = 0;
+
+    while let Some(found) = source[from..].find(ARM_START) {
+        let start = from + found;
+        let name_start = start + ARM_START.len();
+        let name_end = source[name_start..]
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .map(|len| name_start + len)
+            .unwrap_or(source.len());
+
+        if source[name_end..].trim_start().starts_with("=>") {
+            arms.push((&source[name_start..name_end], start));
+        }
+
+        from = name_end;
+    }
+
+    arms
+}
+
+/// How far into `args` the `)` closing them is, skipping over any parentheses inside
+fn find_closing_paren(args: &str) -> Option<usize> {
+    let mut depth = 0;
+
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// The arguments `AnimationSequence::new` takes, floats keep their decimal point so they stay f32 literals
+fn format_sequence(sequence: &AnimationSequence) -> String {
+    format!(
+        "{}, {:?}, {:?}, {:?}, {:?}, {:?}, {}, {}",
+        sequence.frames,
+        sequence.fps,
+        sequence.x_movement,
+        sequence.y_movement,
+        sequence.x_accleration,
+        sequence.y_accleration,
+        sequence.height,
+        sequence.width,
+    )
+}
+
+pub fn get_moveset_path(movesets_dir: &Path, character_type: &CharacterType) -> PathBuf {
+    let name = match character_type {
+        CharacterType::Fighter => "fighter",
+        CharacterType::Shinobi => "shinobi",
+        CharacterType::Samurai => "samurai",
+    };
+
+    movesets_dir.join(format!("{}.rs", name))
+}
+
+pub fn get_anim_type_name(anim_type: &AnimationType) -> String {
+    format!("{:?}", anim_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "match anim_type {
+        AnimationType::ForwardRun => (vec![AnimationSequence::new(8, 20.0, 0.0, 0.0, 0.0, 0.0, 93, 28)], true),
+        AnimationType::ReverseRun => (vec![AnimationSequence::new(8, 20.0, (1.0 + 2.0), 0.0, 0.0, 0.0, 93, 28).with_sound(2, SoundEvent::Footstep)], true),
+    }";
+
+    fn get_sequence(frames: usize, fps: f32) -> AnimationSequence {
+        AnimationSequence::new(frames, fps, 0.0, 0.0, 0.0, 0.0, 93, 28)
+    }
+
+    #[test]
+    fn only_changes_the_animations_own_arm() {
+        let replaced = replace_sequences(SOURCE, &AnimationType::ReverseRun, &[get_sequence(8, 12.0)]).unwrap();
+
+        assert!(replaced.contains("ForwardRun => (vec![AnimationSequence::new(8, 20.0,"));
+        assert!(replaced.contains("ReverseRun => (vec![AnimationSequence::new(8, 12.0, 0.0, 0.0, 0.0, 0.0, 93, 28).with_sound(2, SoundEvent::Footstep)]"));
+    }
+
+    #[test]
+    fn refuses_sequences_that_dont_line_up() {
+        assert!(replace_sequences(SOURCE, &AnimationType::ForwardRun, &[get_sequence(6, 20.0)]).is_err());
+        assert!(replace_sequences(SOURCE, &AnimationType::ForwardRun, &[get_sequence(8, 20.0), get_sequence(8, 20.0)]).is_err());
+        assert!(replace_sequences(SOURCE, &AnimationType::Idle, &[get_sequence(8, 20.0)]).is_err());
+    }
+}
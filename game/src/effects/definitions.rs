@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use macroquad::{color::Color, file::load_string, rand::gen_range};
+use nanoserde::DeJson;
+
+/// Picked at random between the two for every particle
+#[derive(DeJson, Clone, Copy, Debug, Default)]
+pub struct RandomRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl RandomRange {
+    pub fn pick(&self) -> f32 {
+        if self.max <= self.min {
+            return self.min;
+        }
+
+        gen_range(self.min, self.max)
+    }
+}
+
+/// How one effect looks, loaded from assets/effects/effects.json
+#[derive(DeJson, Clone, Debug)]
+pub struct EffectDef {
+    pub name: String,
+    /// Particles spawned at once
+    pub count: u32,
+    /// Seconds
+    pub lifetime: RandomRange,
+    /// Pixels a second
+    pub speed: RandomRange,
+    /// Degrees, 0 is the way the fighter faces and 90 is up
+    pub angle: RandomRange,
+    /// Pixels, the particle shrinks from this to `size * end_scale` over its life
+    pub size: RandomRange,
+    #[nserde(default)]
+    pub end_scale: f32,
+    /// Pixels a second squared, pulls the particles down
+    #[nserde(default)]
+    pub gravity: f32,
+    /// How much of their speed particles lose every second, 0 to 1
+    #[nserde(default)]
+    pub drag: f32,
+    /// Draws particles as streaks this many seconds of movement long, 0 draws squares
+    #[nserde(default)]
+    pub stretch: f32,
+    /// Pixels from the fighter's feet, x points the way they face
+    #[nserde(default)]
+    pub offset: Vec<f32>,
+    /// How far from the offset particles start, in pixels
+    #[nserde(default)]
+    pub spread: f32,
+    /// RGBA from 0 to 1, fades into `end_color` over the particle's life
+    pub color: Vec<f32>,
+    #[nserde(default)]
+    pub end_color: Vec<f32>,
+}
+
+impl EffectDef {
+    pub fn get_offset(&self) -> (f32, f32) {
+        (self.offset.first().copied().unwrap_or(0.0), self.offset.get(1).copied().unwrap_or(0.0))
+    }
+
+    pub fn get_color(&self) -> Color {
+        to_color(&self.color)
+    }
+
+    /// The start color faded out if there's no end color
+    pub fn get_end_color(&self) -> Color {
+        if self.end_color.is_empty() {
+            let color = self.get_color();
+            return Color::new(color.r, color.g, color.b, 0.0);
+        }
+
+        to_color(&self.end_color)
+    }
+}
+
+#[derive(DeJson)]
+struct EffectsFile {
+    effects: Vec<EffectDef>,
+}
+
+/// Every effect by name
+pub struct EffectLibrary {
+    effects: HashMap<String, EffectDef>,
+}
+
+impl EffectLibrary {
+    /// A file that's missing or doesn't parse is reported and leaves the game without effects
+    pub async fn load(path: &str) -> Self {
+        let effects = match load_string(path).await {
+            Ok(json) => match EffectsFile::deserialize_json(&json) {
+                Ok(file) => file.effects,
+                Err(e) => {
+                    eprintln!("Couldn't parse {}: {}", path, e);
+                    vec![]
+                }
+            },
+            Err(e) => {
+                eprintln!("Couldn't load {}: {:?}", path, e);
+                vec![]
+            }
+        };
+
+        Self {
+            effects: effects.into_iter().map(|effect| (effect.name.clone(), effect)).collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EffectDef> {
+        self.effects.get(name)
+    }
+}
+
+fn to_color(rgba: &[f32]) -> Color {
+    let channel = |i: usize, default: f32| rgba.get(i).copied().unwrap_or(default);
+    Color::new(channel(0, 1.0), channel(1, 1.0), channel(2, 1.0), channel(3, 1.0))
+}
@@ -0,0 +1,147 @@
+use macroquad::{
+    color::Color,
+    math::{vec2, Rect, Vec2},
+    shapes::{draw_line, draw_rectangle},
+    rand::gen_range,
+};
+
+use common::{animation::Facing, animation_events::AnimationEvent, effects::HIT_EFFECT};
+
+use crate::characters::frame_events::FighterFrameEvents;
+
+use super::definitions::{EffectDef, EffectLibrary};
+
+// a burst of deaths at once shouldn't be able to slow the game down
+const MAX_PARTICLES: usize = 2000;
+
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    end_size: f32,
+    color: Color,
+    end_color: Color,
+    gravity: f32,
+    drag: f32,
+    stretch: f32,
+}
+
+impl Particle {
+    fn get_progress(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/// Every particle alive right now, updated and drawn once a frame in world space
+pub struct ParticleSystem {
+    library: EffectLibrary,
+    particles: Vec<Particle>,
+}
+
+impl ParticleSystem {
+    pub fn new(library: EffectLibrary) -> Self {
+        Self {
+            library,
+            particles: vec![],
+        }
+    }
+
+    /// Spawns the named effect with `feet` as the point its offset is measured from
+    pub fn spawn(&mut self, name: &str, feet: Vec2, facing: &Facing) {
+        let Some(effect) = self.library.get(name) else {
+            eprintln!("There's no effect called {}", name);
+            return;
+        };
+
+        let direction = if *facing == Facing::Left { -1.0 } else { 1.0 };
+        let (offset_x, offset_y) = effect.get_offset();
+        let origin = feet + vec2(offset_x * direction, offset_y);
+
+        for _ in 0..effect.count {
+            if self.particles.len() >= MAX_PARTICLES {
+                return;
+            }
+
+            self.particles.push(new_particle(effect, origin, direction));
+        }
+    }
+
+    /// Spawns the effects a fighter's animation reached, and sparks if they got hit
+    pub fn spawn_fighter_events(&mut self, fighter: &FighterFrameEvents) {
+        if fighter.damage_taken > 0 {
+            self.spawn(HIT_EFFECT, fighter.feet, &fighter.facing);
+        }
+
+        for event in &fighter.events {
+            if let AnimationEvent::SpawnEffect(name) = event {
+                self.spawn(name, fighter.feet, &fighter.facing);
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.particles.iter_mut() {
+            particle.age += dt;
+            particle.velocity.y += particle.gravity * dt;
+            particle.velocity *= (1.0 - particle.drag * dt).max(0.0);
+            particle.position += particle.velocity * dt;
+        }
+
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+    }
+
+    /// Only draws what falls inside `view` (world coordinates)
+    pub fn draw(&self, view: Rect) {
+        for particle in &self.particles {
+            if !view.contains(particle.position) {
+                continue;
+            }
+
+            let t = particle.get_progress();
+            let size = particle.size + (particle.end_size - particle.size) * t;
+            let color = lerp_color(particle.color, particle.end_color, t);
+
+            if particle.stretch > 0.0 {
+                let tail = particle.position - particle.velocity * particle.stretch;
+                draw_line(tail.x, tail.y, particle.position.x, particle.position.y, size, color);
+            } else {
+                draw_rectangle(particle.position.x - size / 2.0, particle.position.y - size / 2.0, size, size, color);
+            }
+        }
+    }
+}
+
+fn new_particle(effect: &EffectDef, origin: Vec2, direction: f32) -> Particle {
+    // screen y points down, so up is a negative angle
+    let angle = effect.angle.pick().to_radians();
+    let speed = effect.speed.pick();
+    let velocity = vec2(angle.cos() * direction, -angle.sin()) * speed;
+
+    let spread = vec2(gen_range(-1.0, 1.0), gen_range(-1.0, 1.0)) * effect.spread;
+    let size = effect.size.pick();
+
+    Particle {
+        position: origin + spread,
+        velocity,
+        age: 0.0,
+        lifetime: effect.lifetime.pick().max(0.01),
+        size,
+        end_size: size * effect.end_scale,
+        color: effect.get_color(),
+        end_color: effect.get_end_color(),
+        gravity: effect.gravity,
+        drag: effect.drag,
+        stretch: effect.stretch,
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::new(
+        from.r + (to.r - from.r) * t,
+        from.g + (to.g - from.g) * t,
+        from.b + (to.b - from.b) * t,
+        from.a + (to.a - from.a) * t,
+    )
+}
@@ -1,30 +1,57 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Instant, vec};
+use std::{cell::RefCell, collections::{hash_map::Entry, HashMap, HashSet}, path::Path, rc::Rc, time::Instant, vec};
 
-use characters::{character::CharacterTrait, character_1::Character1, character_2::Character2, character_3::Character3, server_character::ServerCharacter};
-use common::{animation::{CharacterTextures, Facing}, types::ServerClient};
+use animation_tuner::AnimationTuner;
+use audio::sound_manager::SoundManager;
+use camera::{game_camera::{CameraMode, GameCamera}, screen::Screen};
+use characters::{character::CharacterTrait, character_1::Character1, character_2::Character2, character_3::Character3, cpu::get_ai_view, frame_events::{FighterFrameEvents, FrameEventWatcher}, respawn::{is_out_of_bounds, RespawnState}, server_character::ServerCharacter, tuning::parse_movesets_dir};
+use common::{ai::AiController, animation::{CharacterTextures, CharacterType, Facing}, effects::DEATH_EFFECT, hit::HitProperties, input::PlayerInput, link_conditioner::LinkConditionerConfig, maps::MAP_FILES, match_rules::MatchPhase, replay::Replay, spawn::choose_spawn_point, types::{JoinType, ServerClient}};
 
 use macroquad::prelude::*;
-use macroquad_tiled::{self as tiled, Map};
 use macroquad_platformer::*;
-use server::ServerConnection;
+use effects::{definitions::EffectLibrary, particles::ParticleSystem};
+use rollback::{config::RollbackConfig, duel::Duel, session::RollbackSession};
+use server::{ConnectionStatus, ServerConnection};
+use replay_viewer::{get_free_camera_input, ReplayViewer};
+use simulation::FixedStep;
 
 
 use common::constants::*;
-use maps::map::GameMap;
-use ui::main_menu::{MenuState, CharacterSelection};
+use maps::{background::{parallax_layer_defs, BackgroundLayerDef, TimeOfDay}, map::GameMap};
+use ui::{connection_status::draw_connection_status, hud::{draw_hud, HudPlayer}, lobby::{draw_lobby, LobbyAction}, main_menu::{MenuState, CharacterSelection, OpponentSelection}, match_hud::{draw_match_results, draw_match_status}, nameplate::draw_nameplate, player_feed::draw_player_feed};
 
+mod animation_tuner;
+mod audio;
+mod camera;
 mod characters;
+mod effects;
 mod maps;
+mod rollback;
 mod types;
 mod ui;
+mod replay_viewer;
 mod server;
+mod simulation;
 
 enum GameState<'a> {
     Menu,
+    /// Connected to a server and waiting for everyone to ready up
+    Lobby,
     Game(&'a GameMap),
+    /// A peer to peer duel started from the command line
+    Rollback(&'a GameMap),
+    /// Watching a recorded match, also started from the command line
+    Replay(&'a GameMap),
+    /// Connected to a server as a spectator while a match runs
+    Spectate(&'a GameMap),
+    /// Single player against a CPU
+    VersusCpu(&'a GameMap),
+    /// Previewing and tuning animations, started from the command line
+    AnimationTuner,
 }
 
 const USE_HITBOXES: bool = false;
+// the CPU picks one of these at random
+const CPU_CHARACTERS: [CharacterType; 3] = [CharacterType::Fighter, CharacterType::Shinobi, CharacterType::Samurai];
 // this is how often we send client updates to the server
 const CLIENT_UPDATE_INTERVAL_SECONDS: f32 = 0.01;
 // this is how often the server sends updates to the clients
@@ -37,29 +64,105 @@ async fn main() {
     // TEXTURES AND WORLD BUILDING
     let character_textures = Rc::new(CharacterTextures::load_all().await);
     let splash_background = load_texture("assets/spritesheets/splash.png").await.unwrap();
-    let tiled_map = load_map().await;
-    let static_colliders = load_static_colliders(&tiled_map).await;
+    let mut sound_manager = SoundManager::load().await;
+    let mut frame_watcher = FrameEventWatcher::new();
+    let mut particles = ParticleSystem::new(EffectLibrary::load("assets/effects/effects.json").await);
 
+    // the world's colliders get loaded from the selected map when the game starts
     let world = Rc::new(RefCell::new(World::new()));
-    world.borrow_mut().add_static_tiled_layer(static_colliders, 32., 32., 40, 1);
 
     // Default my character to be Character1
     let mut my_character: Box<dyn CharacterTrait> = 
-        Box::new(Character1::new(300.0, 50.0, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(&world), None).await);
+        Box::new(Character1::new(300.0, 50.0, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(&world), None));
 
     // only used in multiplayer
     let mut server_characters: HashMap<u64, ServerCharacter> = HashMap::new();
+    // actors of players who left, the world can't remove them so new players get them instead
+    let mut spare_actors: Vec<Actor> = vec![];
 
     let maps = get_maps().await;
     let mut game_state = GameState::Menu;
     let mut menu_state = MenuState::new();
     let mut is_multiplayer = false;
     let mut client_server_update_timer = 0.0;
+    let mut camera = GameCamera::new(CameraMode::FollowPlayer);
+    let mut screen = Screen::new();
+    let mut respawn_state = RespawnState::new();
+    let mut fixed_step = FixedStep::new();
+    // who the current attack already connected with, so it only hits each of them once
+    let mut already_hit: HashSet<u64> = HashSet::new();
+
+    // `--rollback` skips the menu and goes straight into a peer to peer duel
+    let mut rollback_session: Option<RollbackSession> = None;
+    let mut duel: Option<Duel> = None;
+    // drives player 2 of the duel in single player CPU fights
+    let mut cpu_controller: Option<AiController> = None;
+    let args: Vec<String> = std::env::args().collect();
+    // `--latency` and co make our connection to the server a bad one
+    let link_conditioner = LinkConditionerConfig::from_args(&args);
+
+    if let Some(config) = RollbackConfig::from_args(&args) {
+        match (RollbackSession::new(&config), maps.get(config.map_index)) {
+            (Ok(session), Some(map)) => {
+                rollback_session = Some(session);
+                game_state = GameState::Rollback(map);
+            }
+            (Err(e), _) => eprintln!("Couldn't start the rollback session: {:?}", e),
+            (_, None) => eprintln!("There's no map {}", config.map_index),
+        }
+    }
+
+    // who a spectator's camera follows, None frames the whole arena
+    let mut spectate_target: Option<u64> = None;
+
+    // `--replay <file>` watches a match the server recorded
+    let mut replay_viewer: Option<ReplayViewer> = None;
+
+    if let Some(path) = args.iter().position(|arg| arg == "--replay").and_then(|i| args.get(i + 1)) {
+        match Replay::load(Path::new(path)) {
+            Ok(replay) => match maps.iter().find(|map| map.get_name() == replay.get_header().map) {
+                Some(map) => {
+                    load_map_world(map, &world);
+                    camera.mode = CameraMode::FrameAll;
+                    replay_viewer = Some(ReplayViewer::new(replay));
+                    game_state = GameState::Replay(map);
+                }
+                None => eprintln!("The replay is on {}, which we don't have", replay.get_header().map),
+            },
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // `--tune-animations` opens the animation preview instead of the game, `--movesets` is where it saves to
+    let mut animation_tuner: Option<AnimationTuner> = None;
+
+    if args.iter().any(|arg| arg == "--tune-animations") {
+        let tuner = AnimationTuner::new(parse_movesets_dir(&args));
+        camera.mode = CameraMode::Free;
+        camera.snap_to(tuner.get_stage_view().center(), tuner.get_stage_view());
+        animation_tuner = Some(tuner);
+        game_state = GameState::AnimationTuner;
+    }
 
     loop {
         let dt = get_frame_time();
         client_server_update_timer += dt;
 
+        screen.handle_input();
+
+        // what every fighter's animation reached this frame, sounds and effects are played from it after drawing
+        let mut fighter_events: Vec<FighterFrameEvents> = vec![];
+        particles.update(dt);
+
+        // each map has its own music, the menus are quiet
+        let music = match game_state {
+            GameState::Menu | GameState::Lobby | GameState::AnimationTuner => None,
+            GameState::Game(map) | GameState::Rollback(map) | GameState::Replay(map) | GameState::Spectate(map) | GameState::VersusCpu(map) => map.get_music(),
+        };
+
+        sound_manager.set_volume(menu_state.volume);
+        sound_manager.play_music(music).await;
+
         match game_state {
             GameState::Menu => {
                 clear_background(BLACK);
@@ -71,44 +174,233 @@ async fn main() {
 
                 ui::main_menu::draw_menu(&splash_background, &maps, &mut menu_state, callback).await;
                 
+                // connect to server and wait in the lobby, the map and character get picked there
+                if menu_state.connect_pressed {
+                    let join_type = if menu_state.is_spectating { JoinType::Spectator } else { JoinType::Player };
+
+                    match ServerConnection::new(&menu_state.server_address, &menu_state.player_name, join_type, &link_conditioner) {
+                        Ok(connection) => {
+                            server = Some(connection);
+                            is_multiplayer = true;
+                            game_state = GameState::Lobby;
+                        }
+                        Err(e) => {
+                            menu_state.connect_error = Some(e);
+                            menu_state.connect_pressed = false;
+                        }
+                    }
+                }
                 // Check if we need to transition to the game
-                if let Some(map_index) = menu_state.map_selection {
-                    if let Some(map) = maps.get(map_index) {
-                        if let Some(character) = &menu_state.character_selection {
-
-                            // connect to server and set up client variables
-                            if menu_state.connect_pressed {
-                                server = Some(ServerConnection::new(&menu_state.server_address));
-                                is_multiplayer = true;
-
-                                if let Some(server) = &server {
-                                    let client_id = server.get_client_id();
-                                    add_my_character(character, &mut my_character, &world, 300.0, 50.0, Some(client_id)).await;
-                                }
-                            } else {
-                                add_my_character(character, &mut my_character, &world, 300.0, 50.0, None).await;
+                else if let Some(map_index) = menu_state.map_selection {
+                    let opponent = menu_state.opponent_selection.clone();
+
+                    match (maps.get(map_index), &menu_state.character_selection, opponent) {
+                        // a CPU fight is a local duel with the CPU driving player 2
+                        (Some(map), Some(character), Some(OpponentSelection::Cpu(difficulty))) => {
+                            let cpu_character = CPU_CHARACTERS[macroquad::rand::gen_range(0, CPU_CHARACTERS.len())].clone();
+                            let new_duel = start_duel(map, 0, &character.get_character_type(), &cpu_character, &world);
+
+                            camera.snap_to(new_duel.get_fighter(0).get_position(), map.get_bounds());
+                            duel = Some(new_duel);
+                            cpu_controller = Some(AiController::new(difficulty));
+
+                            game_state = GameState::VersusCpu(map);
+                        }
+                        (Some(map), Some(character), _) => {
+                            enter_map(map, character, &mut my_character, &mut server_characters, &mut spare_actors, &world, None);
+
+                            respawn_state.on_respawned();
+                            camera.snap_to(my_character.get_position(), map.get_bounds());
+
+                            game_state = GameState::Game(map);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            GameState::Lobby => {
+                clear_background(BLACK);
+
+                let mut left_lobby = false;
+
+                if let Some(server) = server.as_mut() {
+                    server.handle_server_updates().await;
+
+                    if let Some(reason) = get_disconnect_reason(server) {
+                        menu_state.connect_error = Some(reason);
+                        left_lobby = true;
+                    }
+
+                    let actions = draw_lobby(
+                        &splash_background,
+                        server.get_lobby_state(),
+                        server.get_match_state(),
+                        server.get_client_id(),
+                        server.is_spectator(),
+                        &maps,
+                    );
+
+                    for action in actions {
+                        match action {
+                            LobbyAction::SelectCharacter(character_type) => server.select_character(character_type),
+                            LobbyAction::VoteMap(map_index) => server.vote_map(map_index),
+                            LobbyAction::SelectMatchMode(mode) => server.select_match_mode(mode),
+                            LobbyAction::SetReady(is_ready) => server.set_ready(is_ready),
+                            LobbyAction::Leave => {
+                                server.disconnect();
+                                left_lobby = true;
                             }
                         }
+                    }
+
+                    let client_id = server.get_client_id();
+                    let match_started = server
+                        .get_match_state()
+                        .is_some_and(|match_state| match_state.phase != MatchPhase::Lobby);
+
+                    // maps are looked up by name, if we don't have it we just keep waiting
+                    let lobby_map = server
+                        .get_lobby_state()
+                        .and_then(|lobby| lobby.current_map.as_ref())
+                        .and_then(|map_name| maps.iter().find(|map| map.get_name() == *map_name));
+
+                    let character = server
+                        .get_lobby_state()
+                        .and_then(|lobby| lobby.players.get(&client_id))
+                        .map(|player| CharacterSelection::from_character_type(&player.character_type));
+
+                    draw_player_feed(server.get_player_feed());
+                    draw_connection_status(server.get_status(), server.get_server_addr());
+
+                    if match_started && !left_lobby && server.is_spectator() {
+                        if let Some(map) = lobby_map {
+                            load_map_world(map, &world);
+                            server_characters.clear();
+                            spare_actors.clear();
+
+                            spectate_target = None;
+                            camera.mode = CameraMode::FrameAll;
+                            camera.snap_to(map.get_bounds().center(), map.get_bounds());
+
+                            game_state = GameState::Spectate(map);
+                        }
+                    } else if match_started && !left_lobby {
+                        if let (Some(map), Some(character)) = (lobby_map, character) {
+                            enter_map(map, &character, &mut my_character, &mut server_characters, &mut spare_actors, &world, Some(client_id));
 
+                            // start on the first spawn point until the server moves us somewhere free
+                            server.request_spawn();
+                            respawn_state.on_respawned();
+                            camera.snap_to(my_character.get_position(), map.get_bounds());
 
-                        game_state = GameState::Game(map);
+                            game_state = GameState::Game(map);
+                        }
                     }
+                } else {
+                    left_lobby = true;
+                }
+
+                if left_lobby {
+                    server = None;
+                    is_multiplayer = false;
+                    menu_state.back();
+                    game_state = GameState::Menu;
                 }
             }
             GameState::Game(map) => {
-                map.draw_map();
+                if is_key_pressed(KeyCode::Tab) {
+                    camera.toggle_mode();
+                }
+
+                // remote positions are from last frame, which is close enough for framing
+                let fighter_positions: Vec<Vec2> = server_characters
+                    .values()
+                    .map(|sc| vec2(sc.x_pos, sc.y_pos))
+                    .collect();
+
+                camera.update(dt, my_character.get_position(), &fighter_positions, map.get_bounds());
+                camera.set_active(screen.get_render_target());
+                clear_background(BLACK);
+
+                map.draw_map(camera.get_view_rect());
+
+                // falling off the map kills you until the respawn timer runs out
+                if respawn_state.is_alive() && is_out_of_bounds(my_character.get_position(), map.get_bounds()) {
+                    respawn_state.kill();
+
+                    match server.as_mut() {
+                        // the server shows everyone the burst
+                        Some(server) => server.report_died(),
+                        None => particles.spawn(DEATH_EFFECT, clamp_to_bounds(my_character.get_position(), map.get_bounds()), &Facing::Right),
+                    }
+                }
+
+                if respawn_state.update(dt) {
+                    match server.as_mut() {
+                        // the server knows where everyone is so it gets to choose
+                        Some(server) => server.request_spawn(),
+                        None => {
+                            let occupied: Vec<(f32, f32)> = fighter_positions.iter().map(|p| (p.x, p.y)).collect();
+
+                            if let Some((x, y)) = choose_spawn_point(&map.get_spawn_points(), &occupied) {
+                                my_character.respawn(vec2(x, y));
+                                respawn_state.on_respawned();
+                            }
+                        }
+                    }
+                }
+
+                if let Some((x, y)) = server.as_mut().and_then(|server| server.take_assigned_spawn()) {
+                    my_character.respawn(vec2(x, y));
+                    respawn_state.on_respawned();
+                }
+
+                // during the countdown, results or after being knocked out you can't move
+                let can_play = match server.as_ref() {
+                    Some(server) => server
+                        .get_match_state()
+                        .is_none_or(|match_state| match_state.can_play(server.get_client_id())),
+                    None => true,
+                };
+
+                // the simulation runs at a fixed rate, so a frame can take zero or several ticks of it
+                let sim_steps = fixed_step.advance(dt);
+                let input = PlayerInput::from_keyboard();
 
                 // draw update MY character
-                render_update_my_character(my_character.as_mut(), dt, &world, &character_textures);
+                if respawn_state.is_alive() {
+                    render_update_my_character(my_character.as_mut(), &input, sim_steps, &world, &character_textures, respawn_state.is_invulnerable(), can_play);
+
+                    let my_client_id = server.as_ref().map(|server| server.get_client_id());
+                    let my_health = server
+                        .as_ref()
+                        .zip(my_client_id)
+                        .and_then(|(server, client_id)| server.get_server_clients().get(&client_id))
+                        .map_or(MAX_HEALTH, |sc| sc.health);
+
+                    fighter_events.extend(frame_watcher.watch_character(my_client_id.unwrap_or(0), my_character.as_ref(), my_health));
+                }
                 
                 if is_multiplayer {
                     // check if we have ALL the variables needed
                     if let Some(server) = server.as_mut() {
                         server.handle_server_updates().await;
+                        spawn_server_effects(&mut particles, server, map.get_bounds());
+
+                        for incoming in server.take_hits() {
+                            my_character.apply_hit(&incoming.hit, &incoming.attacker_facing);
+
+                            // the server already counted the KO so we don't report it
+                            if incoming.is_fatal && respawn_state.is_alive() {
+                                respawn_state.kill();
+                            }
+                        }
 
                         // we don't want to send updates every frame
                         if client_server_update_timer >= CLIENT_UPDATE_INTERVAL_SECONDS {
-                            server.handle_client_updates(&my_character).await;
+                            // the server skips hits on us while we're in an invulnerability window too
+                            let is_invulnerable = respawn_state.is_invulnerable() || my_character.is_invulnerable();
+                            server.handle_client_updates(&my_character, is_invulnerable).await;
 
                             // reset our time
                             client_server_update_timer = 0.0;
@@ -122,24 +414,391 @@ async fn main() {
                         let t = (duration.as_secs_f32() / SERVER_UPDATE_FREQUENCY_SECONDS).clamp(0.0, 1.0);
 
                         render_update_server_characters(
-                            server.get_client_id(), 
+                            Some(server.get_client_id()), 
                             &server_clients, 
                             &mut server_characters, 
+                            &mut spare_actors,
                             &character_textures, 
                             &world, 
                             t
                         ).await;
+
+                        fighter_events.extend(frame_watcher.watch_remote_fighters(server_clients, Some(server.get_client_id())));
+
+                        match my_character.get_active_hit() {
+                            Some(hit) if respawn_state.is_alive() => {
+                                // the server checks the hit against where the victims were on our screen
+                                let view_tick = server.get_view_tick(t);
+
+                                for victim in find_hit_victims(&hit, my_character.as_ref(), &server_characters, &world, &already_hit) {
+                                    already_hit.insert(victim);
+                                    server.report_hit(victim, view_tick);
+                                    my_character.on_hit_landed(&hit);
+                                }
+                            }
+                            // the attack is over, the next one can hit everyone again
+                            _ => already_hit.clear(),
+                        }
+                    }
+                }
+
+                particles.draw(camera.get_view_rect());
+                screen.present();
+
+                let hud_players = get_hud_players(server.as_ref(), my_character.as_ref(), &menu_state.player_name);
+                let time_left = server.as_ref().and_then(|server| server.get_match_state()).and_then(|match_state| match_state.time_left);
+                let network_info = server.as_ref().map(|server| server.get_network_info());
+                let link_conditioner = server.as_ref().map(|server| server.get_link_conditioner());
+                draw_hud(&hud_players, &character_textures, time_left, network_info.as_ref(), link_conditioner);
+
+                // match overlays are drawn on top in screen space
+                if let Some(server) = server.as_ref() {
+                    if let Some(match_state) = server.get_match_state() {
+                        draw_match_status(match_state, server.get_client_id());
+
+                        // the match is over and the results are gone, back to the lobby
+                        if match_state.phase == MatchPhase::Lobby {
+                            game_state = GameState::Lobby;
+                        }
+                    }
+
+                    if let Some(results) = server.get_match_results() {
+                        draw_match_results(results);
                     }
+
+                    draw_player_feed(server.get_player_feed());
+                    draw_connection_status(server.get_status(), server.get_server_addr());
+                }
+
+                // reconnecting didn't work out, back to the connect dialog with the reason
+                if let Some(reason) = server.as_ref().and_then(get_disconnect_reason) {
+                    menu_state.connect_error = Some(reason);
+                    server = None;
+                    is_multiplayer = false;
+                    menu_state.back();
+                    game_state = GameState::Menu;
                 }
             }
+            GameState::Rollback(map) => {
+                if let Some(session) = rollback_session.as_mut() {
+                    session.poll();
+
+                    // both fighters get built once we know who the peer picked
+                    if duel.is_none() {
+                        if let Some(remote_character) = session.get_remote_character().cloned() {
+                            let local_player = session.get_local_player();
+                            let new_duel = start_duel(map, local_player, session.get_local_character(), &remote_character, &world);
+
+                            camera.snap_to(new_duel.get_fighter(local_player).get_position(), map.get_bounds());
+                            duel = Some(new_duel);
+                        }
+                    }
+
+                    match duel.as_mut() {
+                        Some(duel) => {
+                            for _ in 0..fixed_step.advance(dt) {
+                                session.add_local_input(PlayerInput::from_keyboard());
+                                // if we're waiting on the peer this frame just gets tried again next tick
+                                session.advance(duel);
+                            }
+                            session.send_inputs();
+
+                            let local_player = session.get_local_player();
+                            let focus = duel.get_fighter(local_player).get_position();
+                            let opponent = duel.get_fighter(1 - local_player).get_position();
+
+                            camera.update(dt, focus, &[opponent], map.get_bounds());
+                            camera.set_active(screen.get_render_target());
+                            clear_background(BLACK);
+
+                            map.draw_map(camera.get_view_rect());
+
+                            draw_duel_fighters(duel, &world, &character_textures);
+                            fighter_events.extend(watch_duel_fighters(&mut frame_watcher, duel));
+                            particles.draw(camera.get_view_rect());
+
+                            screen.present();
+
+                            draw_hud(&get_duel_hud_players(duel, local_player), &character_textures, None, None, None);
+
+                            let stats = format!("frame {}   rolled back {} frames", session.get_frame(), session.get_rollback_frames());
+                            draw_text(&stats, 16.0, 30.0, 20.0, WHITE);
+                        }
+                        None => {
+                            clear_background(BLACK);
+                            draw_text("Waiting for the other player...", 16.0, 30.0, 30.0, WHITE);
+                        }
+                    }
+                }
+
+                // esc leaves the duel, dropping the session is what tells the peer
+                let peer_left = rollback_session.as_ref().is_some_and(|session| session.has_peer_left());
+                if peer_left || is_key_pressed(KeyCode::Escape) {
+                    if peer_left {
+                        println!("The other player left the duel");
+                    }
+
+                    rollback_session = None;
+                    duel = None;
+                    game_state = GameState::Menu;
+                }
+            }
+            GameState::Spectate(map) => {
+                let mut left = false;
+
+                if let Some(server) = server.as_mut() {
+                    server.handle_server_updates().await;
+                    spawn_server_effects(&mut particles, server, map.get_bounds());
+
+                    let server_clients = server.get_server_clients();
+
+                    // tab cycles through the players and then back to the whole arena
+                    if is_key_pressed(KeyCode::Tab) {
+                        spectate_target = get_next_spectate_target(spectate_target, server_clients);
+                    }
+
+                    let target_position = spectate_target
+                        .and_then(|client_id| server_clients.get(&client_id))
+                        .map(|sc| vec2(sc.x_pos, sc.y_pos));
+
+                    // whoever we were following left
+                    if target_position.is_none() {
+                        spectate_target = None;
+                    }
+
+                    camera.mode = if target_position.is_some() { CameraMode::FollowPlayer } else { CameraMode::FrameAll };
+
+                    let positions: Vec<Vec2> = server_clients.values().map(|sc| vec2(sc.x_pos, sc.y_pos)).collect();
+                    let focus = target_position
+                        .or(positions.first().copied())
+                        .unwrap_or(map.get_bounds().center());
+
+                    camera.update(dt, focus, &positions, map.get_bounds());
+                    camera.set_active(screen.get_render_target());
+                    clear_background(BLACK);
+
+                    map.draw_map(camera.get_view_rect());
+
+                    let now = Instant::now();
+                    let duration = now - server.get_last_server_updated();
+                    let t = (duration.as_secs_f32() / SERVER_UPDATE_FREQUENCY_SECONDS).clamp(0.0, 1.0);
+
+                    render_update_server_characters(
+                        None,
+                        server_clients,
+                        &mut server_characters,
+                        &mut spare_actors,
+                        &character_textures,
+                        &world,
+                        t,
+                    ).await;
+
+                    fighter_events.extend(frame_watcher.watch_remote_fighters(server_clients, None));
+                    particles.draw(camera.get_view_rect());
+
+                    screen.present();
+
+                    let time_left = server.get_match_state().and_then(|match_state| match_state.time_left);
+                    let network_info = server.get_network_info();
+                    draw_hud(&get_remote_hud_players(server_clients), &character_textures, time_left, Some(&network_info), Some(server.get_link_conditioner()));
+
+                    let following = spectate_target
+                        .and_then(|client_id| server_clients.get(&client_id))
+                        .map(|sc| format!("Following {}", sc.name))
+                        .unwrap_or_else(|| "Whole arena".to_string());
+                    draw_text(&format!("Spectating - {}   tab switch camera   esc leave", following), 16.0, screen_height() - 16.0, 20.0, WHITE);
+
+                    if let Some(match_state) = server.get_match_state() {
+                        draw_match_status(match_state, server.get_client_id());
+
+                        if match_state.phase == MatchPhase::Lobby {
+                            game_state = GameState::Lobby;
+                        }
+                    }
+
+                    if let Some(results) = server.get_match_results() {
+                        draw_match_results(results);
+                    }
+
+                    draw_player_feed(server.get_player_feed());
+                    draw_connection_status(server.get_status(), server.get_server_addr());
+
+                    if let Some(reason) = get_disconnect_reason(server) {
+                        menu_state.connect_error = Some(reason);
+                        left = true;
+                    } else if is_key_pressed(KeyCode::Escape) {
+                        server.disconnect();
+                        left = true;
+                    }
+                } else {
+                    left = true;
+                }
+
+                if left {
+                    server = None;
+                    is_multiplayer = false;
+                    menu_state.back();
+                    game_state = GameState::Menu;
+                }
+            }
+            GameState::VersusCpu(map) => {
+                if let (Some(duel), Some(cpu)) = (duel.as_mut(), cpu_controller.as_mut()) {
+                    for _ in 0..fixed_step.advance(dt) {
+                        let view = get_ai_view(duel.get_fighter(1), &[duel.get_fighter(0)], &world);
+                        let inputs = [PlayerInput::from_keyboard(), cpu.update(&view)];
+                        duel.step(&inputs);
+                    }
+
+                    let focus = duel.get_fighter(0).get_position();
+                    let opponent = duel.get_fighter(1).get_position();
+
+                    camera.update(dt, focus, &[opponent], map.get_bounds());
+                    camera.set_active(screen.get_render_target());
+                    clear_background(BLACK);
+
+                    map.draw_map(camera.get_view_rect());
+                    draw_duel_fighters(duel, &world, &character_textures);
+                    fighter_events.extend(watch_duel_fighters(&mut frame_watcher, duel));
+                    particles.draw(camera.get_view_rect());
+
+                    screen.present();
+
+                    let mut hud_players = get_duel_hud_players(duel, 0);
+                    hud_players[1].name = format!("CPU ({}) - {}", cpu.get_difficulty().get_description(), duel.get_score(1));
+                    draw_hud(&hud_players, &character_textures, None, None, None);
+                }
+
+                // back to picking a map, with the same character and opponent still picked
+                if is_key_pressed(KeyCode::Escape) {
+                    duel = None;
+                    cpu_controller = None;
+                    menu_state.back();
+                    game_state = GameState::Menu;
+                }
+            }
+            GameState::Replay(map) => {
+                if let Some(viewer) = replay_viewer.as_mut() {
+                    viewer.handle_input();
+                    viewer.update(dt);
+
+                    // tab swaps between framing everyone and flying around freely
+                    if is_key_pressed(KeyCode::Tab) {
+                        camera.mode = match camera.mode {
+                            CameraMode::Free => CameraMode::FrameAll,
+                            _ => CameraMode::Free,
+                        };
+                    }
+
+                    if camera.mode == CameraMode::Free {
+                        let (offset, zoom) = get_free_camera_input(dt);
+                        camera.pan(offset, zoom, map.get_bounds());
+                    }
+
+                    let clients = viewer.get_clients();
+                    let positions: Vec<Vec2> = clients.values().map(|sc| vec2(sc.x_pos, sc.y_pos)).collect();
+                    let focus = positions.first().copied().unwrap_or(map.get_bounds().center());
+
+                    camera.update(dt, focus, &positions, map.get_bounds());
+                    camera.set_active(screen.get_render_target());
+                    clear_background(BLACK);
+
+                    map.draw_map(camera.get_view_rect());
+
+                    // nobody is the local player in a replay
+                    render_update_server_characters(
+                        None,
+                        &clients,
+                        &mut server_characters,
+                        &mut spare_actors,
+                        &character_textures,
+                        &world,
+                        viewer.get_lerp_t(),
+                    ).await;
+
+                    fighter_events.extend(frame_watcher.watch_remote_fighters(&clients, None));
+                    particles.draw(camera.get_view_rect());
+
+                    screen.present();
+
+                    draw_hud(&get_remote_hud_players(&clients), &character_textures, None, None, None);
+                    draw_text(&viewer.get_status(), 16.0, 30.0, 24.0, WHITE);
+                    draw_text("space pause   left/right seek   up/down speed   tab free camera (wasd, wheel)", 16.0, 56.0, 18.0, WHITE);
+
+                    if viewer.is_finished() {
+                        if let Some(results) = viewer.get_replay().get_results() {
+                            draw_match_results(results);
+                        }
+                    }
+                }
+            }
+            GameState::AnimationTuner => {
+                if let Some(tuner) = animation_tuner.as_mut() {
+                    tuner.handle_input();
+
+                    for _ in 0..fixed_step.advance(dt) {
+                        tuner.update();
+                    }
+
+                    camera.set_active(screen.get_render_target());
+                    clear_background(DARKGRAY);
+
+                    let (position, size) = tuner.get_collider();
+                    let texture = character_textures.get_texture(&tuner.get_character_type(), &tuner.get_anim_type());
+                    draw_sprite(&texture, position, size, tuner.get_facing(), tuner.get_sprite_frame(), WHITE);
+                    tuner.draw_overlays();
+
+                    screen.present();
+
+                    tuner.draw_ui();
+                }
+            }
+        }
+
+        // sounds are as loud as they are close to the middle of the screen
+        sound_manager.set_listener(camera.get_view_rect().center());
+
+        for fighter in &fighter_events {
+            sound_manager.play_fighter_events(fighter);
+            particles.spawn_fighter_events(fighter);
         }
 
+        frame_watcher.end_frame();
+
+        // menus and overlays are drawn in screen space
+        set_default_camera();
+
         next_frame().await
     }
 }
 
 
-async fn add_my_character(
+/// Starts from a fresh world with only this map's colliders in it
+fn load_map_world(map: &GameMap, world: &Rc<RefCell<World>>) {
+    *world.borrow_mut() = World::new();
+    map.load_colliders(&mut world.borrow_mut());
+}
+
+/// Loads `map` into a fresh world and puts my character on its first spawn point
+fn enter_map(
+    map: &GameMap,
+    character_selection: &CharacterSelection,
+    my_character: &mut Box<dyn CharacterTrait>,
+    server_characters: &mut HashMap<u64, ServerCharacter>,
+    spare_actors: &mut Vec<Actor>,
+    world: &Rc<RefCell<World>>,
+    client_id: Option<u64>,
+) {
+    load_map_world(map, world);
+
+    // their actors lived in the old world, they get added back on the next server update
+    server_characters.clear();
+    spare_actors.clear();
+
+    let (spawn_x, spawn_y) = map.get_spawn_points()[0];
+    add_my_character(character_selection, my_character, world, spawn_x, spawn_y, client_id);
+}
+
+fn add_my_character(
     character_selection: &CharacterSelection, 
     my_character: &mut Box<dyn CharacterTrait>, 
     world: &Rc<RefCell<World>>, 
@@ -147,84 +806,137 @@ async fn add_my_character(
     y_pos: f32,
     client_id: Option<u64>
 ) {
+    *my_character = new_character(character_selection, world, x_pos, y_pos, client_id);
+}
+
+fn new_character(
+    character_selection: &CharacterSelection,
+    world: &Rc<RefCell<World>>,
+    x_pos: f32,
+    y_pos: f32,
+    client_id: Option<u64>
+) -> Box<dyn CharacterTrait> {
     match character_selection {
         CharacterSelection::Character1 => {
-            *my_character = Box::new(Character1::new(x_pos, y_pos, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(world), client_id).await);
+            Box::new(Character1::new(x_pos, y_pos, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(world), client_id))
         },
         CharacterSelection::Character2 => {
-            *my_character = Box::new(Character2::new(x_pos, y_pos, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(world), client_id).await);
+            Box::new(Character2::new(x_pos, y_pos, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(world), client_id))
         },
         CharacterSelection::Character3 => {
-            *my_character = Box::new(Character3::new(x_pos, y_pos, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(world), client_id).await);
+            Box::new(Character3::new(x_pos, y_pos, DEFAULT_PLAYER_WIDTH, DEFAULT_PLAYER_HEIGHT, Rc::clone(world), client_id))
         },
     }
 }
 
-async fn get_maps() -> Vec<GameMap> {
-    vec![
-        GameMap::new(
-            "First Map".to_owned(),
-            "assets/spritesheets/bg_night_tokyo.png".to_owned(),
-            "assets/maps/tilesets/exclusion-zone-tileset/1 Tiles/Tileset.png".to_owned(),
-            "assets/maps/map_01.json".to_owned(),
-            "Tileset.png".to_owned(),
-            vec!["Platforms".to_owned()],
-        ).await,
-    ]
-}
+/// Loads `map` for a duel and puts the two fighters on its first two spawn points
+fn start_duel(
+    map: &GameMap,
+    local_player: usize,
+    local_character: &CharacterType,
+    remote_character: &CharacterType,
+    world: &Rc<RefCell<World>>,
+) -> Duel {
+    load_map_world(map, world);
 
-async fn load_map() -> Map {
-    let tileset = load_texture("assets/maps/tilesets/exclusion-zone-tileset/1 Tiles/Tileset.png").await.unwrap();
-    tileset.set_filter(FilterMode::Nearest);
+    // on a map with a single spawn point both fighters start on it
+    let spawn_points = map.get_spawn_points();
+    let spawns = [0, 1].map(|i| {
+        let (x, y) = spawn_points[i.min(spawn_points.len() - 1)];
+        vec2(x, y)
+    });
 
-    let tiled_map_json = load_string("assets/maps/map_01.json").await.unwrap();
-    tiled::load_map(
-        &tiled_map_json,
-        &[("Tileset.png", tileset)],
-        &[]
-    ).unwrap()
+    // player 0 is whoever passed `--player 0`, both sides have to agree on the order
+    let mut character_types = [local_character, remote_character];
+    if local_player == 1 {
+        character_types.swap(0, 1);
+    }
+
+    let selections = character_types.map(CharacterSelection::from_character_type);
+    let fighter_0 = new_character(&selections[0], world, spawns[0].x, spawns[0].y, None);
+    let fighter_1 = new_character(&selections[1], world, spawns[1].x, spawns[1].y, None);
+
+    Duel::new([fighter_0, fighter_1], spawns, map.get_bounds())
 }
 
-async fn load_static_colliders(tiled_map: &Map) -> Vec<Tile> { 
-    let mut static_colliders = vec![];
-    for (_x, _y, tile) in tiled_map.tiles("Platforms", None) {
-        static_colliders.push(if tile.is_some() {
-            Tile::Solid
-        } else {
-            Tile::Empty
-        });
+async fn get_maps() -> Vec<GameMap> {
+    let mut maps = vec![];
+
+    // the names and tiled files come from common, so the server reads spawn points out of the same files we play on
+    for (name, file) in MAP_FILES {
+        let map = match name {
+            "First Map" => GameMap::new(
+                name.to_owned(),
+                vec![BackgroundLayerDef::new("assets/spritesheets/bg_night_tokyo.png".to_owned(), 0.0, false)],
+                "assets/maps/tilesets/exclusion-zone-tileset/1 Tiles/Tileset.png".to_owned(),
+                file.to_owned(),
+                "Tileset.png".to_owned(),
+                vec!["Platforms".to_owned()],
+                "Platforms".to_owned(),
+            ).await.with_music("assets/music/first_map.wav".to_owned()),
+            "Green Zone (Day)" => GameMap::new(
+                name.to_owned(),
+                parallax_layer_defs("assets/maps/tilesets/green-zone/2 Background", &TimeOfDay::Day, 5),
+                "assets/maps/tilesets/green-zone/Tileset.png".to_owned(),
+                file.to_owned(),
+                "Tileset.png".to_owned(),
+                vec!["Tile Layer 1".to_owned()],
+                "Tile Layer 1".to_owned(),
+            ).await.with_music("assets/music/green_zone_day.wav".to_owned()),
+            "Green Zone (Night)" => GameMap::new(
+                name.to_owned(),
+                parallax_layer_defs("assets/maps/tilesets/green-zone/2 Background", &TimeOfDay::Night, 5),
+                "assets/maps/tilesets/green-zone/Tileset.png".to_owned(),
+                file.to_owned(),
+                "Tileset.png".to_owned(),
+                vec!["Tile Layer 1".to_owned()],
+                "Tile Layer 1".to_owned(),
+            ).await.with_music("assets/music/green_zone_night.wav".to_owned()),
+            _ => {
+                eprintln!("{} has no tileset or backgrounds set up, leaving it out", name);
+                continue;
+            }
+        };
+
+        maps.push(map);
     }
 
-    static_colliders
+    maps
 }
 
 async fn render_update_server_characters(
-    my_client_id: u64,
+    my_client_id: Option<u64>,
     server_clients: &HashMap<u64, ServerClient>,
     server_characters: &mut HashMap<u64, ServerCharacter>,
+    spare_actors: &mut Vec<Actor>,
     textures: &Rc<CharacterTextures>,
     world: &Rc<RefCell<World>>,
     t: f32,
 ) {
+    // players who left aren't in server_clients anymore
+    let departed: Vec<u64> = server_characters
+        .keys()
+        .filter(|client_id| !server_clients.contains_key(client_id))
+        .copied()
+        .collect();
+
+    for client_id in departed {
+        if let Some(character) = server_characters.remove(&client_id) {
+            spare_actors.push(character.remove());
+        }
+    }
+
     for (client_id, sc) in server_clients {
-        if *client_id == my_client_id { continue; }
+        if Some(*client_id) == my_client_id { continue; }
 
         // we calcualte lerp here
         let lerp_x_pos = lerp(sc.prev_x_pos, sc.x_pos, t);
         let lerp_y_pos = lerp(sc.prev_y_pos, sc.y_pos, t);
 
-        let character = server_characters.entry(*client_id)
-            .and_modify(|v| {
-                v.x_pos = lerp_x_pos;
-                v.y_pos = lerp_y_pos;
-                v.height = sc.height;
-                v.width = sc.width;
-                v.anim_type = sc.anim_type.clone();
-                v.character_type = sc.character_type.clone();
-                v.sprite_frame = sc.sprite_frame;
-                v.facing = sc.facing.clone();
-            })
-            .or_insert(ServerCharacter::new(
+        // only build a character for players we haven't seen yet, building one adds an actor to the world
+        let character = match server_characters.entry(*client_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(ServerCharacter::new(
                 lerp_x_pos,
                 lerp_y_pos, 
                 sc.height,
@@ -233,32 +945,62 @@ async fn render_update_server_characters(
                 sc.anim_type.clone(), 
                 sc.character_type.clone(), 
                 sc.sprite_frame, 
+                sc.is_invulnerable,
+                spare_actors.pop(),
                 Rc::clone(&world)
-            ).await);
+            ).await),
+        };
+
+        character.x_pos = lerp_x_pos;
+        character.y_pos = lerp_y_pos;
+        character.height = sc.height;
+        character.width = sc.width;
+        character.anim_type = sc.anim_type.clone();
+        character.character_type = sc.character_type.clone();
+        character.sprite_frame = sc.sprite_frame;
+        character.facing = sc.facing.clone();
+        character.is_invulnerable = sc.is_invulnerable;
+
+        // identity isn't lerped so it just gets copied over
+        character.name = sc.name.clone();
+        character.health = sc.health;
 
         let texture = character.get_texture(textures);
         let actor = character.get_actor();
         let facing = character.get_facing();
         let sprite_frame = character.get_sprite_frame();
+        let tint = invulnerable_tint(character.get_is_invulnerable());
 
         character.update();
-        draw_player(&texture, &Rc::clone(&world), actor, facing, sprite_frame, USE_HITBOXES);
+        draw_player(&texture, &Rc::clone(&world), actor, facing, sprite_frame, tint, USE_HITBOXES);
+
+        let actor_pos = world.borrow().actor_pos(actor);
+        draw_nameplate(&character.name, character.health, actor_pos, character.width as f32);
     }
 }
 
 fn render_update_my_character(
     character: &mut dyn CharacterTrait, 
-    dt: f32, 
+    input: &PlayerInput,
+    sim_steps: u32,
     world: &Rc<RefCell<World>>, 
     textures: &Rc<CharacterTextures>,
+    is_invulnerable: bool,
+    can_play: bool,
 ) {
-    character.update(dt);
+    if can_play {
+        for _ in 0..sim_steps {
+            character.update(input);
+        }
+    }
+
     let texture = character.get_texture(textures);
     let actor = character.get_actor();
     let facing = character.get_facing();
     let sprite_frame = character.get_sprite_frame();
+    let tint = invulnerable_tint(is_invulnerable);
 
-    draw_player(&texture, &Rc::clone(&world), actor, facing, sprite_frame, USE_HITBOXES);
+    draw_player(&texture, &Rc::clone(&world), actor, facing, sprite_frame, tint, USE_HITBOXES);
 }
 
 fn draw_player(
@@ -267,6 +1009,7 @@ fn draw_player(
     actor: Actor,
     facing: Facing,
     sprite_frame: usize,
+    tint: Color,
     draw_hitboxes: bool
 ) {
     let player_pos = world.borrow_mut().actor_pos(actor);
@@ -276,11 +1019,16 @@ fn draw_player(
         draw_rectangle_lines(player_pos.x, player_pos.y, player_size.0 as f32,  player_size.1 as f32, 4.0, RED);
     }
 
+    draw_sprite(texture, player_pos, player_size, facing, sprite_frame, tint);
+}
+
+/// Draws the sprite so its feet line up with the bottom of the collider at `collider_pos`
+fn draw_sprite(texture: &Texture2D, collider_pos: Vec2, collider_size: (i32, i32), facing: Facing, sprite_frame: usize, tint: Color) {
     draw_texture_ex(
         texture,
-        player_pos.x - (SPRITE_WIDTH / 2.0) + (player_size.0 as f32 / 2.0),
-        player_pos.y - (SPRITE_HEIGHT - player_size.1 as f32),
-        WHITE,
+        collider_pos.x - (SPRITE_WIDTH / 2.0) + (collider_size.0 as f32 / 2.0),
+        collider_pos.y - (SPRITE_HEIGHT - collider_size.1 as f32),
+        tint,
         DrawTextureParams {
             source: Some(Rect::new(
                 TILE_WIDTH * sprite_frame as f32,
@@ -295,13 +1043,161 @@ fn draw_player(
     );
 }
 
+/// Remote players inside our attack's hitbox that it hasn't hit yet
+fn find_hit_victims(
+    hit: &HitProperties,
+    my_character: &dyn CharacterTrait,
+    server_characters: &HashMap<u64, ServerCharacter>,
+    world: &Rc<RefCell<World>>,
+    already_hit: &HashSet<u64>,
+) -> Vec<u64> {
+    let hitbox = hit.get_hitbox(my_character.get_position(), my_character.get_size(), &my_character.get_facing());
+
+    server_characters
+        .iter()
+        .filter(|(client_id, character)| !already_hit.contains(client_id) && !character.get_is_invulnerable())
+        .filter(|(_, character)| {
+            let pos = world.borrow().actor_pos(character.get_actor());
+            let hurtbox = Rect::new(pos.x, pos.y, character.width as f32, character.height as f32);
+            hitbox.overlaps(&hurtbox)
+        })
+        .map(|(client_id, _)| *client_id)
+        .collect()
+}
+
+fn draw_duel_fighters(duel: &Duel, world: &Rc<RefCell<World>>, textures: &Rc<CharacterTextures>) {
+    for player in 0..2 {
+        let fighter = duel.get_fighter(player);
+        let texture = fighter.get_texture(textures);
+        draw_player(&texture, world, fighter.get_actor(), fighter.get_facing(), fighter.get_sprite_frame(), WHITE, USE_HITBOXES);
+    }
+}
+
+/// Both duel fighters are simulated here, player 0 and 1 stand in for client ids
+fn watch_duel_fighters(frame_watcher: &mut FrameEventWatcher, duel: &Duel) -> Vec<FighterFrameEvents> {
+    (0..2)
+        .filter_map(|player| frame_watcher.watch_character(player as u64, duel.get_fighter(player), duel.get_health(player)))
+        .collect()
+}
+
+/// Effects the server saw happen, deaths from falling off the map get pulled back into view
+fn spawn_server_effects(particles: &mut ParticleSystem, server: &mut ServerConnection, bounds: Rect) {
+    for (name, position) in server.take_effects() {
+        particles.spawn(&name, clamp_to_bounds(position, bounds), &Facing::Right);
+    }
+}
+
+fn clamp_to_bounds(position: Vec2, bounds: Rect) -> Vec2 {
+    position.clamp(bounds.point(), bounds.point() + bounds.size())
+}
+
+fn get_duel_hud_players(duel: &Duel, local_player: usize) -> Vec<HudPlayer> {
+    (0..2)
+        .map(|player| HudPlayer {
+            name: format!("Player {} - {}", player + 1, duel.get_score(player)),
+            character_type: duel.get_fighter(player).get_character_type(),
+            health: duel.get_health(player),
+            stocks_left: None,
+            is_eliminated: false,
+            is_local: player == local_player,
+        })
+        .collect()
+}
+
+/// Everyone in `clients` with nobody local, for replays and spectators
+fn get_remote_hud_players(clients: &HashMap<u64, ServerClient>) -> Vec<HudPlayer> {
+    let mut players: Vec<(&u64, &ServerClient)> = clients.iter().collect();
+    players.sort_by_key(|(client_id, _)| **client_id);
+
+    players
+        .into_iter()
+        .map(|(_, sc)| HudPlayer {
+            name: sc.name.clone(),
+            character_type: sc.character_type.clone(),
+            health: sc.health,
+            stocks_left: None,
+            is_eliminated: false,
+            is_local: false,
+        })
+        .collect()
+}
+
+/// The next player after `current` in a stable order, or the whole arena after the last one
+fn get_next_spectate_target(current: Option<u64>, server_clients: &HashMap<u64, ServerClient>) -> Option<u64> {
+    let mut client_ids: Vec<u64> = server_clients.keys().copied().collect();
+    client_ids.sort();
+
+    match current {
+        None => client_ids.first().copied(),
+        Some(current) => client_ids.into_iter().find(|client_id| *client_id > current),
+    }
+}
+
+/// Why the connection is gone, once it's gone for good
+fn get_disconnect_reason(server: &ServerConnection) -> Option<String> {
+    match server.get_status() {
+        ConnectionStatus::Disconnected { reason } => Some(reason.clone()),
+        _ => None,
+    }
+}
+
+/// Everyone connected in a stable order, or just us in single player
+fn get_hud_players(server: Option<&ServerConnection>, my_character: &dyn CharacterTrait, player_name: &str) -> Vec<HudPlayer> {
+    let Some(server) = server else {
+        let name = if player_name.is_empty() { "Player 1" } else { player_name };
+
+        return vec![HudPlayer {
+            name: name.to_owned(),
+            character_type: my_character.get_character_type(),
+            health: MAX_HEALTH,
+            stocks_left: None,
+            is_eliminated: false,
+            is_local: true,
+        }];
+    };
+
+    let my_client_id = server.get_client_id();
+    let match_state = server.get_match_state();
+
+    let mut players: Vec<(&u64, &ServerClient)> = server.get_server_clients().iter().collect();
+    players.sort_by_key(|(client_id, _)| **client_id);
+
+    players
+        .into_iter()
+        .map(|(client_id, sc)| {
+            let score = match_state.and_then(|match_state| match_state.scores.get(client_id));
+            let is_local = *client_id == my_client_id;
+
+            HudPlayer {
+                name: sc.name.clone(),
+                // our own entry is only as fresh as our last update, the local character is always right
+                character_type: if is_local { my_character.get_character_type() } else { sc.character_type.clone() },
+                health: sc.health,
+                stocks_left: score.and_then(|score| score.stocks_left),
+                is_eliminated: score.is_some_and(|score| score.is_eliminated)
+                    && match_state.is_some_and(|match_state| match_state.is_running()),
+                is_local,
+            }
+        })
+        .collect()
+}
+
+/// Invulnerable players blink so everyone can tell they can't be hit yet
+fn invulnerable_tint(is_invulnerable: bool) -> Color {
+    if is_invulnerable && (get_time() * 10.0) as i64 % 2 == 0 {
+        Color::new(1.0, 1.0, 1.0, 0.35)
+    } else {
+        WHITE
+    }
+}
+
 fn window_conf() -> Conf {
     Conf {
         window_title: "dangame".to_owned(),
         window_width: WINDOW_WIDTH,
         window_height: WINDOW_HEIGHT,
         fullscreen: false,
-        window_resizable: false,
+        window_resizable: true,
         ..Default::default()
     }
 }
@@ -0,0 +1,94 @@
+use macroquad::{
+    color::WHITE, math::{vec2, Rect}, texture::{draw_texture_ex, load_texture, DrawTextureParams, FilterMode, Texture2D}
+};
+
+/// Which set of background art a map uses, the tilesets ship both
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimeOfDay {
+    Day,
+    Night,
+}
+
+impl TimeOfDay {
+    pub fn get_folder_name(&self) -> &'static str {
+        match self {
+            TimeOfDay::Day => "Day",
+            TimeOfDay::Night => "Night",
+        }
+    }
+}
+
+/// How a map declares one of its background layers
+pub struct BackgroundLayerDef {
+    pub texture_path: String,
+    /// 0.0 stays pinned to the camera, 1.0 moves with the tiles
+    pub scroll_factor: f32,
+    /// Repeat the texture horizontally instead of stretching it
+    pub looping: bool,
+}
+
+impl BackgroundLayerDef {
+    pub fn new(texture_path: String, scroll_factor: f32, looping: bool) -> Self {
+        Self { texture_path, scroll_factor, looping }
+    }
+}
+
+/// Builds the numbered layers (1.png is the furthest back) found in the tileset background folders
+pub fn parallax_layer_defs(background_dir: &str, time_of_day: &TimeOfDay, layer_count: usize) -> Vec<BackgroundLayerDef> {
+    (0..layer_count)
+        .map(|i| {
+            BackgroundLayerDef::new(
+                format!("{}/{}/{}.png", background_dir, time_of_day.get_folder_name(), i + 1),
+                i as f32 / layer_count as f32,
+                true,
+            )
+        })
+        .collect()
+}
+
+pub struct BackgroundLayer {
+    texture: Texture2D,
+    scroll_factor: f32,
+    looping: bool,
+}
+
+impl BackgroundLayer {
+    pub async fn load(def: &BackgroundLayerDef) -> Self {
+        let texture = load_texture(&def.texture_path).await.unwrap();
+        texture.set_filter(FilterMode::Nearest);
+
+        Self {
+            texture,
+            scroll_factor: def.scroll_factor,
+            looping: def.looping,
+        }
+    }
+
+    /// Draws the layer behind `view` (world coordinates), `map_width` bounds how far the camera can travel
+    pub fn draw(&self, view: Rect, map_width: f32) {
+        let scroll = view.x * self.scroll_factor;
+
+        if self.looping {
+            // scale to the view height and tile across
+            let width = self.texture.width() * (view.h / self.texture.height());
+            let mut x = view.x - scroll.rem_euclid(width);
+
+            while x < view.right() {
+                self.draw_at(x, view.y, width, view.h);
+                x += width;
+            }
+        } else {
+            // stretch wide enough that the layer covers the screen for the whole scroll range
+            let travel = (map_width - view.w).max(0.0);
+            let width = view.w + travel * self.scroll_factor;
+            self.draw_at(view.x - scroll, view.y, width, view.h);
+        }
+    }
+
+    fn draw_at(&self, x: f32, y: f32, width: f32, height: f32) {
+        draw_texture_ex(&self.texture, x, y, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(width, height)),
+            ..Default::default()
+        });
+    }
+}
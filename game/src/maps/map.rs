@@ -1,27 +1,42 @@
 use macroquad::{
-    color::WHITE, file::load_string, math::Rect, texture::{draw_texture, load_texture, FilterMode, Texture2D}
+    file::load_string, math::Rect, texture::{load_texture, FilterMode}
 };
 
+use macroquad_platformer::{Tile, World};
 use macroquad_tiled::{self as tiled, Map};
-use common::constants::*;
+
+use common::spawn::{parse_spawn_points, SpawnPoint, FALLBACK_SPAWN_POINT};
+
+use super::background::{BackgroundLayer, BackgroundLayerDef};
 
 pub struct GameMap {
     name: String,
-    background_texture: Texture2D,
+    /// Drawn in order, so the first layer is the furthest back
+    background_layers: Vec<BackgroundLayer>,
     map: Map,
     layers: Vec<String>,
+    collision_layer: String,
+    /// Actor positions built from the points in the map's "Spawns" object layer
+    spawn_points: Vec<SpawnPoint>,
+    /// Looped while the map is being played
+    music: Option<String>,
 }
 
 impl GameMap {
     pub async fn new(
         name: String,
-        background_texture_path: String,
+        background_layer_defs: Vec<BackgroundLayerDef>,
         tileset_texture_path: String,
         tileset_json_path: String,
         tileset_json_tileset_name: String,
         layers: Vec<String>,
+        collision_layer: String,
     ) -> GameMap {
-        let background_texture = load_texture(&background_texture_path).await.unwrap();
+        let mut background_layers = vec![];
+        for def in &background_layer_defs {
+            background_layers.push(BackgroundLayer::load(def).await);
+        }
+
         let tileset_texture = load_texture(&tileset_texture_path).await.unwrap();
 
         tileset_texture.set_filter(FilterMode::Nearest);
@@ -29,23 +44,100 @@ impl GameMap {
         let tiled_map_json = load_string(&tileset_json_path).await.unwrap();
         let map = tiled::load_map(&tiled_map_json, &[(&tileset_json_tileset_name, tileset_texture.clone())], &[]).unwrap();
 
+        // read the same way the server reads them, so everyone agrees where the spawns are
+        let spawn_points = parse_spawn_points(&tiled_map_json).unwrap_or_else(|e| {
+            eprintln!("Couldn't read the spawn points in {}: {}", tileset_json_path, e);
+            vec![]
+        });
+
         GameMap {
             name,
-            background_texture,
+            background_layers,
             map,
             layers,
+            collision_layer,
+            spawn_points,
+            music: None,
         }
     }
 
-    pub fn draw_map(&self) {
-        let background = &self.background_texture;
+    pub fn with_music(mut self, music_path: String) -> Self {
+        self.music = Some(music_path);
+        self
+    }
 
-        draw_texture(background, 0., 0., WHITE);
+    /// Draws the background layers and only the tiles that fall inside `view` (world coordinates)
+    pub fn draw_map(&self, view: Rect) {
+        let (tile_width, tile_height) = self.get_tile_size();
+        let bounds = self.get_bounds();
+
+        for background_layer in &self.background_layers {
+            background_layer.draw(view, bounds.w);
+        }
+
+        let first_x = (view.x / tile_width).floor().max(0.0);
+        let first_y = (view.y / tile_height).floor().max(0.0);
+        let last_x = (view.right() / tile_width).ceil().min(bounds.w / tile_width);
+        let last_y = (view.bottom() / tile_height).ceil().min(bounds.h / tile_height);
+
+        if last_x <= first_x || last_y <= first_y {
+            return;
+        }
+
+        // source is in tiles, dest is in world pixels
+        let source = Rect::new(first_x, first_y, last_x - first_x, last_y - first_y);
+        let dest = Rect::new(
+            first_x * tile_width,
+            first_y * tile_height,
+            source.w * tile_width,
+            source.h * tile_height,
+        );
 
         for layer in &self.layers {
-            self.map.draw_tiles(layer, Rect::new(0.0, 0.0, WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32), None);
+            self.map.draw_tiles(layer, dest, Some(source));
         }
     }
 
+    /// Adds this map's collision layer to the world as static colliders
+    pub fn load_colliders(&self, world: &mut World) {
+        let mut static_colliders = vec![];
+        for (_x, _y, tile) in self.map.tiles(&self.collision_layer, None) {
+            static_colliders.push(if tile.is_some() {
+                Tile::Solid
+            } else {
+                Tile::Empty
+            });
+        }
+
+        let (tile_width, tile_height) = self.get_tile_size();
+        let width = self.map.raw_tiled_map.width as usize;
+        world.add_static_tiled_layer(static_colliders, tile_width, tile_height, width, 1);
+    }
+
+    /// The full size of the map in world pixels
+    pub fn get_bounds(&self) -> Rect {
+        let (tile_width, tile_height) = self.get_tile_size();
+        Rect::new(
+            0.0,
+            0.0,
+            self.map.raw_tiled_map.width as f32 * tile_width,
+            self.map.raw_tiled_map.height as f32 * tile_height,
+        )
+    }
+
+    pub fn get_spawn_points(&self) -> Vec<SpawnPoint> {
+        if self.spawn_points.is_empty() {
+            return vec![FALLBACK_SPAWN_POINT];
+        }
+
+        self.spawn_points.clone()
+    }
+
+    pub fn get_tile_size(&self) -> (f32, f32) {
+        (self.map.raw_tiled_map.tilewidth as f32, self.map.raw_tiled_map.tileheight as f32)
+    }
+
     pub fn get_name(&self) -> String { self.name.clone() }
+
+    pub fn get_music(&self) -> Option<&str> { self.music.as_deref() }
 }
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use macroquad::input::{is_key_down, is_key_pressed, mouse_wheel, KeyCode};
+use macroquad::math::{vec2, Vec2};
+
+use common::{replay::Replay, types::ServerClient};
+
+use crate::ui::match_hud::format_clock;
+
+const SEEK_SECONDS: f32 = 5.0;
+const PLAYBACK_SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const NORMAL_SPEED_INDEX: usize = 2;
+// world pixels per second the free camera moves at
+const FREE_CAMERA_SPEED: f32 = 600.0;
+const FREE_CAMERA_ZOOM_STEP: f32 = 0.1;
+
+/// Plays a recorded match back, `position` is in ticks and moves with the playback speed
+pub struct ReplayViewer {
+    replay: Replay,
+    position: f32,
+    speed_index: usize,
+    is_paused: bool,
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            position: 0.0,
+            speed_index: NORMAL_SPEED_INDEX,
+            is_paused: false,
+        }
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Space pauses, left and right seek, up and down change the speed, home starts over
+    pub fn handle_input(&mut self) {
+        let seek_ticks = SEEK_SECONDS / self.replay.get_header().tick_seconds;
+
+        if is_key_pressed(KeyCode::Space) {
+            self.is_paused = !self.is_paused;
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.seek(-seek_ticks);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.seek(seek_ticks);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.speed_index = (self.speed_index + 1).min(PLAYBACK_SPEEDS.len() - 1);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.speed_index = self.speed_index.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::Home) {
+            self.position = 0.0;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.is_paused {
+            return;
+        }
+
+        let ticks = dt / self.replay.get_header().tick_seconds * PLAYBACK_SPEEDS[self.speed_index];
+        self.seek(ticks);
+    }
+
+    fn seek(&mut self, ticks: f32) {
+        let last_tick = self.replay.get_frame_count().saturating_sub(1) as f32;
+        self.position = (self.position + ticks).clamp(0.0, last_tick);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position as usize + 1 >= self.replay.get_frame_count()
+    }
+
+    /// Everyone on the current tick, with the previous positions set to the tick before so they can be lerped
+    pub fn get_clients(&self) -> HashMap<u64, ServerClient> {
+        let frame = self.position as usize;
+
+        let (Some(current), Some(next)) = (self.replay.get_frame(frame), self.replay.get_frame(frame + 1)) else {
+            return HashMap::new();
+        };
+
+        next.iter()
+            .map(|(client_id, sc)| {
+                let mut sc = sc.clone();
+                let previous = current.get(client_id).map(|previous| (previous.x_pos, previous.y_pos));
+                (sc.prev_x_pos, sc.prev_y_pos) = previous.unwrap_or((sc.x_pos, sc.y_pos));
+                (*client_id, sc)
+            })
+            .collect()
+    }
+
+    /// How far between the current tick and the next one we are
+    pub fn get_lerp_t(&self) -> f32 {
+        self.position.fract()
+    }
+
+    pub fn get_status(&self) -> String {
+        let tick_seconds = self.replay.get_header().tick_seconds;
+        let time = format_clock(self.position * tick_seconds);
+        let duration = format_clock(self.replay.get_duration());
+        let paused = if self.is_paused { "   paused" } else { "" };
+
+        format!("{} / {}   x{}{}", time, duration, PLAYBACK_SPEEDS[self.speed_index], paused)
+    }
+}
+
+/// WASD moves the free camera and the mouse wheel zooms, returns the pan offset and zoom factor for this frame
+pub fn get_free_camera_input(dt: f32) -> (Vec2, f32) {
+    let mut direction = vec2(0.0, 0.0);
+
+    if is_key_down(KeyCode::A) {
+        direction.x -= 1.0;
+    }
+    if is_key_down(KeyCode::D) {
+        direction.x += 1.0;
+    }
+    if is_key_down(KeyCode::W) {
+        direction.y -= 1.0;
+    }
+    if is_key_down(KeyCode::S) {
+        direction.y += 1.0;
+    }
+
+    let zoom = match mouse_wheel().1 {
+        wheel if wheel > 0.0 => 1.0 - FREE_CAMERA_ZOOM_STEP,
+        wheel if wheel < 0.0 => 1.0 + FREE_CAMERA_ZOOM_STEP,
+        _ => 1.0,
+    };
+
+    (direction * FREE_CAMERA_SPEED * dt, zoom)
+}
@@ -0,0 +1,76 @@
+use std::net::SocketAddr;
+
+use common::{animation::CharacterType, link_conditioner::LinkConditionerConfig};
+
+/// Settings for a peer to peer duel, read from the command line.
+///
+/// `--rollback <local port> <peer address> --player <0|1>` starts one, both sides need the same `--map`.
+pub struct RollbackConfig {
+    pub local_addr: SocketAddr,
+    pub peer_addr: SocketAddr,
+    /// Which side of the duel we are, the two clients have to pick different ones
+    pub local_player: usize,
+    pub character_type: CharacterType,
+    pub map_index: usize,
+    /// Frames our own inputs are held back for, more delay means fewer rollbacks
+    pub input_delay: u64,
+    /// What to do to everything we send, for testing over loopback
+    pub link_conditioner: LinkConditionerConfig,
+}
+
+// a couple of frames of delay hides most of the latency on a decent connection
+const DEFAULT_INPUT_DELAY: u64 = 2;
+
+impl RollbackConfig {
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        let rollback_index = args.iter().position(|arg| arg == "--rollback")?;
+
+        let local_port: u16 = parse_value(args.get(rollback_index + 1), "local port")?;
+        let peer_addr: SocketAddr = parse_value(args.get(rollback_index + 2), "peer address")?;
+        let local_addr: SocketAddr = format!("0.0.0.0:{}", local_port).parse().ok()?;
+
+        let local_player: usize = get_flag(args, "--player").map_or(Some(0), |value| parse_value(Some(value), "player"))?;
+        let map_index: usize = get_flag(args, "--map").map_or(Some(0), |value| parse_value(Some(value), "map"))?;
+        let input_delay = get_flag(args, "--input-delay").map_or(Some(DEFAULT_INPUT_DELAY), |value| parse_value(Some(value), "input delay"))?;
+
+        let character_type = match get_flag(args, "--character").map(|value| value.as_str()) {
+            None | Some("fighter") => CharacterType::Fighter,
+            Some("shinobi") => CharacterType::Shinobi,
+            Some("samurai") => CharacterType::Samurai,
+            Some(other) => {
+                eprintln!("Unknown character {}, expected fighter, shinobi or samurai", other);
+                return None;
+            }
+        };
+
+        if local_player > 1 {
+            eprintln!("--player has to be 0 or 1");
+            return None;
+        }
+
+        Some(Self {
+            local_addr,
+            peer_addr,
+            local_player,
+            character_type,
+            map_index,
+            input_delay,
+            link_conditioner: LinkConditionerConfig::from_args(args),
+        })
+    }
+}
+
+fn get_flag<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    let i = args.iter().position(|arg| arg == flag)?;
+    args.get(i + 1)
+}
+
+fn parse_value<T: std::str::FromStr>(value: Option<&String>, name: &str) -> Option<T> {
+    match value.map(|value| value.parse()) {
+        Some(Ok(value)) => Some(value),
+        _ => {
+            eprintln!("Missing or invalid {} for --rollback", name);
+            None
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use macroquad::math::{Rect, Vec2};
+
+use common::{constants::MAX_HEALTH, input::PlayerInput};
+
+use crate::characters::{character::CharacterTrait, respawn::is_out_of_bounds, snapshot::CharacterSnapshot};
+
+/// Everything a duel needs to go back in time to
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuelState {
+    fighters: [CharacterSnapshot; 2],
+    health: [i32; 2],
+    /// Whether each fighter's current attack already connected
+    hit_landed: [bool; 2],
+    scores: [u32; 2],
+}
+
+/// A 1v1 where both fighters are simulated locally from their inputs, nothing in here reads the clock or the keyboard
+pub struct Duel {
+    fighters: [Box<dyn CharacterTrait>; 2],
+    health: [i32; 2],
+    hit_landed: [bool; 2],
+    scores: [u32; 2],
+    spawns: [Vec2; 2],
+    bounds: Rect,
+}
+
+impl Duel {
+    pub fn new(fighters: [Box<dyn CharacterTrait>; 2], spawns: [Vec2; 2], bounds: Rect) -> Self {
+        Self {
+            fighters,
+            health: [MAX_HEALTH; 2],
+            hit_landed: [false; 2],
+            scores: [0; 2],
+            spawns,
+            bounds,
+        }
+    }
+
+    pub fn get_fighter(&self, player: usize) -> &dyn CharacterTrait {
+        self.fighters[player].as_ref()
+    }
+
+    pub fn get_health(&self, player: usize) -> i32 {
+        self.health[player]
+    }
+
+    pub fn get_score(&self, player: usize) -> u32 {
+        self.scores[player]
+    }
+
+    pub fn save(&self) -> DuelState {
+        DuelState {
+            fighters: [self.fighters[0].save_snapshot(), self.fighters[1].save_snapshot()],
+            health: self.health,
+            hit_landed: self.hit_landed,
+            scores: self.scores,
+        }
+    }
+
+    pub fn load(&mut self, state: &DuelState) {
+        for (fighter, snapshot) in self.fighters.iter_mut().zip(&state.fighters) {
+            fighter.load_snapshot(snapshot);
+        }
+
+        self.health = state.health;
+        self.hit_landed = state.hit_landed;
+        self.scores = state.scores;
+    }
+
+    /// Simulates one tick, `inputs` are indexed by player
+    pub fn step(&mut self, inputs: &[PlayerInput; 2]) {
+        for (fighter, input) in self.fighters.iter_mut().zip(inputs) {
+            fighter.update(input);
+        }
+
+        // player 0 always goes first so both sides resolve trades the same way
+        for attacker in 0..2 {
+            self.check_hit(attacker);
+        }
+
+        for player in 0..2 {
+            let is_out = is_out_of_bounds(self.fighters[player].get_position(), self.bounds);
+
+            if self.health[player] == 0 || is_out {
+                self.scores[1 - player] += 1;
+                self.reset_round();
+                return;
+            }
+        }
+    }
+
+    fn check_hit(&mut self, attacker: usize) {
+        let victim = 1 - attacker;
+
+        let Some(hit) = self.fighters[attacker].get_active_hit() else {
+            // the attack is over, the next one can land again
+            self.hit_landed[attacker] = false;
+            return;
+        };
+
+        if self.hit_landed[attacker] || self.fighters[victim].is_invulnerable() {
+            return;
+        }
+
+        let attacker_fighter = &self.fighters[attacker];
+        let hitbox = hit.get_hitbox(attacker_fighter.get_position(), attacker_fighter.get_size(), &attacker_fighter.get_facing());
+        let attacker_facing = attacker_fighter.get_facing();
+
+        let victim_pos = self.fighters[victim].get_position();
+        let victim_size = self.fighters[victim].get_size();
+        let hurtbox = Rect::new(victim_pos.x, victim_pos.y, victim_size.0 as f32, victim_size.1 as f32);
+
+        if !hitbox.overlaps(&hurtbox) {
+            return;
+        }
+
+        self.hit_landed[attacker] = true;
+        self.health[victim] = (self.health[victim] - hit.damage).max(0);
+        self.fighters[victim].apply_hit(&hit, &attacker_facing);
+        self.fighters[attacker].on_hit_landed(&hit);
+    }
+
+    /// Someone got knocked out, both fighters go back to their spawns with full health
+    fn reset_round(&mut self) {
+        for (fighter, spawn) in self.fighters.iter_mut().zip(self.spawns) {
+            fighter.respawn(spawn);
+        }
+
+        self.health = [MAX_HEALTH; 2];
+        self.hit_landed = [false; 2];
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::SocketAddr,
+};
+
+use bincode::{config::Configuration, Decode, Encode};
+
+use common::{animation::CharacterType, input::PlayerInput, link_conditioner::ConditionedSocket};
+
+use super::{
+    config::RollbackConfig,
+    duel::{Duel, DuelState},
+};
+
+// how far we let the simulation run ahead of the peer's inputs before waiting for them
+const MAX_PREDICTION_FRAMES: u64 = 8;
+// unacked inputs get resent every frame, this keeps a packet from growing without end
+const MAX_INPUTS_PER_PACKET: usize = 32;
+// leaving is only said once we're on the way out, so it goes a few times in case one gets lost
+const LEAVE_SENDS: usize = 3;
+const MAX_PACKET_SIZE: usize = 1200;
+
+/// What the two peers send each other
+#[derive(Encode, Decode, Debug)]
+enum PeerMessage {
+    /// Sent until the peer starts sending inputs, so they know who they're fighting
+    Hello { character_type: CharacterType },
+    /// Inputs for `start_frame` onwards, `ack_frame` is the newest frame we have every input up to
+    Inputs {
+        start_frame: u64,
+        inputs: Vec<PlayerInput>,
+        ack_frame: Option<u64>,
+    },
+    /// Sent when the session is dropped, the duel is over
+    Leave,
+}
+
+/// GGPO style rollback between two clients.
+///
+/// Our inputs are delayed by a few frames and sent straight to the peer. Their inputs we don't have yet are
+/// predicted by repeating their last one, and when the real ones turn out different we load the state from
+/// before that frame and simulate forward again.
+pub struct RollbackSession {
+    bincode_config: Configuration,
+    socket: ConditionedSocket,
+    peer_addr: SocketAddr,
+    local_player: usize,
+    local_character: CharacterType,
+    remote_character: Option<CharacterType>,
+    input_delay: u64,
+    /// The next frame to simulate
+    frame: u64,
+    local_inputs: BTreeMap<u64, PlayerInput>,
+    remote_inputs: BTreeMap<u64, PlayerInput>,
+    /// What we guessed for remote frames we simulated before their input got here
+    predictions: BTreeMap<u64, PlayerInput>,
+    /// Every remote input up to this frame has arrived
+    confirmed_remote_frame: Option<u64>,
+    /// The peer has every one of our inputs up to this frame
+    peer_acked_frame: Option<u64>,
+    /// The earliest frame we got wrong, we go back to it before simulating anything new
+    rollback_to: Option<u64>,
+    /// Duel state from just before each recent frame was simulated
+    saved_states: VecDeque<(u64, DuelState)>,
+    /// Counts resimulated frames, shown on screen to see how often predictions miss
+    rollback_frames: u64,
+    has_peer_left: bool,
+}
+
+impl RollbackSession {
+    pub fn new(config: &RollbackConfig) -> std::io::Result<Self> {
+        let socket = ConditionedSocket::bind(config.local_addr, config.link_conditioner.clone())?;
+
+        // nobody acts during the delay at the start, so those frames are known to be empty
+        let local_inputs = (0..config.input_delay).map(|frame| (frame, PlayerInput::default())).collect();
+
+        Ok(Self {
+            bincode_config: bincode::config::standard(),
+            socket,
+            peer_addr: config.peer_addr,
+            local_player: config.local_player,
+            local_character: config.character_type.clone(),
+            remote_character: None,
+            input_delay: config.input_delay,
+            frame: 0,
+            local_inputs,
+            remote_inputs: BTreeMap::new(),
+            predictions: BTreeMap::new(),
+            confirmed_remote_frame: None,
+            peer_acked_frame: None,
+            rollback_to: None,
+            saved_states: VecDeque::new(),
+            rollback_frames: 0,
+            has_peer_left: false,
+        })
+    }
+
+    pub fn get_local_player(&self) -> usize {
+        self.local_player
+    }
+
+    pub fn get_local_character(&self) -> &CharacterType {
+        &self.local_character
+    }
+
+    /// Known once the peer said hello, the duel can't start before that
+    pub fn get_remote_character(&self) -> Option<&CharacterType> {
+        self.remote_character.as_ref()
+    }
+
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn get_rollback_frames(&self) -> u64 {
+        self.rollback_frames
+    }
+
+    /// The peer dropped their session, nothing more is coming from them
+    pub fn has_peer_left(&self) -> bool {
+        self.has_peer_left
+    }
+
+    /// Reads everything the peer sent and works out whether we have to roll back
+    pub fn poll(&mut self) {
+        self.socket.flush();
+
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        while let Some((len, addr)) = self.socket.recv_from(&mut buf) {
+            if addr != self.peer_addr {
+                continue;
+            }
+
+            let message: PeerMessage = match bincode::decode_from_slice(&buf[..len], self.bincode_config) {
+                Ok((message, _)) => message,
+                Err(e) => {
+                    eprintln!("Error decoding peer message: {:?}", e);
+                    continue;
+                }
+            };
+
+            match message {
+                PeerMessage::Hello { character_type } => {
+                    self.remote_character = Some(character_type);
+                }
+                PeerMessage::Inputs { start_frame, inputs, ack_frame } => {
+                    self.receive_inputs(start_frame, inputs);
+
+                    if ack_frame > self.peer_acked_frame {
+                        self.peer_acked_frame = ack_frame;
+                    }
+                }
+                PeerMessage::Leave => {
+                    self.has_peer_left = true;
+                }
+            }
+        }
+
+        // until inputs show up the peer might not know who we are yet
+        if self.confirmed_remote_frame.is_none() {
+            self.send(&PeerMessage::Hello { character_type: self.local_character.clone() });
+        }
+    }
+
+    fn receive_inputs(&mut self, start_frame: u64, inputs: Vec<PlayerInput>) {
+        for (frame, input) in (start_frame..).zip(inputs) {
+            if self.remote_inputs.contains_key(&frame) || self.confirmed_remote_frame.is_some_and(|confirmed| frame <= confirmed) {
+                continue;
+            }
+
+            if let Some(predicted) = self.predictions.remove(&frame) {
+                if predicted != input {
+                    self.rollback_to = Some(self.rollback_to.map_or(frame, |rollback_to| rollback_to.min(frame)));
+                }
+            }
+
+            self.remote_inputs.insert(frame, input);
+        }
+
+        let mut next = self.confirmed_remote_frame.map_or(0, |confirmed| confirmed + 1);
+        while self.remote_inputs.contains_key(&next) {
+            self.confirmed_remote_frame = Some(next);
+            next += 1;
+        }
+    }
+
+    /// Queues what the local player is pressing, it takes effect `input_delay` frames from now
+    pub fn add_local_input(&mut self, input: PlayerInput) {
+        self.local_inputs.entry(self.frame + self.input_delay).or_insert(input);
+    }
+
+    /// Sends every input the peer hasn't acked yet, they get resent until they do since packets can go missing
+    pub fn send_inputs(&mut self) {
+        let start_frame = self.peer_acked_frame.map_or(0, |acked| acked + 1);
+
+        let inputs: Vec<PlayerInput> = self.local_inputs
+            .range(start_frame..)
+            .take(MAX_INPUTS_PER_PACKET)
+            .map(|(_, input)| *input)
+            .collect();
+
+        if inputs.is_empty() {
+            return;
+        }
+
+        self.send(&PeerMessage::Inputs {
+            start_frame,
+            inputs,
+            ack_frame: self.confirmed_remote_frame,
+        });
+    }
+
+    fn send(&mut self, message: &PeerMessage) {
+        match bincode::encode_to_vec(message, self.bincode_config) {
+            Ok(encoded) => self.socket.send_to(encoded, self.peer_addr),
+            Err(e) => eprintln!("Error encoding peer message: {:?}", e),
+        }
+    }
+
+    /// Rolls back if a prediction was wrong, then simulates the next frame.
+    ///
+    /// Returns false when we're too far ahead of the peer and have to wait for their inputs.
+    pub fn advance(&mut self, duel: &mut Duel) -> bool {
+        if let Some(rollback_to) = self.rollback_to.take() {
+            self.rollback(duel, rollback_to);
+        }
+
+        let confirmed_frames = self.confirmed_remote_frame.map_or(0, |confirmed| confirmed + 1);
+        if self.frame >= confirmed_frames + MAX_PREDICTION_FRAMES || !self.local_inputs.contains_key(&self.frame) {
+            return false;
+        }
+
+        self.simulate_frame(duel, self.frame);
+        self.frame += 1;
+        self.forget_old_frames();
+
+        true
+    }
+
+    fn rollback(&mut self, duel: &mut Duel, rollback_to: u64) {
+        let Some(state) = self.saved_states.iter().find(|(frame, _)| *frame == rollback_to).map(|(_, state)| state.clone()) else {
+            eprintln!("Can't roll back to frame {}, it's no longer saved", rollback_to);
+            return;
+        };
+
+        duel.load(&state);
+
+        for frame in rollback_to..self.frame {
+            self.simulate_frame(duel, frame);
+            self.rollback_frames += 1;
+        }
+    }
+
+    fn simulate_frame(&mut self, duel: &mut Duel, frame: u64) {
+        self.saved_states.retain(|(saved_frame, _)| *saved_frame < frame);
+        self.saved_states.push_back((frame, duel.save()));
+
+        let local_input = self.local_inputs.get(&frame).copied().unwrap_or_default();
+        let remote_input = match self.remote_inputs.get(&frame) {
+            Some(input) => *input,
+            None => {
+                // people mostly keep holding whatever they were holding
+                let predicted = self.remote_inputs.range(..frame).next_back().map(|(_, input)| *input).unwrap_or_default();
+                self.predictions.insert(frame, predicted);
+                predicted
+            }
+        };
+
+        let mut inputs = [PlayerInput::default(); 2];
+        inputs[self.local_player] = local_input;
+        inputs[1 - self.local_player] = remote_input;
+
+        duel.step(&inputs);
+    }
+
+    /// Nothing older than the prediction window can be rolled back to anymore
+    fn forget_old_frames(&mut self) {
+        let oldest = self.frame.saturating_sub(MAX_PREDICTION_FRAMES + 1);
+
+        while self.saved_states.front().is_some_and(|(frame, _)| *frame < oldest) {
+            self.saved_states.pop_front();
+        }
+
+        // local inputs stay around until the peer has them and we can't need them for a rollback
+        let oldest_local = self.peer_acked_frame.map_or(0, |acked| acked + 1).min(oldest);
+        self.local_inputs.retain(|frame, _| *frame >= oldest_local);
+
+        // the newest remote input before the window is kept for predictions
+        let oldest_remote = oldest.min(self.confirmed_remote_frame.unwrap_or(0));
+        self.remote_inputs.retain(|frame, _| *frame >= oldest_remote);
+        self.predictions.retain(|frame, _| *frame >= oldest);
+    }
+}
+
+impl Drop for RollbackSession {
+    /// Tells the peer we're gone, the socket closes with us so this skips the conditioner's delay
+    fn drop(&mut self) {
+        match bincode::encode_to_vec(&PeerMessage::Leave, self.bincode_config) {
+            Ok(encoded) => {
+                for _ in 0..LEAVE_SENDS {
+                    self.socket.send_now(&encoded, self.peer_addr);
+                }
+            }
+            Err(e) => eprintln!("Error encoding peer message: {:?}", e),
+        }
+    }
+}
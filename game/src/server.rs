@@ -1,62 +1,145 @@
 use std::{
     collections::HashMap,
     net::{SocketAddr, UdpSocket},
-    time::{Instant, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 
 use bincode::config::Configuration;
-use common::types::{ClientEventType, ServerClient};
-use renet::{ConnectionConfig, DefaultChannel, RenetClient};
-use renet_netcode::{ClientAuthentication, NetcodeClientTransport};
+use common::{
+    animation::{CharacterType, Facing},
+    hit::HitProperties,
+    link_conditioner::{spawn_conditioned_proxy, LinkConditionerConfig},
+    lobby::LobbyState,
+    match_rules::{MatchMode, MatchPhase, MatchResults, MatchState},
+    spawn::SpawnPoint,
+    types::{player_name_to_user_data, ClientEventType, JoinType, ServerClient, ServerEventType},
+};
+use macroquad::math::{vec2, Vec2};
+use renet::{ConnectionConfig, DefaultChannel, NetworkInfo, RenetClient};
+use renet_netcode::{ClientAuthentication, ConnectToken, NetcodeClientTransport, NETCODE_KEY_BYTES};
 
 use crate::characters::character::CharacterTrait;
 
+/// A hit the server says we took
+pub struct IncomingHit {
+    pub hit: HitProperties,
+    pub attacker_facing: Facing,
+    /// The server already counted the KO, we just have to go down
+    pub is_fatal: bool,
+}
+
+const PROTOCOL_ID: u64 = 7;
+// what renet puts in the connect tokens it makes for unsecure connections
+const CONNECT_TOKEN_EXPIRE_SECONDS: u64 = 300;
+const CONNECT_TOKEN_TIMEOUT_SECONDS: i32 = 15;
+// how long one attempt at connecting gets before we give up on it
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// the server holds a dropped player's slot for a while, these attempts have to fit inside that
+const MAX_RECONNECT_ATTEMPTS: u32 = 3;
+// how long joins and leaves stay in the feed
+const FEED_ENTRY_LIFETIME: Duration = Duration::from_secs(6);
+
+/// Where our connection to the server is at, shown on screen whenever it isn't `Connected`
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Connected,
+    /// We lost the connection and are trying to get our slot back
+    Reconnecting { attempt: u32 },
+    /// For good, the reason is shown in the connect dialog
+    Disconnected { reason: String },
+}
+
 pub struct ServerConnection {
     bincode_config: Configuration,
     client: RenetClient,
     transport: NetcodeClientTransport,
+    server_addr: SocketAddr,
+    /// Where our conditioned proxy to the server listens, when `--latency` and co were given
+    proxy_addr: Option<SocketAddr>,
+    link_conditioner: LinkConditionerConfig,
+    player_name: String,
+    join_type: JoinType,
+    status: ConnectionStatus,
+    /// When the current connection attempt started
+    status_since: Instant,
+    /// We only try to reconnect if we got in at least once
+    was_connected: bool,
     last_renet_updated: Instant,
     last_server_updated: Instant,
     client_id: u64,
     server_clients: HashMap<u64, ServerClient>,
+    /// Tick of the newest character update from the server
+    server_tick: u64,
+    /// Events we still need to send, they wait here until the connection is up
+    outgoing_events: Vec<ServerEventType>,
+    assigned_spawn: Option<SpawnPoint>,
+    match_state: Option<MatchState>,
+    /// Results of the last match, cleared when the next one starts
+    match_results: Option<MatchResults>,
+    lobby_state: Option<LobbyState>,
+    incoming_hits: Vec<IncomingHit>,
+    /// Effects the server saw happen, by name and world position
+    incoming_effects: Vec<(String, Vec2)>,
+    /// Recent joins and leaves, newest last
+    player_feed: Vec<(Instant, String)>,
 }
 
 impl ServerConnection {
-    pub fn new(server_addr: &str) -> Self {
-        const PROTOCOL_ID: u64 = 7;
-        let server_addr: SocketAddr = server_addr.parse().unwrap();
-
-        let config = ConnectionConfig::default();
-        let client = RenetClient::new(config.clone());
-        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    /// Starts connecting, the errors are meant to be shown to the player
+    pub fn new(server_addr: &str, player_name: &str, join_type: JoinType, link_conditioner: &LinkConditionerConfig) -> Result<Self, String> {
+        let server_addr: SocketAddr = server_addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} isn't a valid address, it should look like 127.0.0.1:5000", server_addr.trim()))?;
 
         let current_time = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap();
         let client_id = current_time.as_millis() as u64;
 
-        let authentication = ClientAuthentication::Unsecure {
-            server_addr,
-            client_id,
-            user_data: None,
-            protocol_id: PROTOCOL_ID,
+        let proxy_addr = if link_conditioner.is_enabled() {
+            let proxy_addr = spawn_conditioned_proxy("127.0.0.1:0".parse().unwrap(), server_addr, link_conditioner.clone())
+                .map_err(|e| format!("Couldn't start the link conditioner: {}", e))?;
+
+            println!("Conditioning the link to {}: {}", server_addr, link_conditioner.get_description());
+            Some(proxy_addr)
+        } else {
+            None
         };
 
-        let transport = NetcodeClientTransport::new(current_time, authentication, socket).unwrap();
+        let (client, transport) = connect(server_addr, proxy_addr, client_id, player_name, join_type)?;
         let last_renet_updated = Instant::now();
         let last_server_updated = Instant::now();
         let bincode_config = bincode::config::standard();
         let server_clients = HashMap::new();
 
-        Self {
+        Ok(Self {
             bincode_config,
             client,
             transport,
+            server_addr,
+            proxy_addr,
+            link_conditioner: link_conditioner.clone(),
+            player_name: player_name.to_string(),
+            join_type,
+            status: ConnectionStatus::Connecting,
+            status_since: Instant::now(),
+            was_connected: false,
             last_renet_updated,
             last_server_updated,
             client_id,
             server_clients,
-        }
+            server_tick: 0,
+            outgoing_events: vec![],
+            assigned_spawn: None,
+            match_state: None,
+            match_results: None,
+            lobby_state: None,
+            incoming_hits: vec![],
+            incoming_effects: vec![],
+            player_feed: vec![],
+        })
     }
 
 
@@ -64,6 +147,19 @@ impl ServerConnection {
         self.client_id
     }
 
+    /// Spectators have no character, the server ignores anything they send
+    pub fn is_spectator(&self) -> bool {
+        self.join_type == JoinType::Spectator
+    }
+
+    pub fn get_status(&self) -> &ConnectionStatus {
+        &self.status
+    }
+
+    pub fn get_server_addr(&self) -> SocketAddr {
+        self.server_addr
+    }
+
     pub fn get_last_server_updated(&self) -> Instant {
         self.last_server_updated
     }
@@ -73,6 +169,153 @@ impl ServerConnection {
     }
 
 
+    pub fn get_match_state(&self) -> Option<&MatchState> {
+        self.match_state.as_ref()
+    }
+
+    pub fn get_match_results(&self) -> Option<&MatchResults> {
+        self.match_results.as_ref()
+    }
+
+    /// Round trip time and packet loss of our connection, shown on the HUD
+    pub fn get_network_info(&self) -> NetworkInfo {
+        self.client.network_info()
+    }
+
+    /// How badly we're treating our own packets, shown under the network info
+    pub fn get_link_conditioner(&self) -> &LinkConditionerConfig {
+        &self.link_conditioner
+    }
+
+    /// Recent joins and leaves, oldest first
+    pub fn get_player_feed(&self) -> impl Iterator<Item = &String> {
+        self.player_feed.iter().map(|(_, line)| line)
+    }
+
+    pub fn get_lobby_state(&self) -> Option<&LobbyState> {
+        self.lobby_state.as_ref()
+    }
+
+    pub fn select_character(&mut self, character_type: CharacterType) {
+        self.outgoing_events.push(ServerEventType::LobbySelectCharacter(character_type));
+    }
+
+    pub fn vote_map(&mut self, map_index: usize) {
+        self.outgoing_events.push(ServerEventType::LobbyVoteMap(map_index));
+    }
+
+    pub fn set_ready(&mut self, is_ready: bool) {
+        self.outgoing_events.push(ServerEventType::LobbySetReady(is_ready));
+    }
+
+    /// Only does anything while the server is in the lobby
+    pub fn select_match_mode(&mut self, mode: MatchMode) {
+        self.outgoing_events.push(ServerEventType::SelectMatchMode(mode));
+    }
+
+    /// Lets the server know we're leaving instead of waiting for us to time out
+    pub fn disconnect(&mut self) {
+        self.transport.disconnect();
+        self.status = ConnectionStatus::Disconnected { reason: "Left the server".to_string() };
+    }
+
+    /// Moves between the connection states, called every update
+    fn update_status(&mut self) {
+        if self.client.is_connected() {
+            if self.status != ConnectionStatus::Connected {
+                println!("Connected to {}", self.server_addr);
+                self.status = ConnectionStatus::Connected;
+                self.was_connected = true;
+            }
+            return;
+        }
+
+        let timed_out = self.status_since.elapsed() > CONNECT_TIMEOUT;
+
+        let reason = match self.status {
+            ConnectionStatus::Disconnected { .. } => return,
+            _ if self.client.is_disconnected() => self.get_disconnect_reason(),
+            ConnectionStatus::Connecting | ConnectionStatus::Reconnecting { .. } if timed_out => {
+                format!("Timed out connecting to {}", self.server_addr)
+            }
+            _ => return,
+        };
+
+        let attempt = match self.status {
+            ConnectionStatus::Reconnecting { attempt } => attempt + 1,
+            _ => 1,
+        };
+
+        if !self.was_connected || attempt > MAX_RECONNECT_ATTEMPTS {
+            eprintln!("Disconnected: {}", reason);
+            self.status = ConnectionStatus::Disconnected { reason };
+            return;
+        }
+
+        eprintln!("Lost the connection ({}), reconnecting, attempt {}", reason, attempt);
+
+        // same client id as before, that's how the server knows to give us our slot back
+        match connect(self.server_addr, self.proxy_addr, self.client_id, &self.player_name, self.join_type) {
+            Ok((client, transport)) => {
+                self.client = client;
+                self.transport = transport;
+                self.status = ConnectionStatus::Reconnecting { attempt };
+                self.status_since = Instant::now();
+            }
+            Err(e) => {
+                self.status = ConnectionStatus::Disconnected { reason: e };
+            }
+        }
+    }
+
+    fn get_disconnect_reason(&self) -> String {
+        match (self.transport.disconnect_reason(), self.client.disconnect_reason()) {
+            (Some(reason), _) => format!("{:?}", reason),
+            (None, Some(reason)) => format!("{:?}", reason),
+            (None, None) => "Connection lost".to_string(),
+        }
+    }
+
+    /// Asks the server to choose one of the map's spawn points for us, the answer shows up in `take_assigned_spawn`
+    pub fn request_spawn(&mut self) {
+        // only the latest request matters
+        self.outgoing_events.retain(|event| !matches!(event, ServerEventType::RequestSpawn));
+        self.outgoing_events.push(ServerEventType::RequestSpawn);
+    }
+
+    /// Tells the server we died so it can count it towards the match
+    pub fn report_died(&mut self) {
+        self.outgoing_events.push(ServerEventType::Died);
+    }
+
+    /// The server tick remote players are being drawn at.
+    ///
+    /// They're lerped from the previous update to the newest one, `t` is how far along we are.
+    pub fn get_view_tick(&self, t: f32) -> u64 {
+        if t < 0.5 {
+            self.server_tick.saturating_sub(1)
+        } else {
+            self.server_tick
+        }
+    }
+
+    /// Lets the server know one of our attacks connected with `victim` while it was drawn at `view_tick`
+    pub fn report_hit(&mut self, victim: u64, view_tick: u64) {
+        self.outgoing_events.push(ServerEventType::Hit { victim, view_tick });
+    }
+
+    pub fn take_hits(&mut self) -> Vec<IncomingHit> {
+        std::mem::take(&mut self.incoming_hits)
+    }
+
+    pub fn take_effects(&mut self) -> Vec<(String, Vec2)> {
+        std::mem::take(&mut self.incoming_effects)
+    }
+
+    pub fn take_assigned_spawn(&mut self) -> Option<SpawnPoint> {
+        self.assigned_spawn.take()
+    }
+
     pub async fn handle_server_updates(&mut self) {
         let mut got_update = false;
         let now = Instant::now();
@@ -87,17 +330,33 @@ impl ServerConnection {
             }
         }
 
+        self.update_status();
+        self.player_feed.retain(|(added, _)| added.elapsed() < FEED_ENTRY_LIFETIME);
+
         if self.client.is_connected() {
             while let Some(message) = self.client.receive_message(DefaultChannel::ReliableOrdered) {
-                let (client_event_type, _len): (ClientEventType, usize) =
-                    bincode::decode_from_slice(&message[..], self.bincode_config).unwrap();
+                let client_event_type: ClientEventType = match bincode::decode_from_slice(&message[..], self.bincode_config) {
+                    Ok((client_event_type, _)) => client_event_type,
+                    Err(e) => {
+                        eprintln!("Error decoding server message: {:?}", e);
+                        continue;
+                    }
+                };
 
                 match client_event_type {
-                    ClientEventType::ClientCharacterUpdate(message) => {
-                        for (client_id, cse) in message {
+                    ClientEventType::ClientCharacterUpdate { tick, clients, .. } => {
+                        self.server_tick = tick;
+
+                        // anyone missing from the update is gone, even if we never heard them leave
+                        let my_client_id = self.client_id;
+                        self.server_clients.retain(|client_id, _| *client_id == my_client_id || clients.contains_key(client_id));
+
+                        for (client_id, cse) in clients {
                             self.server_clients
                                 .entry(client_id)
                                 .and_modify(|v| {
+                                    v.name = cse.name.clone();
+                                    v.health = cse.health;
                                     v.prev_x_pos = v.x_pos;
                                     v.prev_y_pos = v.y_pos;
                                     v.x_pos = cse.x_pos;
@@ -108,13 +367,50 @@ impl ServerConnection {
                                     v.character_type = cse.character_type.clone();
                                     v.sprite_frame = cse.sprite_frame;
                                     v.facing = cse.facing.clone();
+                                    v.is_invulnerable = cse.is_invulnerable;
                                 })
                                 .or_insert(cse);
                         }
                         got_update = true;
                     }
+                    ClientEventType::SpawnAssigned(spawn) => {
+                        self.assigned_spawn = Some(spawn);
+                    }
+                    ClientEventType::MatchStateUpdate(match_state) => {
+                        if matches!(match_state.phase, MatchPhase::Countdown { .. }) {
+                            self.match_results = None;
+                        }
+                        self.match_state = Some(match_state);
+                    }
+                    ClientEventType::MatchFinished(results) => {
+                        self.match_results = Some(results);
+                    }
+                    ClientEventType::LobbyUpdate(lobby_state) => {
+                        self.lobby_state = Some(lobby_state);
+                    }
+                    ClientEventType::Hit { attacker: _, hit, attacker_facing, is_fatal } => {
+                        self.incoming_hits.push(IncomingHit { hit, attacker_facing, is_fatal });
+                    }
+                    ClientEventType::Effect { name, position: (x, y) } => {
+                        self.incoming_effects.push((name, vec2(x, y)));
+                    }
+                    ClientEventType::PlayerJoined { client_id, name } => {
+                        if client_id != self.client_id {
+                            self.player_feed.push((Instant::now(), format!("{} joined", name)));
+                        }
+                    }
+                    ClientEventType::PlayerLeft { client_id, name } => {
+                        self.server_clients.remove(&client_id);
+                        self.player_feed.push((Instant::now(), format!("{} left", name)));
+                    }
                 }
             }
+
+            // queued events go out here so they also get sent from the lobby
+            for event in self.outgoing_events.drain(..) {
+                let encoded_event = bincode::encode_to_vec(&event, self.bincode_config).unwrap();
+                self.client.send_message(DefaultChannel::ReliableOrdered, encoded_event);
+            }
         }
 
         if got_update {
@@ -131,7 +427,7 @@ impl ServerConnection {
     }
 
 
-    pub async fn handle_client_updates(&mut self, my_character: &Box<dyn CharacterTrait>) {
+    pub async fn handle_client_updates(&mut self, my_character: &Box<dyn CharacterTrait>, is_invulnerable: bool) {
         if self.client.is_connected() {
             let client_id = my_character.get_client_id().expect("Client ID not set");
 
@@ -148,6 +444,7 @@ impl ServerConnection {
                     sc.anim_type = my_character.get_anim_type();
                     sc.character_type = my_character.get_character_type();
                     sc.sprite_frame = my_character.get_sprite_frame();
+                    sc.is_invulnerable = is_invulnerable;
                 },
                 None => {
                     eprintln!("Client ID not found in server clients: {:?}", client_id);
@@ -156,14 +453,14 @@ impl ServerConnection {
 
             match self.server_clients.get(&client_id) {
                 Some(sc) => {
-                    let encoded_client_server_event = bincode::encode_to_vec(sc, self.bincode_config).unwrap();
+                    let event = ServerEventType::ClientCharacterUpdate(sc.clone());
+                    let encoded_client_server_event = bincode::encode_to_vec(&event, self.bincode_config).unwrap();
                     self.client.send_message(DefaultChannel::ReliableOrdered, encoded_client_server_event);
                 },
                 None => {
                     eprintln!("Client ID not found in server clients: {:?}", client_id);
                 }
             }
-
         }
 
         match self.transport.send_packets(&mut self.client) {
@@ -174,3 +471,55 @@ impl ServerConnection {
         }
     }
 }
+
+/// A fresh client and transport for `server_addr`, renet can't reuse them once they've disconnected.
+/// With a `proxy_addr` our packets go through it instead
+fn connect(
+    server_addr: SocketAddr,
+    proxy_addr: Option<SocketAddr>,
+    client_id: u64,
+    player_name: &str,
+    join_type: JoinType,
+) -> Result<(RenetClient, NetcodeClientTransport), String> {
+    let client = RenetClient::new(ConnectionConfig::default());
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Couldn't open a socket: {}", e))?;
+
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+
+    // the server reads our name out of this when we connect
+    let user_data = player_name_to_user_data(player_name, join_type);
+
+    let authentication = match proxy_addr {
+        // the client sends to the first address in its token, but the server turns away tokens that don't
+        // list its own address, so the proxy goes first and the server after it. this is the token renet
+        // would make for an unsecure connection, signed with the same empty key the server checks with
+        Some(proxy_addr) => {
+            let connect_token = ConnectToken::generate(
+                current_time,
+                PROTOCOL_ID,
+                CONNECT_TOKEN_EXPIRE_SECONDS,
+                client_id,
+                CONNECT_TOKEN_TIMEOUT_SECONDS,
+                vec![proxy_addr, server_addr],
+                Some(&user_data),
+                &[0; NETCODE_KEY_BYTES],
+            )
+            .map_err(|e| format!("Couldn't make a connect token: {:?}", e))?;
+
+            ClientAuthentication::Secure { connect_token }
+        }
+        None => ClientAuthentication::Unsecure {
+            server_addr,
+            client_id,
+            user_data: Some(user_data),
+            protocol_id: PROTOCOL_ID,
+        },
+    };
+
+    let transport = NetcodeClientTransport::new(current_time, authentication, socket)
+        .map_err(|e| format!("Couldn't connect to {}: {:?}", server_addr, e))?;
+
+    Ok((client, transport))
+}
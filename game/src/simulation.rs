@@ -0,0 +1,32 @@
+use common::simulation::SIM_DT;
+
+// after a long hitch we drop the time we can't catch up on instead of freezing to simulate it all
+const MAX_STEPS_PER_FRAME: u32 = 5;
+
+/// Turns variable frame times into a whole number of fixed simulation ticks
+pub struct FixedStep {
+    accumulator: f32,
+}
+
+impl FixedStep {
+    pub fn new() -> Self {
+        Self { accumulator: 0.0 }
+    }
+
+    /// How many ticks to simulate for a frame that took `frame_time` seconds
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time;
+
+        let mut steps = 0;
+        while self.accumulator >= SIM_DT && steps < MAX_STEPS_PER_FRAME {
+            self.accumulator -= SIM_DT;
+            steps += 1;
+        }
+
+        if steps == MAX_STEPS_PER_FRAME {
+            self.accumulator = self.accumulator.min(SIM_DT);
+        }
+
+        steps
+    }
+}
@@ -0,0 +1,30 @@
+use std::net::SocketAddr;
+
+use macroquad::{
+    color::{Color, GOLD},
+    shapes::draw_rectangle,
+    text::{draw_text, measure_text},
+    window::{screen_height, screen_width},
+};
+
+use crate::server::ConnectionStatus;
+
+const STATUS_FONT_SIZE: u16 = 28;
+const PANEL_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+
+/// A banner in the middle of the screen while we're not connected, nothing once we are
+pub fn draw_connection_status(status: &ConnectionStatus, server_addr: SocketAddr) {
+    let text = match status {
+        ConnectionStatus::Connected => return,
+        ConnectionStatus::Connecting => format!("Connecting to {}...", server_addr),
+        ConnectionStatus::Reconnecting { attempt } => format!("Connection lost, reconnecting ({})...", attempt),
+        ConnectionStatus::Disconnected { reason } => format!("Disconnected: {}", reason),
+    };
+
+    let dims = measure_text(&text, None, STATUS_FONT_SIZE, 1.0);
+    let x = (screen_width() - dims.width) / 2.0;
+    let y = screen_height() / 3.0;
+
+    draw_rectangle(x - 20.0, y - dims.height - 16.0, dims.width + 40.0, dims.height + 32.0, PANEL_COLOR);
+    draw_text(&text, x, y, STATUS_FONT_SIZE as f32, GOLD);
+}
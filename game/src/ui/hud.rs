@@ -0,0 +1,191 @@
+use macroquad::{
+    color::{Color, BLACK, GOLD, GRAY, WHITE},
+    math::{vec2, Rect},
+    shapes::{draw_circle, draw_circle_lines, draw_rectangle, draw_rectangle_lines},
+    text::{draw_text, measure_text},
+    texture::{draw_texture_ex, DrawTextureParams},
+    window::{screen_height, screen_width},
+};
+use renet::NetworkInfo;
+
+use common::{
+    animation::{AnimationType, CharacterTextures, CharacterType},
+    constants::{MAX_HEALTH, TILE_HEIGHT, TILE_WIDTH},
+    link_conditioner::LinkConditionerConfig,
+};
+
+use super::match_hud::format_clock;
+
+// panels never get wider than this, with few players they just sit in the middle
+const MAX_PANEL_WIDTH: f32 = 260.0;
+const PANEL_GAP: f32 = 12.0;
+const HUD_MARGIN: f32 = 16.0;
+// panel height relative to its width
+const PANEL_ASPECT: f32 = 0.36;
+const MAX_STOCK_ICONS: u32 = 5;
+
+const PANEL_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+const LOCAL_PANEL_COLOR: Color = Color::new(0.15, 0.15, 0.3, 0.75);
+const HEALTH_COLOR: Color = Color::new(0.2, 0.85, 0.3, 1.0);
+const LOW_HEALTH_COLOR: Color = Color::new(0.9, 0.2, 0.2, 1.0);
+const ELIMINATED_TINT: Color = Color::new(0.4, 0.4, 0.4, 1.0);
+
+/// One player's panel along the bottom of the screen
+pub struct HudPlayer {
+    pub name: String,
+    pub character_type: CharacterType,
+    pub health: i32,
+    /// Only set in stock matches
+    pub stocks_left: Option<u32>,
+    pub is_eliminated: bool,
+    pub is_local: bool,
+}
+
+/// Player panels, the match clock and connection stats, drawn in screen space after the world
+pub fn draw_hud(
+    players: &[HudPlayer],
+    textures: &CharacterTextures,
+    time_left: Option<f32>,
+    network_info: Option<&NetworkInfo>,
+    link_conditioner: Option<&LinkConditionerConfig>,
+) {
+    draw_player_panels(players, textures);
+
+    if let Some(time_left) = time_left {
+        draw_clock(time_left);
+    }
+
+    if let Some(network_info) = network_info {
+        draw_network_info(network_info, link_conditioner.filter(|config| config.is_enabled()));
+    }
+}
+
+fn draw_player_panels(players: &[HudPlayer], textures: &CharacterTextures) {
+    if players.is_empty() {
+        return;
+    }
+
+    // share the width between everyone, shrinking the panels once they stop fitting
+    let count = players.len() as f32;
+    let available = screen_width() - HUD_MARGIN * 2.0 - PANEL_GAP * (count - 1.0);
+    let panel_width = (available / count).min(MAX_PANEL_WIDTH);
+    let panel_height = panel_width * PANEL_ASPECT;
+
+    let total_width = panel_width * count + PANEL_GAP * (count - 1.0);
+    let start_x = (screen_width() - total_width) / 2.0;
+    let y = screen_height() - panel_height - HUD_MARGIN;
+
+    for (i, player) in players.iter().enumerate() {
+        let x = start_x + i as f32 * (panel_width + PANEL_GAP);
+        draw_player_panel(player, textures, Rect::new(x, y, panel_width, panel_height));
+    }
+}
+
+fn draw_player_panel(player: &HudPlayer, textures: &CharacterTextures, panel: Rect) {
+    let background = if player.is_local { LOCAL_PANEL_COLOR } else { PANEL_COLOR };
+    draw_rectangle(panel.x, panel.y, panel.w, panel.h, background);
+
+    if player.is_local {
+        draw_rectangle_lines(panel.x, panel.y, panel.w, panel.h, 2.0, GOLD);
+    }
+
+    let padding = panel.h * 0.1;
+    let portrait_size = panel.h - padding * 2.0;
+    let tint = if player.is_eliminated { ELIMINATED_TINT } else { WHITE };
+
+    // the fighters only fill the middle of their frames, so we crop in on the first idle frame
+    let texture = textures.get_texture(&player.character_type, &AnimationType::Idle);
+    draw_rectangle(panel.x + padding, panel.y + padding, portrait_size, portrait_size, BLACK);
+    draw_texture_ex(
+        &texture,
+        panel.x + padding,
+        panel.y + padding,
+        tint,
+        DrawTextureParams {
+            source: Some(Rect::new(TILE_WIDTH * 0.25, TILE_HEIGHT * 0.35, TILE_WIDTH * 0.5, TILE_HEIGHT * 0.5)),
+            dest_size: Some(vec2(portrait_size, portrait_size)),
+            ..Default::default()
+        },
+    );
+
+    let info_x = panel.x + padding * 2.0 + portrait_size;
+    let info_width = panel.right() - padding - info_x;
+    let font_size = (panel.h * 0.26).max(12.0);
+
+    // names get cut down until they fit next to the portrait
+    let mut name = player.name.clone();
+    while !name.is_empty() && measure_text(&name, None, font_size as u16, 1.0).width > info_width {
+        name.pop();
+    }
+
+    let name_y = panel.y + padding + font_size * 0.8;
+    draw_text(&name, info_x, name_y, font_size, if player.is_eliminated { GRAY } else { WHITE });
+
+    let bar_y = name_y + padding;
+    let bar_height = (panel.h * 0.14).max(4.0);
+    let health_fraction = (player.health as f32 / MAX_HEALTH as f32).clamp(0.0, 1.0);
+    let health_color = if health_fraction > 0.3 { HEALTH_COLOR } else { LOW_HEALTH_COLOR };
+
+    draw_rectangle(info_x - 1.0, bar_y - 1.0, info_width + 2.0, bar_height + 2.0, BLACK);
+    draw_rectangle(info_x, bar_y, info_width * health_fraction, bar_height, health_color);
+
+    let stocks_y = bar_y + bar_height + padding + font_size * 0.4;
+
+    if player.is_eliminated {
+        draw_text("OUT", info_x, stocks_y + font_size * 0.4, font_size, LOW_HEALTH_COLOR);
+    } else if let Some(stocks_left) = player.stocks_left {
+        let radius = (font_size * 0.3).max(3.0);
+
+        for i in 0..stocks_left.min(MAX_STOCK_ICONS) {
+            let center_x = info_x + radius + i as f32 * radius * 2.6;
+            draw_circle(center_x, stocks_y, radius, GOLD);
+            draw_circle_lines(center_x, stocks_y, radius, 1.0, BLACK);
+        }
+
+        // too many lives to draw, put the count next to the icons instead
+        if stocks_left > MAX_STOCK_ICONS {
+            let text_x = info_x + MAX_STOCK_ICONS as f32 * radius * 2.6;
+            draw_text(&format!("x{}", stocks_left), text_x, stocks_y + radius, font_size, WHITE);
+        }
+    }
+}
+
+fn draw_clock(time_left: f32) {
+    let text = format_clock(time_left);
+    let font_size = 40.0;
+    let size = measure_text(&text, None, font_size as u16, 1.0);
+    let x = screen_width() - size.width - HUD_MARGIN;
+    let y = HUD_MARGIN + size.height;
+
+    // the last ten seconds go red
+    let color = if time_left <= 10.0 { LOW_HEALTH_COLOR } else { WHITE };
+
+    draw_text(&text, x + 2.0, y + 2.0, font_size, BLACK);
+    draw_text(&text, x, y, font_size, color);
+}
+
+fn draw_network_info(network_info: &NetworkInfo, link_conditioner: Option<&LinkConditionerConfig>) {
+    // renet reports rtt in seconds and packet loss as a fraction
+    let mut lines = vec![format!(
+        "{:.0} ms   {:.1}% loss",
+        network_info.rtt * 1000.0,
+        network_info.packet_loss * 100.0,
+    )];
+
+    // so nobody forgets the lag is on purpose
+    if let Some(config) = link_conditioner {
+        lines.push(format!("conditioned: {}", config.get_description()));
+    }
+
+    let font_size = 18.0;
+    let mut y = HUD_MARGIN + 40.0 + 4.0;
+
+    for text in lines {
+        let size = measure_text(&text, None, font_size as u16, 1.0);
+        let x = screen_width() - size.width - HUD_MARGIN;
+        y += size.height + 4.0;
+
+        draw_text(&text, x + 1.0, y + 1.0, font_size, BLACK);
+        draw_text(&text, x, y, font_size, WHITE);
+    }
+}
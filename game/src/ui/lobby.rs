@@ -0,0 +1,170 @@
+use macroquad::prelude::*;
+
+use macroquad::ui::{
+    hash, root_ui,
+    widgets::{self, Group},
+};
+
+use common::{
+    animation::CharacterType,
+    lobby::LobbyState,
+    match_rules::{MatchMode, MatchState},
+};
+
+use crate::maps::map::GameMap;
+
+use super::main_menu::{draw_menu_background, menu_window_position};
+
+/// What the player clicked in the lobby this frame, main passes these on to the server
+pub enum LobbyAction {
+    SelectCharacter(CharacterType),
+    VoteMap(usize),
+    SelectMatchMode(MatchMode),
+    SetReady(bool),
+    Leave,
+}
+
+const CHARACTERS: [(CharacterType, &str); 3] = [
+    (CharacterType::Fighter, "Fighter"),
+    (CharacterType::Shinobi, "Shinobi"),
+    (CharacterType::Samurai, "Samurai"),
+];
+
+pub fn draw_lobby(
+    background: &Texture2D,
+    lobby: Option<&LobbyState>,
+    match_state: Option<&MatchState>,
+    my_client_id: u64,
+    is_spectator: bool,
+    maps: &[GameMap],
+) -> Vec<LobbyAction> {
+    draw_menu_background(background);
+
+    let mut actions = vec![];
+
+    let id = hash!();
+    let size = vec2(620., 560.);
+    widgets::Window::new(id, menu_window_position(id, size), size)
+        .label("Lobby")
+        .titlebar(true)
+        .ui(&mut *root_ui(), |ui| {
+            // nothing from the server yet
+            let Some(lobby) = lobby else {
+                ui.label(Vec2::new(10., 10.), "Connecting...");
+
+                if ui.button(Vec2::new(10., 40.), "Back") {
+                    actions.push(LobbyAction::Leave);
+                }
+                return;
+            };
+
+            let me = lobby.players.get(&my_client_id);
+
+            // spectators just see who's playing and wait for the match
+            if is_spectator {
+                let mut players: Vec<_> = lobby.players.values().collect();
+                players.sort_by(|a, b| a.name.cmp(&b.name));
+
+                ui.label(Vec2::new(10., 10.), "Spectating, the match starts once every player is ready");
+
+                for (i, player) in players.iter().enumerate() {
+                    let ready = if player.is_ready { "Ready" } else { "..." };
+                    ui.label(Vec2::new(10., 40. + i as f32 * 22.), &format!("{} - {}", player.name, ready));
+                }
+
+                if ui.button(Vec2::new(10., 480.), "Leave") {
+                    actions.push(LobbyAction::Leave);
+                }
+                return;
+            }
+
+            Group::new(hash!("lobby-players"), Vec2::new(300., 300.)).ui(ui, |ui| {
+                ui.label(Vec2::new(10., 10.), "Players:");
+
+                let mut players: Vec<_> = lobby.players.iter().collect();
+                players.sort_by_key(|(client_id, _)| **client_id);
+
+                for (i, (client_id, player)) in players.iter().enumerate() {
+                    let character = CHARACTERS
+                        .iter()
+                        .find(|(character_type, _)| *character_type == player.character_type)
+                        .map(|(_, label)| *label)
+                        .unwrap_or("?");
+
+                    let ready = if player.is_ready { "Ready" } else { "..." };
+                    let you = if **client_id == my_client_id { " (you)" } else { "" };
+                    let host = if lobby.host == Some(**client_id) { " (host)" } else { "" };
+
+                    ui.label(Vec2::new(10., 35. + i as f32 * 22.), &format!("{}{}{} - {} - {}", player.name, you, host, character, ready));
+                }
+            });
+
+            ui.same_line(0.);
+
+            Group::new(hash!("lobby-picks"), Vec2::new(300., 300.)).ui(ui, |ui| {
+                ui.label(Vec2::new(10., 10.), "Character:");
+
+                for (i, (character_type, label)) in CHARACTERS.iter().enumerate() {
+                    let is_selected = me.is_some_and(|me| me.character_type == *character_type);
+                    let label = if is_selected { format!("> {}", label) } else { label.to_string() };
+
+                    if ui.button(Vec2::new(10. + i as f32 * 95., 35.), label.as_str()) {
+                        actions.push(LobbyAction::SelectCharacter(character_type.clone()));
+                    }
+                }
+
+                ui.label(Vec2::new(10., 75.), "Vote for a map:");
+
+                for (i, map_name) in lobby.map_rotation.iter().enumerate() {
+                    let is_voted = me.is_some_and(|me| me.map_vote == Some(i));
+                    let is_missing = !maps.iter().any(|map| map.get_name() == *map_name);
+
+                    let label = format!(
+                        "{}{} ({}){}",
+                        if is_voted { "> " } else { "" },
+                        map_name,
+                        lobby.get_vote_count(i),
+                        if is_missing { " - missing" } else { "" },
+                    );
+
+                    if ui.button(Vec2::new(10., 100. + i as f32 * 28.), label.as_str()) {
+                        actions.push(LobbyAction::VoteMap(i));
+                    }
+                }
+            });
+
+            Group::new(hash!("lobby-mode"), Vec2::new(604., 130.)).ui(ui, |ui| {
+                let current_mode = match_state.map(|match_state| &match_state.mode);
+                let description = current_mode.map(|mode| mode.get_description()).unwrap_or_default();
+                ui.label(Vec2::new(10., 10.), &format!("Rules: {}", description));
+
+                if lobby.host != Some(my_client_id) {
+                    ui.label(Vec2::new(10., 40.), "The host picks the rules");
+                    return;
+                }
+
+                for (i, mode) in MatchMode::presets().into_iter().enumerate() {
+                    let position = Vec2::new(10. + (i % 3) as f32 * 195., 40. + (i / 3) as f32 * 30.);
+
+                    if ui.button(position, mode.get_description().as_str()) {
+                        actions.push(LobbyAction::SelectMatchMode(mode));
+                    }
+                }
+            });
+
+            Group::new(hash!("lobby-ready"), Vec2::new(604., 60.)).ui(ui, |ui| {
+                let is_ready = me.is_some_and(|me| me.is_ready);
+                let ready_label = if is_ready { "Not ready" } else { "Ready up" };
+
+                if ui.button(Vec2::new(10., 15.), ready_label) {
+                    actions.push(LobbyAction::SetReady(!is_ready));
+                }
+
+                if ui.button(Vec2::new(150., 15.), "Leave") {
+                    actions.push(LobbyAction::Leave);
+                }
+            });
+        });
+
+    actions
+}
@@ -8,7 +8,9 @@ use macroquad::ui::{
     widgets::{self, Group},
 };
 
-use crate::maps::map::GameMap;
+use common::{ai::AiDifficulty, animation::CharacterType, constants::MAX_PLAYER_NAME_LENGTH};
+
+use crate::{audio::sound_manager::VolumeSettings, maps::map::GameMap};
 
 pub enum GameMode {
     SinglePlayer,
@@ -22,12 +24,47 @@ pub enum CharacterSelection {
     Character3,
 }
 
+/// Who you fight in single player
+#[derive(Clone)]
+pub enum OpponentSelection {
+    Nobody,
+    Cpu(AiDifficulty),
+}
+
+impl CharacterSelection {
+    pub fn get_character_type(&self) -> CharacterType {
+        match self {
+            CharacterSelection::Character1 => CharacterType::Fighter,
+            CharacterSelection::Character2 => CharacterType::Shinobi,
+            CharacterSelection::Character3 => CharacterType::Samurai,
+        }
+    }
+
+
+    /// The lobby talks in character types, this maps them back to the playable characters
+    pub fn from_character_type(character_type: &CharacterType) -> Self {
+        match character_type {
+            CharacterType::Fighter => CharacterSelection::Character1,
+            CharacterType::Shinobi => CharacterSelection::Character2,
+            CharacterType::Samurai => CharacterSelection::Character3,
+        }
+    }
+}
+
 pub struct MenuState {
     pub game_mode: Option<GameMode>,
     pub character_selection: Option<CharacterSelection>,
+    pub opponent_selection: Option<OpponentSelection>,
     pub map_selection: Option<usize>,
     pub connect_pressed: bool,
+    /// Connect to watch instead of play
+    pub is_spectating: bool,
     pub server_address: String,
+    pub player_name: String,
+    /// Why the last connection failed or dropped, shown in the connect dialog
+    pub connect_error: Option<String>,
+    pub is_showing_settings: bool,
+    pub volume: VolumeSettings,
 }
 
 impl MenuState {
@@ -35,18 +72,28 @@ impl MenuState {
         Self {
             game_mode: None,
             character_selection: None,
+            opponent_selection: None,
             map_selection: None,
             connect_pressed: false,
+            is_spectating: false,
             server_address: "44.220.137.149:5000".to_string(),
+            player_name: String::new(),
+            connect_error: None,
+            is_showing_settings: false,
+            volume: VolumeSettings::new(),
         }
     }
     
     /// Go back one step in the menu
     pub fn back(&mut self) {
-        if self.map_selection.is_some() {
+        if self.is_showing_settings {
+            self.is_showing_settings = false;
+        } else if self.map_selection.is_some() {
             self.map_selection = None;
         } else if self.connect_pressed {
             self.connect_pressed = false;
+        } else if self.opponent_selection.is_some() {
+            self.opponent_selection = None;
         } else if self.character_selection.is_some() {
             self.character_selection = None;
         } else if self.game_mode.is_some() {
@@ -55,16 +102,62 @@ impl MenuState {
     }
 }
 
+/// Centers a menu window horizontally and keeps it in the lower part of the screen whatever the window size
+pub fn menu_window_position(id: u64, size: Vec2) -> Vec2 {
+    let x = (screen_width() - size.x) / 2.0;
+    let y = (screen_height() * 0.72).min(screen_height() - size.y - 10.0).max(0.0);
+    let position = vec2(x.max(0.0), y);
+
+    // windows remember where they were first opened, so we move them on resize
+    root_ui().move_window(id, position);
+    position
+}
+
+/// Scales the splash to cover the whole window, cropping whatever doesn't fit
+pub fn draw_menu_background(background: &Texture2D) {
+    let scale = (screen_width() / background.width()).max(screen_height() / background.height());
+    let size = vec2(background.width() * scale, background.height() * scale);
+
+    draw_texture_ex(background, (screen_width() - size.x) / 2.0, (screen_height() - size.y) / 2.0, WHITE, DrawTextureParams {
+        dest_size: Some(size),
+        ..Default::default()
+    });
+}
+
 pub async fn draw_menu(background: &Texture2D, maps: &Vec<GameMap>, menu_state: &mut MenuState, callback: Rc<RefCell<impl FnMut(usize)>>) {
-    draw_texture(&background, 0., 0., WHITE);
+    draw_menu_background(background);
+
+    // Settings Window, opened from the game mode window
+    if menu_state.is_showing_settings {
+        let id = hash!();
+        let size = vec2(310., 220.);
+        widgets::Window::new(id, menu_window_position(id, size), size)
+            .label("Settings")
+            .titlebar(true)
+            .ui(&mut *root_ui(), |ui| {
+                Group::new(hash!("settings"), Vec2::new(300., 160.)).ui(ui, |ui| {
+                    ui.label(Vec2::new(10., 10.), "Volume:");
 
+                    ui.slider(hash!(), "Master", 0.0..1.0, &mut menu_state.volume.master);
+                    ui.slider(hash!(), "Music", 0.0..1.0, &mut menu_state.volume.music);
+                    ui.slider(hash!(), "Effects", 0.0..1.0, &mut menu_state.volume.effects);
+
+                    // Back button
+                    if ui.button(Vec2::new(40., 120.), "Back") {
+                        menu_state.back();
+                    }
+                });
+            });
+    }
     // Game Mode Selection Window
-    if menu_state.game_mode.is_none() {
-        widgets::Window::new(hash!(), vec2(500., 600.), vec2(310., 180.))
+    else if menu_state.game_mode.is_none() {
+        let id = hash!();
+        let size = vec2(310., 220.);
+        widgets::Window::new(id, menu_window_position(id, size), size)
             .label("Game Mode")
             .titlebar(true)
             .ui(&mut *root_ui(), |ui| {
-                Group::new(hash!("game-mode"), Vec2::new(300., 120.)).ui(ui, |ui| {
+                Group::new(hash!("game-mode"), Vec2::new(300., 160.)).ui(ui, |ui| {
                     ui.label(Vec2::new(10., 10.), "Select Game Mode:");
                     
                     if ui.button(Vec2::new(40., 40.), "Single Player") {
@@ -74,12 +167,18 @@ pub async fn draw_menu(background: &Texture2D, maps: &Vec<GameMap>, menu_state:
                     if ui.button(Vec2::new(40., 80.), "Multiplayer") {
                         menu_state.game_mode = Some(GameMode::Multiplayer);
                     }
+
+                    if ui.button(Vec2::new(40., 120.), "Settings") {
+                        menu_state.is_showing_settings = true;
+                    }
                 });
             });
     }
-    // Character Selection Window
-    else if menu_state.character_selection.is_none() {
-        widgets::Window::new(hash!(), vec2(500., 600.), vec2(310., 260.))
+    // Character Selection Window, in multiplayer this happens in the lobby
+    else if matches!(menu_state.game_mode, Some(GameMode::SinglePlayer)) && menu_state.character_selection.is_none() {
+        let id = hash!();
+        let size = vec2(310., 260.);
+        widgets::Window::new(id, menu_window_position(id, size), size)
             .label("Character Selection")
             .titlebar(true)
             .ui(&mut *root_ui(), |ui| {
@@ -107,29 +206,80 @@ pub async fn draw_menu(background: &Texture2D, maps: &Vec<GameMap>, menu_state:
     }
     // Multiplayer Connect Button (if in multiplayer mode)
     else if matches!(menu_state.game_mode, Some(GameMode::Multiplayer)) && !menu_state.connect_pressed {
-        widgets::Window::new(hash!(), vec2(500., 600.), vec2(310., 220.))
+        let id = hash!();
+        let size = vec2(310., 250.);
+        widgets::Window::new(id, menu_window_position(id, size), size)
             .label("Connect")
             .titlebar(true)
             .ui(&mut *root_ui(), |ui| {
-                Group::new(hash!("connect"), Vec2::new(300., 160.)).ui(ui, |ui| {
+                Group::new(hash!("connect"), Vec2::new(300., 190.)).ui(ui, |ui| {
                     ui.input_text(hash!(), "Server address", &mut menu_state.server_address);
+                    ui.input_text(hash!(), "Name", &mut menu_state.player_name);
+
+                    // the server cuts names off anyway, this just shows it while typing
+                    if menu_state.player_name.chars().count() > MAX_PLAYER_NAME_LENGTH {
+                        menu_state.player_name = menu_state.player_name.chars().take(MAX_PLAYER_NAME_LENGTH).collect();
+                    }
                     
-                    ui.label(Vec2::new(10., 50.), "Ready to connect:");
+                    match &menu_state.connect_error {
+                        Some(error) => ui.label(Vec2::new(10., 80.), error),
+                        None => ui.label(Vec2::new(10., 80.), "Ready to connect:"),
+                    }
                     
-                    if ui.button(Vec2::new(40., 80.), "Connect") {
+                    if ui.button(Vec2::new(40., 110.), "Connect") {
                         menu_state.connect_pressed = true;
+                        menu_state.is_spectating = false;
+                        menu_state.connect_error = None;
+                    }
+
+                    if ui.button(Vec2::new(140., 110.), "Spectate") {
+                        menu_state.connect_pressed = true;
+                        menu_state.is_spectating = true;
+                        menu_state.connect_error = None;
                     }
                     
                     // Back button
-                    if ui.button(Vec2::new(40., 120.), "Back") {
+                    if ui.button(Vec2::new(40., 150.), "Back") {
+                        menu_state.back();
+                    }
+                });
+            });
+    }
+    // Opponent Selection Window, single player can be against a CPU
+    else if matches!(menu_state.game_mode, Some(GameMode::SinglePlayer)) && menu_state.opponent_selection.is_none() {
+        let id = hash!();
+        let size = vec2(310., 300.);
+        widgets::Window::new(id, menu_window_position(id, size), size)
+            .label("Opponent")
+            .titlebar(true)
+            .ui(&mut *root_ui(), |ui| {
+                Group::new(hash!("opponent-select"), Vec2::new(300., 240.)).ui(ui, |ui| {
+                    ui.label(Vec2::new(10., 10.), "Select Opponent:");
+
+                    if ui.button(Vec2::new(40., 40.), "Nobody") {
+                        menu_state.opponent_selection = Some(OpponentSelection::Nobody);
+                    }
+
+                    for (i, difficulty) in AiDifficulty::ALL.iter().enumerate() {
+                        let label = format!("CPU ({})", difficulty.get_description());
+
+                        if ui.button(Vec2::new(40., 80. + i as f32 * 40.), label.as_str()) {
+                            menu_state.opponent_selection = Some(OpponentSelection::Cpu(*difficulty));
+                        }
+                    }
+
+                    // Back button
+                    if ui.button(Vec2::new(40., 200.), "Back") {
                         menu_state.back();
                     }
                 });
             });
     }
-    // Map Selection Window (after completing previous steps)
-    else if menu_state.map_selection.is_none() {
-        widgets::Window::new(hash!(), vec2(500., 600.), vec2(310., 450.))
+    // Map Selection Window (after completing previous steps), in multiplayer the lobby votes on it
+    else if matches!(menu_state.game_mode, Some(GameMode::SinglePlayer)) && menu_state.map_selection.is_none() {
+        let id = hash!();
+        let size = vec2(310., 450.);
+        widgets::Window::new(id, menu_window_position(id, size), size)
             .label("Select Map")
             .titlebar(true)
             .ui(&mut *root_ui(), |ui| {
@@ -0,0 +1,78 @@
+use macroquad::{
+    color::{Color, BLACK, GOLD, WHITE},
+    shapes::draw_rectangle,
+    text::{draw_text, measure_text},
+    window::{screen_height, screen_width},
+};
+
+use common::match_rules::{MatchPhase, MatchResults, MatchState};
+
+const STATUS_FONT_SIZE: u16 = 28;
+const COUNTDOWN_FONT_SIZE: u16 = 120;
+const RESULTS_FONT_SIZE: u16 = 30;
+const RESULTS_LINE_HEIGHT: f32 = 40.0;
+const PANEL_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+
+/// Mode and countdown across the top of the screen, drawn in screen space. The clock is part of the HUD
+pub fn draw_match_status(match_state: &MatchState, my_client_id: u64) {
+    draw_centered_text(&match_state.mode.get_description(), 30.0, STATUS_FONT_SIZE, WHITE);
+
+    let status = match &match_state.phase {
+        MatchPhase::Lobby => Some("Waiting for players".to_string()),
+        MatchPhase::Countdown { seconds_left } => {
+            let count = seconds_left.ceil().max(1.0) as i32;
+            draw_centered_text(&count.to_string(), screen_height() / 2.0, COUNTDOWN_FONT_SIZE, GOLD);
+            None
+        }
+        MatchPhase::InProgress => None,
+        MatchPhase::SuddenDeath => Some("SUDDEN DEATH".to_string()),
+        MatchPhase::Finished { .. } => None,
+    };
+
+    if let Some(status) = status {
+        draw_centered_text(&status, 60.0, STATUS_FONT_SIZE, WHITE);
+    }
+
+    // spectators aren't in the scores, they don't need telling
+    let is_playing = match_state.scores.contains_key(&my_client_id);
+
+    if match_state.is_running() && is_playing && !match_state.can_play(my_client_id) {
+        draw_centered_text("Knocked out, spectating", 90.0, STATUS_FONT_SIZE, GOLD);
+    }
+}
+
+/// Final standings in the middle of the screen
+pub fn draw_match_results(results: &MatchResults) {
+    let width = 520.0;
+    let height = RESULTS_LINE_HEIGHT * (results.standings.len() as f32 + 2.0) + 20.0;
+    let x = (screen_width() - width) / 2.0;
+    let y = (screen_height() - height) / 2.0;
+
+    draw_rectangle(x, y, width, height, PANEL_COLOR);
+
+    let winner = results
+        .winner
+        .and_then(|winner| results.standings.iter().find(|(client_id, _)| *client_id == winner))
+        .map(|(_, score)| format!("{} wins!", score.name))
+        .unwrap_or_else(|| "No contest".to_string());
+
+    draw_centered_text(&winner, y + RESULTS_LINE_HEIGHT, RESULTS_FONT_SIZE, GOLD);
+
+    for (i, (_, score)) in results.standings.iter().enumerate() {
+        let line = format!("{}. {}   KOs {}   Falls {}", i + 1, score.name, score.kos, score.falls);
+        draw_text(&line, x + 30.0, y + RESULTS_LINE_HEIGHT * (i as f32 + 2.0), RESULTS_FONT_SIZE as f32, WHITE);
+    }
+}
+
+pub fn format_clock(seconds: f32) -> String {
+    let seconds = seconds.ceil() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn draw_centered_text(text: &str, y: f32, font_size: u16, color: Color) {
+    let size = measure_text(text, None, font_size, 1.0);
+    let x = (screen_width() - size.width) / 2.0;
+
+    draw_text(text, x + 2.0, y + 2.0, font_size as f32, BLACK);
+    draw_text(text, x, y, font_size as f32, color);
+}
@@ -1 +1,7 @@
+pub mod connection_status;
+pub mod hud;
+pub mod lobby;
 pub mod main_menu;
+pub mod match_hud;
+pub mod nameplate;
+pub mod player_feed;
@@ -0,0 +1,40 @@
+use macroquad::{
+    color::{Color, BLACK, WHITE},
+    math::Vec2,
+    shapes::draw_rectangle,
+    text::{draw_text, measure_text},
+};
+
+use common::constants::MAX_HEALTH;
+
+const NAMEPLATE_FONT_SIZE: u16 = 18;
+const HEALTH_BAR_WIDTH: f32 = 60.0;
+const HEALTH_BAR_HEIGHT: f32 = 6.0;
+// space between the top of the hitbox and the health bar
+const NAMEPLATE_OFFSET: f32 = 40.0;
+
+const HEALTH_COLOR: Color = Color::new(0.2, 0.85, 0.3, 1.0);
+const LOW_HEALTH_COLOR: Color = Color::new(0.9, 0.2, 0.2, 1.0);
+
+/// Draws a name with a health bar under it, centered above an actor.
+///
+/// `actor_pos` and `actor_width` are the actor's collider, in world coordinates.
+pub fn draw_nameplate(name: &str, health: i32, actor_pos: Vec2, actor_width: f32) {
+    let center_x = actor_pos.x + actor_width / 2.0;
+    let bar_y = actor_pos.y - NAMEPLATE_OFFSET;
+
+    let text_size = measure_text(name, None, NAMEPLATE_FONT_SIZE, 1.0);
+    let text_x = center_x - text_size.width / 2.0;
+    let text_y = bar_y - 4.0;
+
+    // a drop shadow keeps names readable over any background
+    draw_text(name, text_x + 1.0, text_y + 1.0, NAMEPLATE_FONT_SIZE as f32, BLACK);
+    draw_text(name, text_x, text_y, NAMEPLATE_FONT_SIZE as f32, WHITE);
+
+    let health_fraction = (health as f32 / MAX_HEALTH as f32).clamp(0.0, 1.0);
+    let bar_x = center_x - HEALTH_BAR_WIDTH / 2.0;
+    let health_color = if health_fraction > 0.3 { HEALTH_COLOR } else { LOW_HEALTH_COLOR };
+
+    draw_rectangle(bar_x - 1.0, bar_y - 1.0, HEALTH_BAR_WIDTH + 2.0, HEALTH_BAR_HEIGHT + 2.0, BLACK);
+    draw_rectangle(bar_x, bar_y, HEALTH_BAR_WIDTH * health_fraction, HEALTH_BAR_HEIGHT, health_color);
+}
@@ -0,0 +1,21 @@
+use macroquad::{
+    color::{Color, WHITE},
+    shapes::draw_rectangle,
+    text::{draw_text, measure_text},
+};
+
+const FEED_FONT_SIZE: u16 = 22;
+const FEED_LINE_HEIGHT: f32 = 28.0;
+const FEED_MARGIN: f32 = 16.0;
+const PANEL_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.5);
+
+/// Joins and leaves down the top left corner, in screen space
+pub fn draw_player_feed<'a>(lines: impl Iterator<Item = &'a String>) {
+    for (i, line) in lines.enumerate() {
+        let dims = measure_text(line, None, FEED_FONT_SIZE, 1.0);
+        let y = FEED_MARGIN + FEED_LINE_HEIGHT * i as f32;
+
+        draw_rectangle(FEED_MARGIN - 6.0, y, dims.width + 12.0, FEED_LINE_HEIGHT - 4.0, PANEL_COLOR);
+        draw_text(line, FEED_MARGIN, y + FEED_LINE_HEIGHT - 10.0, FEED_FONT_SIZE as f32, WHITE);
+    }
+}
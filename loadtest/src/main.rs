@@ -0,0 +1,120 @@
+use std::{
+    net::SocketAddr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use sim_client::SimClient;
+use stats::{RunSummary, StatsWindow};
+
+mod sim_client;
+mod stats;
+
+const DEFAULT_SERVER_ADDR: &str = "127.0.0.1:5000";
+// the server's player limit
+const DEFAULT_CLIENTS: usize = 64;
+const DEFAULT_SECONDS: u64 = 60;
+// updates a second each client sends, about what the game sends at 60fps
+const DEFAULT_SEND_RATE: u32 = 60;
+// connecting everyone at once only tests the handshake, this spreads the joins out
+const DEFAULT_RAMP_MILLISECONDS: u64 = 100;
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Connects a crowd of scripted players to a server and prints what they see once a second.
+///
+/// `--server 127.0.0.1:5000 --clients 64 --seconds 60 --rate 60 --ramp 100`
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    let server_addr: SocketAddr = match get_arg(&args, "--server").unwrap_or(DEFAULT_SERVER_ADDR).parse() {
+        Ok(server_addr) => server_addr,
+        Err(e) => {
+            eprintln!("--server has to be an address like {}: {}", DEFAULT_SERVER_ADDR, e);
+            return;
+        }
+    };
+
+    let client_count = parse_arg(&args, "--clients", DEFAULT_CLIENTS);
+    let run_time = Duration::from_secs(parse_arg(&args, "--seconds", DEFAULT_SECONDS));
+    let send_rate = parse_arg(&args, "--rate", DEFAULT_SEND_RATE).max(1);
+    let ramp = Duration::from_millis(parse_arg(&args, "--ramp", DEFAULT_RAMP_MILLISECONDS));
+    let frame = Duration::from_secs(1) / send_rate;
+
+    println!(
+        "Load testing {} with {} clients for {}s, {} updates a second each, one joining every {}ms",
+        server_addr,
+        client_count,
+        run_time.as_secs(),
+        send_rate,
+        ramp.as_millis()
+    );
+
+    let mut clients: Vec<SimClient> = vec![];
+    let mut summary = RunSummary::new();
+    let mut window = StatsWindow::new();
+
+    let started = Instant::now();
+    let mut last_updated = started;
+    let mut last_report = started;
+    let mut next_connect = started;
+    let mut next_index = 0;
+
+    while started.elapsed() < run_time {
+        let now = Instant::now();
+        let dt = now - last_updated;
+        last_updated = now;
+
+        if next_index < client_count && now >= next_connect {
+            match SimClient::new(server_addr, next_index) {
+                Ok(client) => clients.push(client),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    summary.record_failed_connect();
+                }
+            }
+
+            next_index += 1;
+            next_connect = now + ramp;
+        }
+
+        for client in clients.iter_mut() {
+            client.update(dt, &mut window);
+        }
+
+        if last_report.elapsed() >= REPORT_INTERVAL {
+            let report = window.get_report(&clients, last_report.elapsed());
+            println!("[{:>4}s] {}", started.elapsed().as_secs(), report.get_description());
+
+            summary.add_report(report);
+            window = StatsWindow::new();
+            last_report = Instant::now();
+        }
+
+        thread::sleep(frame.saturating_sub(now.elapsed()));
+    }
+
+    for client in clients.iter_mut() {
+        client.disconnect();
+    }
+
+    println!();
+    println!("{}", summary.get_description());
+}
+
+fn get_arg<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|value| value.as_str())
+}
+
+/// The number after `name`, or `default` if it's missing or isn't one
+fn parse_arg<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> T {
+    match get_arg(args, name) {
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("{} wants a number, not {}", name, value);
+            default
+        }),
+        None => default,
+    }
+}
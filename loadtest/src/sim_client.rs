@@ -0,0 +1,229 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant, SystemTime},
+};
+
+use bincode::config::Configuration;
+use common::{
+    animation::{AnimationType, CharacterType, Facing},
+    match_rules::MatchPhase,
+    spawn::{SpawnPoint, FALLBACK_SPAWN_POINT},
+    types::{player_name_to_user_data, ClientEventType, JoinType, ServerClient, ServerEventType},
+};
+use renet::{ConnectionConfig, DefaultChannel, NetworkInfo, RenetClient};
+use renet_netcode::{ClientAuthentication, NetcodeClientTransport};
+
+use crate::stats::StatsWindow;
+
+// has to match the server
+const PROTOCOL_ID: u64 = 7;
+const CHARACTERS: [CharacterType; 3] = [CharacterType::Fighter, CharacterType::Shinobi, CharacterType::Samurai];
+// the script walks back and forth this far either side of the spawn, jumping every few seconds
+const WANDER_DISTANCE: f32 = 150.0;
+const WANDER_SPEED: f32 = 1.0;
+const JUMP_EVERY_SECONDS: f32 = 3.0;
+const JUMP_SECONDS: f32 = 0.6;
+const JUMP_HEIGHT: f32 = 120.0;
+const ANIMATION_FPS: f32 = 10.0;
+const WALK_FRAMES: usize = 8;
+
+/// One scripted player, does what a real client would over the network but never draws anything
+pub struct SimClient {
+    bincode_config: Configuration,
+    client: RenetClient,
+    transport: NetcodeClientTransport,
+    client_id: u64,
+    has_connected: bool,
+    has_disconnected: bool,
+    state: ServerClient,
+    home: SpawnPoint,
+    /// Drives the scripted movement, everyone starts at a different point in it
+    script_time: f32,
+    has_requested_spawn: bool,
+    /// Newest server tick we got
+    newest_tick: u64,
+    /// The newest tick as of the last update it moved on in, and when that was
+    last_tick: Option<(u64, Instant)>,
+}
+
+impl SimClient {
+    pub fn new(server_addr: SocketAddr, index: usize) -> Result<Self, String> {
+        let current_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        // connect times in milliseconds like the game, plus the index since we connect several a millisecond
+        let client_id = current_time.as_millis() as u64 + index as u64;
+        let name = format!("Load {}", index + 1);
+
+        let client = RenetClient::new(ConnectionConfig::default());
+        let socket = UdpSocket::bind("127.0.0.1:0").map_err(|e| format!("Couldn't open a socket: {}", e))?;
+
+        let authentication = ClientAuthentication::Unsecure {
+            server_addr,
+            client_id,
+            user_data: Some(player_name_to_user_data(&name, JoinType::Player)),
+            protocol_id: PROTOCOL_ID,
+        };
+
+        let transport = NetcodeClientTransport::new(current_time, authentication, socket)
+            .map_err(|e| format!("Couldn't connect to {}: {:?}", server_addr, e))?;
+
+        // the server moves us to one of the map's spawn points once a match starts
+        let home = FALLBACK_SPAWN_POINT;
+
+        Ok(Self {
+            bincode_config: bincode::config::standard(),
+            client,
+            transport,
+            client_id,
+            has_connected: false,
+            has_disconnected: false,
+            state: ServerClient {
+                name,
+                character_type: CHARACTERS[index % CHARACTERS.len()].clone(),
+                x_pos: home.0,
+                y_pos: home.1,
+                prev_x_pos: home.0,
+                prev_y_pos: home.1,
+                ..ServerClient::default()
+            },
+            home,
+            script_time: index as f32 * 0.37,
+            has_requested_spawn: false,
+            newest_tick: 0,
+            last_tick: None,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client.is_connected()
+    }
+
+    pub fn get_network_info(&self) -> NetworkInfo {
+        self.client.network_info()
+    }
+
+    /// One network update, reads everything the server sent and sends our scripted state back
+    pub fn update(&mut self, dt: Duration, window: &mut StatsWindow) {
+        self.client.update(dt);
+        if let Err(e) = self.transport.update(dt, &mut self.client) {
+            eprintln!("{}: error updating transport: {:?}", self.state.name, e);
+        }
+
+        if self.client.is_disconnected() {
+            if !self.has_disconnected {
+                self.has_disconnected = true;
+                window.record_disconnect();
+                eprintln!("{} disconnected: {:?}", self.state.name, self.transport.disconnect_reason());
+            }
+            return;
+        }
+
+        if !self.client.is_connected() {
+            return;
+        }
+
+        if !self.has_connected {
+            self.has_connected = true;
+            // always ready, so matches start and we get spawned like real players
+            self.send(ServerEventType::LobbySetReady(true));
+        }
+
+        while let Some(message) = self.client.receive_message(DefaultChannel::ReliableOrdered) {
+            let decoded: Result<(ClientEventType, usize), _> = bincode::decode_from_slice(&message[..], self.bincode_config);
+
+            match decoded {
+                Ok((event, _len)) => self.handle_event(event, window),
+                Err(e) => eprintln!("{}: error decoding message: {:?}", self.state.name, e),
+            }
+        }
+
+        // several ticks can land in one update, the time since the last one is spread over all of them
+        let now = Instant::now();
+        match self.last_tick {
+            Some((last_tick, last_arrived)) if self.newest_tick > last_tick => {
+                window.record_snapshot_gap((now - last_arrived) / (self.newest_tick - last_tick) as u32);
+                self.last_tick = Some((self.newest_tick, now));
+            }
+            None if self.newest_tick > 0 => self.last_tick = Some((self.newest_tick, now)),
+            _ => {}
+        }
+
+        self.move_along_script(dt.as_secs_f32());
+        self.send(ServerEventType::ClientCharacterUpdate(self.state.clone()));
+
+        if let Err(e) = self.transport.send_packets(&mut self.client) {
+            eprintln!("{}: error sending packets: {:?}", self.state.name, e);
+        }
+    }
+
+    /// Lets the server know we're going instead of leaving it to time us out
+    pub fn disconnect(&mut self) {
+        self.transport.disconnect();
+    }
+
+    fn handle_event(&mut self, event: ClientEventType, window: &mut StatsWindow) {
+        match event {
+            ClientEventType::ClientCharacterUpdate { tick, tick_ms, clients } => {
+                self.newest_tick = self.newest_tick.max(tick);
+                window.record_snapshot(clients.len());
+                window.record_server_tick(tick, tick_ms);
+
+                if let Some(me) = clients.get(&self.client_id) {
+                    self.state.health = me.health;
+                }
+            }
+            ClientEventType::SpawnAssigned(spawn) => {
+                self.home = spawn;
+            }
+            ClientEventType::MatchStateUpdate(match_state) => match match_state.phase {
+                MatchPhase::Countdown { .. } if !self.has_requested_spawn => {
+                    self.has_requested_spawn = true;
+                    self.send(ServerEventType::RequestSpawn);
+                }
+                MatchPhase::Lobby if self.has_requested_spawn => {
+                    self.has_requested_spawn = false;
+                    self.send(ServerEventType::LobbySetReady(true));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Walks back and forth around the spawn point and jumps now and then
+    fn move_along_script(&mut self, dt: f32) {
+        self.script_time += dt;
+
+        let phase = self.script_time * WANDER_SPEED;
+        let jump_time = self.script_time % JUMP_EVERY_SECONDS;
+        let jump_offset = if jump_time < JUMP_SECONDS {
+            let t = jump_time / JUMP_SECONDS;
+            4.0 * JUMP_HEIGHT * t * (1.0 - t)
+        } else {
+            0.0
+        };
+
+        self.state.prev_x_pos = self.state.x_pos;
+        self.state.prev_y_pos = self.state.y_pos;
+        self.state.x_pos = self.home.0 + phase.sin() * WANDER_DISTANCE;
+        self.state.y_pos = self.home.1 - jump_offset;
+        self.state.facing = if phase.cos() >= 0.0 { Facing::Right } else { Facing::Left };
+
+        if jump_offset > 0.0 {
+            self.state.anim_type = AnimationType::Jump;
+            self.state.sprite_frame = 0;
+        } else {
+            self.state.anim_type = AnimationType::ForwardWalk;
+            self.state.sprite_frame = (self.script_time * ANIMATION_FPS) as usize % WALK_FRAMES;
+        }
+    }
+
+    fn send(&mut self, event: ServerEventType) {
+        match bincode::encode_to_vec(&event, self.bincode_config) {
+            Ok(encoded) => self.client.send_message(DefaultChannel::ReliableOrdered, encoded),
+            Err(e) => eprintln!("{}: error encoding event: {:?}", self.state.name, e),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::sim_client::SimClient;
+
+/// What the clients saw over one report interval
+pub struct StatsWindow {
+    /// What the server said each tick took to work through, every client hears about the same ticks
+    server_tick_ms: HashMap<u64, f32>,
+    snapshot_gaps: Vec<Duration>,
+    snapshots: u32,
+    /// Players in the newest snapshot, to check the server sees everyone
+    players_in_snapshot: usize,
+    disconnects: u32,
+}
+
+impl StatsWindow {
+    pub fn new() -> Self {
+        Self {
+            server_tick_ms: HashMap::new(),
+            snapshot_gaps: vec![],
+            snapshots: 0,
+            players_in_snapshot: 0,
+            disconnects: 0,
+        }
+    }
+
+    /// The server measures its own ticks, `tick_ms` is for the one before `tick`
+    pub fn record_server_tick(&mut self, tick: u64, tick_ms: f32) {
+        self.server_tick_ms.insert(tick.saturating_sub(1), tick_ms);
+    }
+
+    /// Time between two server ticks arriving as one client saw it, includes the server's sleep and the network
+    pub fn record_snapshot_gap(&mut self, gap: Duration) {
+        self.snapshot_gaps.push(gap);
+    }
+
+    pub fn record_snapshot(&mut self, player_count: usize) {
+        self.snapshots += 1;
+        self.players_in_snapshot = player_count;
+    }
+
+    pub fn record_disconnect(&mut self) {
+        self.disconnects += 1;
+    }
+
+    /// Sums the window up along with every client's view of its connection, `elapsed` is how long the window ran
+    pub fn get_report(&self, clients: &[SimClient], elapsed: Duration) -> Report {
+        let connected: Vec<&SimClient> = clients.iter().filter(|client| client.is_connected()).collect();
+        let network_infos: Vec<_> = connected.iter().map(|client| client.get_network_info()).collect();
+
+        let mut tick_times: Vec<f64> = self.server_tick_ms.values().map(|tick_ms| *tick_ms as f64).collect();
+        tick_times.sort_by(f64::total_cmp);
+
+        let snapshot_gaps: Vec<f64> = self.snapshot_gaps.iter().map(|gap| gap.as_secs_f64() * 1000.0).collect();
+
+        let rtts: Vec<f64> = network_infos.iter().map(|info| info.rtt * 1000.0).collect();
+        let losses: Vec<f64> = network_infos.iter().map(|info| info.packet_loss * 100.0).collect();
+
+        Report {
+            connected: connected.len(),
+            total: clients.len(),
+            players_in_snapshot: self.players_in_snapshot,
+            snapshots_per_second: self.snapshots as f64 / elapsed.as_secs_f64() / connected.len().max(1) as f64,
+            tick_ms: get_average(&tick_times),
+            tick_p99_ms: get_percentile(&tick_times, 0.99),
+            tick_max_ms: tick_times.last().copied().unwrap_or(0.0),
+            snapshot_gap_ms: get_average(&snapshot_gaps),
+            snapshot_gap_max_ms: snapshot_gaps.iter().copied().fold(0.0, f64::max),
+            upload_bytes_per_second: network_infos.iter().map(|info| info.bytes_sent_per_second).sum(),
+            download_bytes_per_second: network_infos.iter().map(|info| info.bytes_received_per_second).sum(),
+            rtt_ms: get_average(&rtts),
+            rtt_max_ms: rtts.iter().copied().fold(0.0, f64::max),
+            loss_percent: get_average(&losses),
+            loss_max_percent: losses.iter().copied().fold(0.0, f64::max),
+            disconnects: self.disconnects,
+        }
+    }
+}
+
+/// One line of output, bandwidth is summed over every client and the rest is averaged unless it says max
+pub struct Report {
+    pub connected: usize,
+    pub total: usize,
+    pub players_in_snapshot: usize,
+    /// Per client
+    pub snapshots_per_second: f64,
+    /// Time the server spent working on its ticks, as it measured it
+    pub tick_ms: f64,
+    pub tick_p99_ms: f64,
+    pub tick_max_ms: f64,
+    pub snapshot_gap_ms: f64,
+    pub snapshot_gap_max_ms: f64,
+    pub upload_bytes_per_second: f64,
+    pub download_bytes_per_second: f64,
+    pub rtt_ms: f64,
+    pub rtt_max_ms: f64,
+    pub loss_percent: f64,
+    pub loss_max_percent: f64,
+    pub disconnects: u32,
+}
+
+impl Report {
+    pub fn get_description(&self) -> String {
+        format!(
+            "{}/{} connected ({} in snapshot)  {:.0} snapshots/s  tick {:.1}ms avg {:.1}ms p99 {:.1}ms max  gap {:.1}ms avg {:.1}ms max  up {} down {}  rtt {:.1}ms avg {:.1}ms max  loss {:.1}% avg {:.1}% max",
+            self.connected,
+            self.total,
+            self.players_in_snapshot,
+            self.snapshots_per_second,
+            self.tick_ms,
+            self.tick_p99_ms,
+            self.tick_max_ms,
+            self.snapshot_gap_ms,
+            self.snapshot_gap_max_ms,
+            format_bandwidth(self.upload_bytes_per_second),
+            format_bandwidth(self.download_bytes_per_second),
+            self.rtt_ms,
+            self.rtt_max_ms,
+            self.loss_percent,
+            self.loss_max_percent,
+        )
+    }
+}
+
+/// Every report of the run, printed as a summary at the end
+pub struct RunSummary {
+    reports: Vec<Report>,
+    failed_connects: usize,
+}
+
+impl RunSummary {
+    pub fn new() -> Self {
+        Self {
+            reports: vec![],
+            failed_connects: 0,
+        }
+    }
+
+    pub fn add_report(&mut self, report: Report) {
+        self.reports.push(report);
+    }
+
+    pub fn record_failed_connect(&mut self) {
+        self.failed_connects += 1;
+    }
+
+    pub fn get_description(&self) -> String {
+        if self.reports.is_empty() {
+            return "Nothing to report, the run was too short".to_string();
+        }
+
+        let average = |value: fn(&Report) -> f64| get_average(&self.reports.iter().map(value).collect::<Vec<f64>>());
+        let max = |value: fn(&Report) -> f64| self.reports.iter().map(value).fold(0.0, f64::max);
+
+        let most_connected = self.reports.iter().map(|report| report.connected).max().unwrap_or(0);
+        let disconnects: u32 = self.reports.iter().map(|report| report.disconnects).sum();
+
+        [
+            format!("Most connected at once: {}, failed connects: {}, disconnects: {}", most_connected, self.failed_connects, disconnects),
+            format!("Snapshots: {:.1}/s avg per client", average(|r| r.snapshots_per_second)),
+            format!("Server tick: {:.1}ms avg, {:.1}ms worst p99, {:.1}ms max", average(|r| r.tick_ms), max(|r| r.tick_p99_ms), max(|r| r.tick_max_ms)),
+            format!("Snapshot gap: {:.1}ms avg, {:.1}ms max", average(|r| r.snapshot_gap_ms), max(|r| r.snapshot_gap_max_ms)),
+            format!("Bandwidth: up {} avg {} peak, down {} avg {} peak",
+                format_bandwidth(average(|r| r.upload_bytes_per_second)),
+                format_bandwidth(max(|r| r.upload_bytes_per_second)),
+                format_bandwidth(average(|r| r.download_bytes_per_second)),
+                format_bandwidth(max(|r| r.download_bytes_per_second)),
+            ),
+            format!("RTT: {:.1}ms avg, {:.1}ms max", average(|r| r.rtt_ms), max(|r| r.rtt_max_ms)),
+            format!("Packet loss: {:.2}% avg, {:.1}% max", average(|r| r.loss_percent), max(|r| r.loss_max_percent)),
+        ]
+        .join("\n")
+    }
+}
+
+fn get_average(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// `values` has to be sorted
+fn get_percentile(values: &[f64], percentile: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let index = ((values.len() - 1) as f64 * percentile).round() as usize;
+    values[index]
+}
+
+fn format_bandwidth(bytes_per_second: f64) -> String {
+    if bytes_per_second >= 1024.0 * 1024.0 {
+        format!("{:.2} MB/s", bytes_per_second / 1024.0 / 1024.0)
+    } else {
+        format!("{:.1} KB/s", bytes_per_second / 1024.0)
+    }
+}
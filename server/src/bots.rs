@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use common::{
+    ai::{AiController, AiDifficulty, AiTarget, AiView},
+    animation::{AnimationType, CharacterType, Facing, PlayerAnimationState},
+    constants::{GRAVITY, MAX_HEALTH, RUN_SPEED, WALK_SPEED},
+    hit::{HitProperties, Hurtbox},
+    match_rules::{MatchPhase, MatchState},
+    movesets::{get_animation, get_hit_at},
+    simulation::SIM_DT,
+    spawn::{choose_spawn_point, SpawnPoint},
+    types::ServerClient,
+};
+use macroquad::math::vec2;
+use renet::ClientId;
+
+// real client ids are connect times in milliseconds, bots count down from the top so they never meet
+const FIRST_BOT_CLIENT_ID: ClientId = u64::MAX;
+const BOT_CHARACTERS: [CharacterType; 3] = [CharacterType::Fighter, CharacterType::Shinobi, CharacterType::Samurai];
+// where bots wait while they're knocked out of the match, far off every map
+const PARKED_POSITION: (f32, f32) = (-100_000.0, -100_000.0);
+const SPAWN_INVULNERABILITY_SECONDS: f32 = 2.0;
+
+/// A hit one of the bots landed this tick, the server applies it like one reported by a client
+pub struct BotHit {
+    pub attacker: ClientId,
+    pub victim: ClientId,
+    pub hit: HitProperties,
+}
+
+/// One server side player.
+///
+/// The server doesn't load maps, so a bot treats the height it spawned at as the floor and only walks
+/// between the outermost spawn points. Its animations, jumps and hits come out of the same movesets clients play
+struct Bot {
+    controller: AiController,
+    character_type: CharacterType,
+    animation: PlayerAnimationState,
+    velocity: (f32, f32),
+    floor_y: f32,
+    is_parked: bool,
+    stun_time_left: f32,
+    invulnerable_time_left: f32,
+    /// Server time we haven't simulated yet, bots step in whole simulation ticks like clients do
+    unsimulated_time: f32,
+    /// Who the current swing already landed on, it only hits each of them once
+    hit_victims: Vec<ClientId>,
+}
+
+impl Bot {
+    fn new(difficulty: AiDifficulty, character_type: CharacterType) -> Self {
+        Self {
+            controller: AiController::new(difficulty),
+            animation: get_animation(&character_type, &AnimationType::Idle),
+            character_type,
+            velocity: (0.0, 0.0),
+            floor_y: 0.0,
+            is_parked: true,
+            stun_time_left: 0.0,
+            invulnerable_time_left: 0.0,
+            unsimulated_time: 0.0,
+            hit_victims: vec![],
+        }
+    }
+
+    /// Switches to `anim_type` from its start, unless it's already playing
+    fn play(&mut self, anim_type: AnimationType) {
+        if self.animation.anim_type == anim_type {
+            return;
+        }
+
+        self.animation = get_animation(&self.character_type, &anim_type);
+        self.animation.actively_playing = !self.animation.always_plays;
+    }
+
+    /// Attacks, jumps and landings play out before anything else can start
+    fn is_busy(&self) -> bool {
+        self.animation.actively_playing && !self.animation.always_plays
+    }
+}
+
+/// Keeps the server topped up to `count` players with bots, they make way when humans join
+pub struct Bots {
+    bots: HashMap<ClientId, Bot>,
+    count: usize,
+    difficulty: AiDifficulty,
+    next_client_id: ClientId,
+    /// The current map's spawn points, given to us when a match's countdown starts
+    spawn_points: Vec<SpawnPoint>,
+}
+
+impl Bots {
+    pub fn new(count: usize, difficulty: AiDifficulty) -> Self {
+        Self {
+            bots: HashMap::new(),
+            count,
+            difficulty,
+            next_client_id: FIRST_BOT_CLIENT_ID,
+            spawn_points: vec![],
+        }
+    }
+
+    pub fn is_bot(&self, client_id: ClientId) -> bool {
+        self.bots.contains_key(&client_id)
+    }
+
+    pub fn get_client_ids(&self) -> Vec<ClientId> {
+        self.bots.keys().copied().collect()
+    }
+
+    /// How many bots have to come or go so humans and bots add up to `count` again, positive means add
+    pub fn get_wanted_change(&self, human_count: usize) -> isize {
+        let wanted = self.count.saturating_sub(human_count);
+        wanted as isize - self.bots.len() as isize
+    }
+
+    /// Makes a new bot and returns its id, name and character, the caller adds it to the match and lobby
+    pub fn add(&mut self) -> (ClientId, ServerClient) {
+        let client_id = self.next_client_id;
+        self.next_client_id -= 1;
+
+        let number = FIRST_BOT_CLIENT_ID - client_id + 1;
+        let character_type = BOT_CHARACTERS[number as usize % BOT_CHARACTERS.len()].clone();
+        let state = ServerClient {
+            name: format!("Bot {}", number),
+            character_type: character_type.clone(),
+            x_pos: PARKED_POSITION.0,
+            y_pos: PARKED_POSITION.1,
+            prev_x_pos: PARKED_POSITION.0,
+            prev_y_pos: PARKED_POSITION.1,
+            ..ServerClient::default()
+        };
+
+        self.bots.insert(client_id, Bot::new(self.difficulty, character_type));
+        (client_id, state)
+    }
+
+    /// Takes the newest bot out, the caller removes it from everywhere else
+    pub fn remove(&mut self) -> Option<ClientId> {
+        let client_id = self.bots.keys().copied().min()?;
+        self.bots.remove(&client_id);
+        Some(client_id)
+    }
+
+    /// A new match could be on a different map, and everyone starts over from one of its `spawn_points`
+    pub fn reset(&mut self, client_states: &mut HashMap<ClientId, ServerClient>, spawn_points: &[SpawnPoint]) {
+        self.spawn_points = spawn_points.to_vec();
+
+        for (client_id, bot) in self.bots.iter_mut() {
+            bot.is_parked = true;
+            bot.play(AnimationType::Idle);
+
+            if let Some(sc) = client_states.get_mut(client_id) {
+                sc.x_pos = PARKED_POSITION.0;
+                sc.y_pos = PARKED_POSITION.1;
+                sc.health = MAX_HEALTH;
+            }
+        }
+    }
+
+    /// Someone landed a hit on a bot
+    pub fn on_hit(&mut self, client_id: ClientId, hit: &HitProperties, attacker_facing: &Facing) {
+        let Some(bot) = self.bots.get_mut(&client_id) else {
+            return;
+        };
+
+        // y is up in the knockback, down on screen
+        let knockback = hit.get_knockback(attacker_facing);
+        bot.velocity = (knockback.x, -knockback.y);
+        bot.stun_time_left = hit.hit_stop + hit.hit_stun;
+
+        // getting hit knocks us out of whatever we were doing
+        if bot.is_busy() {
+            bot.play(AnimationType::Idle);
+        }
+    }
+
+    /// Moves every bot through the simulation ticks `dt` covers, returns the hits they landed
+    pub fn update(&mut self, dt: f32, match_state: &MatchState, client_states: &mut HashMap<ClientId, ServerClient>) -> Vec<BotHit> {
+        let mut hits = vec![];
+
+        // clients only have the map loaded from the countdown on
+        if !match_state.is_running() && !matches!(match_state.phase, MatchPhase::Countdown { .. }) {
+            return hits;
+        }
+
+        // everyone as of the start of the tick, so bots moving earlier in the loop don't change what later ones see
+        let everyone: Vec<(ClientId, ServerClient)> = client_states.iter().map(|(id, sc)| (*id, sc.clone())).collect();
+
+        for (client_id, bot) in self.bots.iter_mut() {
+            let client_id = *client_id;
+            let others: Vec<&(ClientId, ServerClient)> = everyone.iter().filter(|(id, _)| *id != client_id).collect();
+
+            let Some(sc) = client_states.get_mut(&client_id) else {
+                continue;
+            };
+
+            sc.prev_x_pos = sc.x_pos;
+            sc.prev_y_pos = sc.y_pos;
+
+            bot.unsimulated_time += dt;
+            while bot.unsimulated_time >= SIM_DT {
+                bot.unsimulated_time -= SIM_DT;
+                step_bot(client_id, bot, sc, &others, &self.spawn_points, match_state, &mut hits);
+            }
+        }
+
+        hits
+    }
+}
+
+/// One simulation tick of a bot, the same length a client's fighter steps by
+fn step_bot(
+    client_id: ClientId,
+    bot: &mut Bot,
+    sc: &mut ServerClient,
+    others: &[&(ClientId, ServerClient)],
+    spawn_points: &[SpawnPoint],
+    match_state: &MatchState,
+    hits: &mut Vec<BotHit>,
+) {
+    // knocked out bots come straight back if the rules let them, otherwise they sit the match out
+    if bot.is_parked || sc.health <= 0 {
+        let occupied: Vec<SpawnPoint> = others.iter().map(|(_, other)| (other.x_pos, other.y_pos)).collect();
+        let spawn = choose_spawn_point(spawn_points, &occupied);
+
+        match spawn.filter(|_| match_state.can_spawn(client_id)) {
+            Some((x, y)) => {
+                sc.x_pos = x;
+                sc.y_pos = y;
+                sc.prev_x_pos = x;
+                sc.prev_y_pos = y;
+                sc.health = MAX_HEALTH;
+                bot.floor_y = y;
+                bot.velocity = (0.0, 0.0);
+                bot.is_parked = false;
+                bot.invulnerable_time_left = SPAWN_INVULNERABILITY_SECONDS;
+                bot.play(AnimationType::Idle);
+            }
+            None => {
+                sc.x_pos = PARKED_POSITION.0;
+                sc.y_pos = PARKED_POSITION.1;
+                bot.is_parked = true;
+            }
+        }
+        return;
+    }
+
+    let (min_x, max_x) = spawn_points
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min_x, max_x), (x, _)| (min_x.min(*x), max_x.max(*x)));
+
+    bot.invulnerable_time_left = (bot.invulnerable_time_left - SIM_DT).max(0.0);
+
+    let is_grounded = sc.y_pos >= bot.floor_y;
+    let size = vec2(sc.width as f32, sc.height as f32);
+
+    let view = AiView {
+        position: vec2(sc.x_pos, sc.y_pos),
+        size,
+        is_grounded,
+        ground_left: sc.x_pos - size.x >= min_x,
+        ground_right: sc.x_pos + size.x <= max_x,
+        targets: others
+            .iter()
+            .filter(|(id, other)| other.health > 0 && match_state.can_play(*id))
+            .map(|(_, other)| AiTarget {
+                position: vec2(other.x_pos, other.y_pos),
+                size: vec2(other.width as f32, other.height as f32),
+                is_attacking: is_attack(&other.anim_type),
+            })
+            .collect(),
+    };
+
+    let input = bot.controller.update(&view);
+
+    if bot.stun_time_left > 0.0 {
+        bot.stun_time_left -= SIM_DT;
+    } else {
+        // friction, like a fighter standing on the ground
+        if is_grounded {
+            bot.velocity.0 = 0.0;
+        }
+
+        let is_attacking = bot.is_busy() && is_attack(&bot.animation.anim_type);
+
+        if !is_attacking && match_state.can_play(client_id) {
+            let speed = if input.run { RUN_SPEED } else { WALK_SPEED };
+
+            match (input.left, input.right) {
+                (true, false) => bot.velocity.0 = -speed,
+                (false, true) => bot.velocity.0 = speed,
+                _ => {}
+            }
+
+            if input.left != input.right {
+                sc.facing = if input.right { Facing::Right } else { Facing::Left };
+            }
+
+            // attacks and jumps are picked like the transition table picks them for players on the ground
+            let started = if !is_grounded || bot.is_busy() {
+                None
+            } else if input.kick {
+                Some(AnimationType::Attack3)
+            } else if input.attack_2 {
+                Some(AnimationType::Attack2)
+            } else if input.attack_1 {
+                Some(AnimationType::Attack1)
+            } else if input.jump && bot.velocity.0 != 0.0 {
+                Some(AnimationType::JumpMoving)
+            } else if input.jump {
+                Some(AnimationType::Jump)
+            } else {
+                None
+            };
+
+            if let Some(anim_type) = started {
+                if is_attack(&anim_type) {
+                    bot.velocity.0 = 0.0;
+                }
+                bot.play(anim_type);
+            }
+        }
+    }
+
+    // the animation moves and launches us the same way it does a client's fighter, jumps get their speed from it
+    let deltas = bot.animation.update();
+    let direction = if sc.facing == Facing::Left { -1.0 } else { 1.0 };
+    sc.x_pos += deltas.pos_delta.0 * direction;
+    sc.y_pos -= deltas.pos_delta.1;
+    bot.velocity.0 += deltas.vel_delta.0 * direction;
+    bot.velocity.1 -= deltas.vel_delta.1;
+
+    if deltas.width > 0 && deltas.height > 0 {
+        sc.width = deltas.width;
+        sc.height = deltas.height;
+    }
+
+    let was_airborne = sc.y_pos < bot.floor_y;
+
+    bot.velocity.1 += GRAVITY * SIM_DT;
+    sc.x_pos = (sc.x_pos + bot.velocity.0 * SIM_DT).clamp(min_x, max_x);
+    sc.y_pos += bot.velocity.1 * SIM_DT;
+
+    if sc.y_pos >= bot.floor_y {
+        sc.y_pos = bot.floor_y;
+        bot.velocity.1 = 0.0;
+
+        if was_airborne {
+            bot.play(AnimationType::Landing);
+        }
+    }
+
+    // finished jumps hold their last frame until we land
+    if !bot.is_busy() && sc.y_pos >= bot.floor_y {
+        let anim_type = if bot.velocity.0.abs() > WALK_SPEED {
+            AnimationType::ForwardRun
+        } else if bot.velocity.0 != 0.0 {
+            AnimationType::ForwardWalk
+        } else {
+            AnimationType::Idle
+        };
+
+        bot.play(anim_type);
+    }
+
+    sc.anim_type = bot.animation.anim_type.clone();
+    sc.sprite_frame = bot.animation.sprite_frame;
+    sc.is_invulnerable = bot.invulnerable_time_left > 0.0 || bot.animation.is_invulnerable();
+
+    // the server reads a bot's hits off its frames the same way it does a player's
+    match get_hit_at(&sc.character_type, &sc.anim_type, sc.sprite_frame).filter(|_| bot.is_busy()) {
+        Some(hit) => {
+            for (victim, other) in others.iter() {
+                if !bot.hit_victims.contains(victim) && hit.connects(sc, &Hurtbox::from_client(other)) {
+                    bot.hit_victims.push(*victim);
+                    hits.push(BotHit { attacker: client_id, victim: *victim, hit: hit.clone() });
+                }
+            }
+        }
+        None => bot.hit_victims.clear(),
+    }
+}
+
+fn is_attack(anim_type: &AnimationType) -> bool {
+    matches!(anim_type, AnimationType::Attack1 | AnimationType::Attack2 | AnimationType::Attack3 | AnimationType::SoaringKick)
+}
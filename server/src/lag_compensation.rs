@@ -0,0 +1,113 @@
+use std::collections::{HashMap, VecDeque};
+
+use common::{hit::Hurtbox, types::ServerClient};
+use renet::ClientId;
+
+// how far back a hit can be checked, about a quarter of a second at our tick rate.
+// anyone lagging further behind than this has to lead their targets
+pub const MAX_REWIND_TICKS: u64 = 15;
+
+/// Everyone's hurtboxes for the last few ticks, so hits can be checked against what the attacker saw
+pub struct HurtboxHistory {
+    /// Oldest first
+    snapshots: VecDeque<(u64, HashMap<ClientId, Hurtbox>)>,
+}
+
+impl HurtboxHistory {
+    pub fn new() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    /// Stores where everyone is at `tick` and forgets anything too old to rewind to
+    pub fn record(&mut self, tick: u64, client_states: &HashMap<ClientId, ServerClient>) {
+        let hurtboxes = client_states
+            .iter()
+            .map(|(client_id, sc)| (*client_id, Hurtbox::from_client(sc)))
+            .collect();
+
+        self.snapshots.push_back((tick, hurtboxes));
+
+        while self.snapshots.front().is_some_and(|(oldest, _)| tick - oldest > MAX_REWIND_TICKS) {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Where `client_id` was at `view_tick`, clamped to the rewind window ending at `current_tick`
+    pub fn get_hurtbox(&self, client_id: ClientId, view_tick: u64, current_tick: u64) -> Option<&Hurtbox> {
+        let view_tick = view_tick.clamp(current_tick.saturating_sub(MAX_REWIND_TICKS), current_tick);
+
+        // the newest snapshot that isn't ahead of what the attacker saw
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|(tick, hurtboxes)| *tick <= view_tick && hurtboxes.contains_key(&client_id))
+            .or_else(|| self.snapshots.iter().find(|(_, hurtboxes)| hurtboxes.contains_key(&client_id)))
+            .and_then(|(_, hurtboxes)| hurtboxes.get(&client_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // everyone's x is the tick they were recorded at, so a hurtbox says which snapshot it came from
+    fn record_ticks(history: &mut HurtboxHistory, client_ids: &[ClientId], ticks: std::ops::RangeInclusive<u64>) {
+        for tick in ticks {
+            let client_states = client_ids
+                .iter()
+                .map(|client_id| (*client_id, ServerClient { x_pos: tick as f32, ..ServerClient::default() }))
+                .collect();
+
+            history.record(tick, &client_states);
+        }
+    }
+
+    #[test]
+    fn forgets_snapshots_older_than_the_rewind_window() {
+        let mut history = HurtboxHistory::new();
+        record_ticks(&mut history, &[1], 1..=40);
+
+        assert_eq!(history.snapshots.len() as u64, MAX_REWIND_TICKS + 1);
+        assert_eq!(history.snapshots.front().map(|(tick, _)| *tick), Some(40 - MAX_REWIND_TICKS));
+    }
+
+    #[test]
+    fn rewinds_to_the_view_tick() {
+        let mut history = HurtboxHistory::new();
+        record_ticks(&mut history, &[1], 1..=40);
+
+        assert_eq!(history.get_hurtbox(1, 33, 40).map(|hurtbox| hurtbox.x), Some(33.0));
+    }
+
+    #[test]
+    fn clamps_the_view_tick_to_the_rewind_window() {
+        let mut history = HurtboxHistory::new();
+        record_ticks(&mut history, &[1], 1..=40);
+
+        // too far back only rewinds as far as the window goes
+        assert_eq!(history.get_hurtbox(1, 2, 40).map(|hurtbox| hurtbox.x), Some((40 - MAX_REWIND_TICKS) as f32));
+        // ticks that haven't happened yet are the present
+        assert_eq!(history.get_hurtbox(1, 90, 40).map(|hurtbox| hurtbox.x), Some(40.0));
+    }
+
+    #[test]
+    fn falls_back_to_the_oldest_snapshot_with_the_victim() {
+        let mut history = HurtboxHistory::new();
+        record_ticks(&mut history, &[1], 1..=30);
+        record_ticks(&mut history, &[1, 2], 31..=40);
+
+        // 2 only showed up at tick 31, so that's as far back as they go
+        assert_eq!(history.get_hurtbox(2, 28, 40).map(|hurtbox| hurtbox.x), Some(31.0));
+    }
+
+    #[test]
+    fn unknown_victims_have_no_hurtbox() {
+        let mut history = HurtboxHistory::new();
+        record_ticks(&mut history, &[1], 1..=40);
+
+        assert_eq!(history.get_hurtbox(2, 35, 40), None);
+        assert_eq!(HurtboxHistory::new().get_hurtbox(1, 35, 40), None);
+    }
+}
@@ -0,0 +1,87 @@
+use common::{
+    animation::CharacterType,
+    lobby::{LobbyPlayer, LobbyState},
+};
+
+/// Who is connected, what they picked and whether they're ready for the next match
+pub struct Lobby {
+    state: LobbyState,
+    /// Humans in the order they joined, the first one is the host
+    humans: Vec<u64>,
+}
+
+impl Lobby {
+    pub fn new(map_rotation: Vec<String>) -> Self {
+        Self {
+            state: LobbyState::new(map_rotation),
+            humans: vec![],
+        }
+    }
+
+    pub fn get_state(&self) -> &LobbyState {
+        &self.state
+    }
+
+    /// Bots never become the host
+    pub fn add_player(&mut self, client_id: u64, name: String, is_bot: bool) {
+        self.state.players.insert(client_id, LobbyPlayer::new(name));
+
+        if !is_bot {
+            self.humans.push(client_id);
+            self.state.host = self.humans.first().copied();
+        }
+    }
+
+    /// The next human in line takes over if the host leaves
+    pub fn remove_player(&mut self, client_id: u64) {
+        self.state.players.remove(&client_id);
+        self.humans.retain(|human| *human != client_id);
+        self.state.host = self.humans.first().copied();
+    }
+
+    pub fn is_host(&self, client_id: u64) -> bool {
+        self.state.host == Some(client_id)
+    }
+
+    pub fn select_character(&mut self, client_id: u64, character_type: CharacterType) {
+        if let Some(player) = self.state.players.get_mut(&client_id) {
+            player.character_type = character_type;
+        }
+    }
+
+    pub fn vote_map(&mut self, client_id: u64, map_index: usize) {
+        if map_index >= self.state.map_rotation.len() {
+            return;
+        }
+
+        if let Some(player) = self.state.players.get_mut(&client_id) {
+            player.map_vote = Some(map_index);
+        }
+    }
+
+    pub fn set_ready(&mut self, client_id: u64, is_ready: bool) {
+        if let Some(player) = self.state.players.get_mut(&client_id) {
+            player.is_ready = is_ready;
+        }
+    }
+
+    pub fn is_everyone_ready(&self) -> bool {
+        self.state.is_everyone_ready()
+    }
+
+    /// Locks in the most voted map for the match that's about to start
+    pub fn start_match(&mut self) -> Option<String> {
+        self.state.current_map = self.state.get_winning_map();
+        self.state.current_map.clone()
+    }
+
+    /// Everyone has to ready up and vote again for the next match
+    pub fn return_from_match(&mut self) {
+        self.state.current_map = None;
+
+        for player in self.state.players.values_mut() {
+            player.is_ready = false;
+            player.map_vote = None;
+        }
+    }
+}
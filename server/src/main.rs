@@ -1,13 +1,38 @@
 use std::{
-    collections::{HashMap, HashSet}, net::{SocketAddr, UdpSocket}, thread, time::{Duration, Instant, SystemTime}
+    collections::{HashMap, HashSet}, net::{SocketAddr, UdpSocket}, path::{Path, PathBuf}, thread, time::{Duration, Instant, SystemTime}
 };
 
-use common::types::{ClientEventType, ServerClient};
+use bincode::config::Configuration;
+use bots::Bots;
+use common::{
+    ai::AiDifficulty,
+    constants::MAX_HEALTH,
+    effects::DEATH_EFFECT,
+    hit::HitProperties,
+    link_conditioner::{spawn_conditioned_proxy, LinkConditionerConfig},
+    lobby::LobbyState,
+    match_rules::{MatchMode, MatchPhase, MatchResults, MatchState},
+    movesets::get_hit_at,
+    replay::{ReplayRecorder, REPLAY_EXTENSION},
+    maps::{get_map_file, MAP_FILES},
+    spawn::{choose_spawn_point, parse_spawn_points, SpawnPoint, FALLBACK_SPAWN_POINT},
+    types::{join_type_from_user_data, player_name_from_user_data, ClientEventType, JoinType, ServerClient, ServerEventType},
+};
+use lag_compensation::HurtboxHistory;
+use lobby::Lobby;
+use match_controller::{MatchController, MatchEvent, MIN_PLAYERS};
+use reconnect::HeldSlots;
 use renet::{ClientId, ConnectionConfig, DefaultChannel, RenetServer, ServerEvent};
 use renet_netcode::{
     NetcodeServerTransport, ServerAuthentication, ServerConfig,
 };
 
+mod bots;
+mod lag_compensation;
+mod lobby;
+mod match_controller;
+mod reconnect;
+
 
 //
 // full exmaple here
@@ -15,13 +40,165 @@ use renet_netcode::{
 //
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let match_mode = parse_match_mode(&args);
+    let map_rotation = parse_map_rotation(&args);
+    let link_conditioner = LinkConditionerConfig::from_args(&args);
+    let replay_dir = parse_replay_dir(&args);
+    let bots = parse_bots(&args);
+    let map_spawn_points = load_map_spawn_points(&parse_game_dir(&args), &map_rotation);
+
     let server_addr: SocketAddr = format!("0.0.0.0:{}", 5000).parse().unwrap();
-    server(server_addr);
+    server(server_addr, match_mode, map_rotation, map_spawn_points, link_conditioner, replay_dir, bots);
+}
+
+/// `--maps "First Map,Green Zone (Day)"` picks which maps players can vote on, every map is in by default
+fn parse_map_rotation(args: &[String]) -> Vec<String> {
+    let maps = args
+        .iter()
+        .position(|arg| arg == "--maps")
+        .and_then(|i| args.get(i + 1));
+
+    match maps {
+        Some(maps) => maps.split(',').map(|map| map.trim().to_owned()).filter(|map| !map.is_empty()).collect(),
+        None => MAP_FILES.iter().map(|(map, _)| map.to_string()).collect(),
+    }
+}
+
+/// `--game-dir ..` is the folder with the game's `assets` in it, the maps' spawn points are read from there
+fn parse_game_dir(args: &[String]) -> PathBuf {
+    args.iter()
+        .position(|arg| arg == "--game-dir")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(".."))
+}
+
+/// The spawn points of every map in the rotation, a map that can't be read gets the fallback point
+fn load_map_spawn_points(game_dir: &Path, map_rotation: &[String]) -> HashMap<String, Vec<SpawnPoint>> {
+    map_rotation
+        .iter()
+        .map(|map| {
+            let spawn_points = match get_map_file(map) {
+                Some(file) => {
+                    let path = game_dir.join(file);
+                    let spawn_points = std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|json| parse_spawn_points(&json));
+
+                    match spawn_points {
+                        Ok(spawn_points) => spawn_points,
+                        Err(e) => {
+                            println!("Couldn't read the spawn points in {}: {}", path.display(), e);
+                            vec![]
+                        }
+                    }
+                }
+                None => {
+                    println!("There's no map called {}", map);
+                    vec![]
+                }
+            };
+
+            if spawn_points.is_empty() {
+                return (map.clone(), vec![FALLBACK_SPAWN_POINT]);
+            }
+
+            (map.clone(), spawn_points)
+        })
+        .collect()
+}
+
+/// `--record replays` saves every match into that folder
+fn parse_replay_dir(args: &[String]) -> Option<PathBuf> {
+    args.iter()
+        .position(|arg| arg == "--record")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+}
+
+/// `--bots 4` keeps the server topped up to 4 players with bots, `--bot-difficulty easy|normal|hard` picks how well they play
+fn parse_bots(args: &[String]) -> Bots {
+    let count = args
+        .iter()
+        .position(|arg| arg == "--bots")
+        .and_then(|i| args.get(i + 1))
+        .map(|count| count.parse::<usize>().unwrap_or_else(|_| {
+            println!("--bots wants a number of players, not {}", count);
+            0
+        }))
+        .unwrap_or(0)
+        .min(MAX_PLAYERS);
+
+    let difficulty = args
+        .iter()
+        .position(|arg| arg == "--bot-difficulty")
+        .and_then(|i| args.get(i + 1));
+
+    let difficulty = match difficulty {
+        Some(difficulty) => AiDifficulty::ALL
+            .into_iter()
+            .find(|known| known.get_description().eq_ignore_ascii_case(difficulty))
+            .unwrap_or_else(|| {
+                println!("Unknown bot difficulty {}, using normal", difficulty);
+                AiDifficulty::Normal
+            }),
+        None => AiDifficulty::Normal,
+    };
+
+    Bots::new(count, difficulty)
+}
+
+/// `--mode stock|timed|kos` picks the rules the server starts with
+fn parse_match_mode(args: &[String]) -> MatchMode {
+    let mode = args
+        .iter()
+        .position(|arg| arg == "--mode")
+        .and_then(|i| args.get(i + 1));
+
+    match mode.map(|mode| mode.as_str()) {
+        Some("timed") => MatchMode::Timed { seconds: 120 },
+        Some("kos") => MatchMode::FirstToKos { kos: 5 },
+        Some("stock") | None => MatchMode::default(),
+        Some(other) => {
+            println!("Unknown match mode {}, using stock", other);
+            MatchMode::default()
+        }
+    }
 }
 
 const PROTOCOL_ID: u64 = 7;
+// spectators have their own slots so they never keep a player out
+const MAX_PLAYERS: usize = 64;
+const MAX_SPECTATORS: usize = 16;
+// one server tick, replays play back at this rate too
+const TICK_DURATION: Duration = Duration::from_millis(16);
+
+/// With `--latency`, `--jitter`, `--loss`, `--duplicate` or `--reorder` set, the real socket moves to a
+/// loopback port and a conditioned proxy takes its place on `public_addr`, so every client gets the bad link
+fn bind_server_socket(public_addr: SocketAddr, link_conditioner: LinkConditionerConfig) -> UdpSocket {
+    if !link_conditioner.is_enabled() {
+        return UdpSocket::bind(public_addr).unwrap();
+    }
 
-fn server(public_addr: SocketAddr) {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let socket_addr = socket.local_addr().unwrap();
+
+    println!("Conditioning the link to every client: {}", link_conditioner.get_description());
+    spawn_conditioned_proxy(public_addr, socket_addr, link_conditioner).unwrap();
+
+    socket
+}
+
+fn server(
+    public_addr: SocketAddr,
+    match_mode: MatchMode,
+    map_rotation: Vec<String>,
+    map_spawn_points: HashMap<String, Vec<SpawnPoint>>,
+    link_conditioner: LinkConditionerConfig,
+    replay_dir: Option<PathBuf>,
+    mut bots: Bots,
+) {
     let config = bincode::config::standard();
 
     let connection_config = ConnectionConfig::default();
@@ -30,21 +207,43 @@ fn server(public_addr: SocketAddr) {
     let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
     let server_config = ServerConfig {
         current_time,
-        max_clients: 64,
+        max_clients: MAX_PLAYERS + MAX_SPECTATORS,
         protocol_id: PROTOCOL_ID,
         public_addresses: vec![public_addr],
         authentication: ServerAuthentication::Unsecure,
     };
-    let socket: UdpSocket = UdpSocket::bind(public_addr).unwrap();
+    let socket: UdpSocket = bind_server_socket(public_addr, link_conditioner);
 
     let mut transport = NetcodeServerTransport::new(server_config, socket).unwrap();
     let mut client_states: HashMap<ClientId, ServerClient> = HashMap::new();
     let mut last_updated = Instant::now();
+    let mut match_controller = MatchController::new(match_mode);
+    let mut lobby = Lobby::new(map_rotation);
+    // the spawn points of the map being played, picked when the countdown starts
+    let mut spawn_points: Vec<SpawnPoint> = vec![];
+    // who last hit each player, they get the KO if that player falls off
+    let mut last_attackers: HashMap<ClientId, ClientId> = HashMap::new();
+    // counts loop iterations, clients tell us which tick they were looking at when they attack
+    let mut tick: u64 = 0;
+    let mut hurtbox_history = HurtboxHistory::new();
+    // who each attacker already hit with the attack they're in, one attack only lands once on each victim
+    let mut attack_victims: HashMap<ClientId, HashSet<ClientId>> = HashMap::new();
+    // players who dropped mid match, they get their slot back if they reconnect in time
+    let mut held_slots = HeldSlots::new();
+    let mut spectators: HashSet<ClientId> = HashSet::new();
+    // only set while a match is running and `--record` was given
+    let mut replay_recorder: Option<ReplayRecorder> = None;
+    // what clients were last sent, the lobby and match state only go out again when they change
+    let mut sent_lobby_state: Option<LobbyState> = None;
+    let mut sent_match_state: Option<MatchState> = None;
+    // how long the last tick took without the sleep, clients see it in every update
+    let mut tick_time = Duration::ZERO;
 
     loop {
         let now = Instant::now();
         let duration = now - last_updated;
         last_updated = now;
+        tick += 1;
 
         server.update(duration);
         match transport.update(duration, &mut server) {
@@ -55,23 +254,108 @@ fn server(public_addr: SocketAddr) {
             }
         };
 
+        let mut match_events: Vec<MatchEvent> = vec![];
+        // they missed the lobby and match state everyone else already has
+        let mut newly_joined: Vec<ClientId> = vec![];
+
         while let Some(event) = server.get_event() {
             match event {
                 ServerEvent::ClientConnected { client_id } => {
-                    println!("Client {} connected", client_id);
-                    client_states.insert(client_id, ServerClient::default());
+                    // still in the match and the lobby, they just need their character back
+                    if let Some(state) = held_slots.take(client_id) {
+                        println!("Client {} reconnected as {}", client_id, state.name);
+                        broadcast(&mut server, &ClientEventType::PlayerJoined { client_id, name: state.name.clone() }, config);
+                        client_states.insert(client_id, state);
+                        newly_joined.push(client_id);
+                        continue;
+                    }
+
+                    let (name, join_type) = match transport.user_data(client_id) {
+                        Some(user_data) => (player_name_from_user_data(&user_data), join_type_from_user_data(&user_data)),
+                        None => (String::new(), JoinType::Player),
+                    };
+
+                    // everyone gets a name even if they didn't type one in
+                    let name = if name.is_empty() { format!("Player {}", client_id % 10000) } else { name };
+
+                    // spectators get every broadcast but never show up in the match, the lobby or client_states
+                    if join_type == JoinType::Spectator {
+                        if spectators.len() >= MAX_SPECTATORS {
+                            println!("Client {} can't spectate as {}, there are too many spectators", client_id, name);
+                            server.disconnect(client_id);
+                            continue;
+                        }
+
+                        println!("Client {} is spectating as {}", client_id, name);
+                        spectators.insert(client_id);
+                        newly_joined.push(client_id);
+                        continue;
+                    }
+
+                    // bots don't count, one of them makes way below
+                    if match_controller.get_player_count() - bots.get_client_ids().len() >= MAX_PLAYERS {
+                        println!("Client {} can't join as {}, the server is full", client_id, name);
+                        server.disconnect(client_id);
+                        continue;
+                    }
+
+                    println!("Client {} connected as {}", client_id, name);
+                    broadcast(&mut server, &ClientEventType::PlayerJoined { client_id, name: name.clone() }, config);
+                    match_controller.add_player(client_id, name.clone());
+                    lobby.add_player(client_id, name.clone(), false);
+                    client_states.insert(client_id, ServerClient { name, ..ServerClient::default() });
+                    newly_joined.push(client_id);
                 }
                 ServerEvent::ClientDisconnected { client_id, reason } => {
-                    client_states.remove(&client_id);
+                    if spectators.remove(&client_id) {
+                        println!("Spectator {} disconnected: {}", client_id, reason);
+                        continue;
+                    }
+
+                    let state = client_states.remove(&client_id);
+
+                    // everyone else drops their character right away, even if the slot is held
+                    if let Some(state) = state.as_ref() {
+                        broadcast(&mut server, &ClientEventType::PlayerLeft { client_id, name: state.name.clone() }, config);
+                    }
+
+                    if let Some(state) = state.filter(|_| match_controller.get_state().phase != MatchPhase::Lobby) {
+                        println!("Client {} dropped out of the match: {}, holding their slot", client_id, reason);
+                        held_slots.hold(client_id, state);
+                        continue;
+                    }
+
+                    last_attackers.remove(&client_id);
+                    match_events.extend(match_controller.remove_player(client_id));
+                    lobby.remove_player(client_id);
                     println!("Client {} disconnected: {}", client_id, reason);
                 }
             }
         }
 
+        for client_id in held_slots.expire() {
+            println!("Client {} didn't come back", client_id);
+            last_attackers.remove(&client_id);
+            match_events.extend(match_controller.remove_player(client_id));
+            lobby.remove_player(client_id);
+        }
+
+        // humans push bots out and bots fill in behind them, the empty server starts out full of bots
+        match_events.extend(balance_bots(&mut bots, &mut server, &mut match_controller, &mut lobby, &mut client_states, &mut last_attackers, config));
+
+        // hits that passed every check this tick, from clients and from bots
+        let mut landed_hits: Vec<(ClientId, ClientId, HitProperties)> = vec![];
+
         // this is where we get client updates
         for client_id in server.clients_id() {
+            // spectators can't do anything, whatever they send is dropped
+            if spectators.contains(&client_id) {
+                while server.receive_message(client_id, DefaultChannel::ReliableOrdered).is_some() {}
+                continue;
+            }
+
             while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered) {
-                let (decoded, _len): (ServerClient, usize) = match bincode::decode_from_slice(&message[..], config) {
+                let (decoded, _len): (ServerEventType, usize) = match bincode::decode_from_slice(&message[..], config) {
                     Ok(decoded) => decoded,
                     Err(e) => {
                         println!("Error decoding message: {:?}", e);
@@ -79,15 +363,247 @@ fn server(public_addr: SocketAddr) {
                     }
                 };
 
-                // insert or update client states
-                client_states.entry(client_id)
-                    .and_modify(|v| *v = decoded.clone())
-                    .or_insert(decoded.clone());
+                match decoded {
+                    ServerEventType::ClientCharacterUpdate(decoded) => {
+                        // insert or update client states
+                        // the name came from the handshake and health is ours to track, clients don't get to change them
+                        client_states.entry(client_id)
+                            .and_modify(|v| {
+                                let name = std::mem::take(&mut v.name);
+                                let health = v.health;
+                                *v = decoded.clone();
+                                v.name = name;
+                                v.health = health;
+                            })
+                            .or_insert(decoded.clone());
+                    }
+                    ServerEventType::RequestSpawn => {
+                        // knocked out players stay dead until the match is over
+                        if !match_controller.can_spawn(client_id) {
+                            continue;
+                        }
+
+                        let occupied: Vec<(f32, f32)> = client_states
+                            .iter()
+                            .filter(|(id, _)| **id != client_id)
+                            .map(|(_, sc)| (sc.x_pos, sc.y_pos))
+                            .collect();
+
+                        let Some(spawn) = choose_spawn_point(&spawn_points, &occupied) else {
+                            println!("Client {} asked to spawn before a map was picked", client_id);
+                            continue;
+                        };
+
+                        // count the spot as taken right away so the next request this tick goes elsewhere
+                        if let Some(sc) = client_states.get_mut(&client_id) {
+                            sc.x_pos = spawn.0;
+                            sc.y_pos = spawn.1;
+                            sc.health = MAX_HEALTH;
+                        }
+
+                        last_attackers.remove(&client_id);
+
+                        send_spawn(&mut server, client_id, spawn, config);
+                    }
+                    ServerEventType::Died => {
+                        if let Some(sc) = client_states.get(&client_id) {
+                            broadcast_death_effect(&mut server, sc, config);
+                        }
+
+                        let attacker = last_attackers.remove(&client_id);
+                        match_events.extend(match_controller.on_ko(client_id, attacker));
+                    }
+                    ServerEventType::Hit { victim, view_tick } => {
+                        // our own hurtbox sits right up against our hitbox, and nobody we don't know about can be hit
+                        if victim == client_id || !client_states.contains_key(&victim) {
+                            println!("Client {} reported a hit on {}, who they can't hit", client_id, victim);
+                            continue;
+                        }
+
+                        // both fighters have to be in play
+                        let match_state = match_controller.get_state();
+                        if !match_state.can_play(client_id) || !match_state.can_play(victim) {
+                            continue;
+                        }
+
+                        let Some(attacker_state) = client_states.get(&client_id) else {
+                            continue;
+                        };
+
+                        // the hit comes from the attack frame we last saw them on, out of the same definitions their client plays
+                        let Some(hit) = get_hit_at(&attacker_state.character_type, &attacker_state.anim_type, attacker_state.sprite_frame) else {
+                            println!("Client {} reported a hit outside of an attack", client_id);
+                            continue;
+                        };
+
+                        if attack_victims.get(&client_id).is_some_and(|victims| victims.contains(&victim)) {
+                            continue;
+                        }
+
+                        // the attacker sees themselves in the present but everyone else slightly in the past,
+                        // so the victim gets rewound to what was on the attacker's screen
+                        let Some(hurtbox) = hurtbox_history.get_hurtbox(victim, view_tick, tick) else {
+                            continue;
+                        };
+
+                        if !hit.connects(attacker_state, hurtbox) {
+                            continue;
+                        }
+
+                        attack_victims.entry(client_id).or_default().insert(victim);
+                        landed_hits.push((client_id, victim, hit));
+                    }
+                    ServerEventType::SelectMatchMode(mode) => {
+                        if match_controller.get_state().phase != MatchPhase::Lobby || !lobby.is_host(client_id) {
+                            println!("Client {} can't pick the rules, only the host can between matches", client_id);
+                            continue;
+                        }
+
+                        println!("Client {} picked {}", client_id, mode.get_description());
+                        match_controller.set_mode(mode);
+                    }
+                    ServerEventType::LobbySelectCharacter(character_type) => {
+                        lobby.select_character(client_id, character_type);
+                    }
+                    ServerEventType::LobbyVoteMap(map_index) => {
+                        lobby.vote_map(client_id, map_index);
+                    }
+                    ServerEventType::LobbySetReady(is_ready) => {
+                        lobby.set_ready(client_id, is_ready);
+                    }
+                }
+            }
+        }
+
+
+        // the match starts once everyone in the lobby has readied up, a room of only bots would never get a map
+        let human_count = match_controller.get_player_count() - bots.get_client_ids().len();
+        if lobby.is_everyone_ready() && match_controller.get_player_count() >= MIN_PLAYERS && human_count > 0 {
+            if let Some(event) = match_controller.start_countdown() {
+                if let Some(map) = lobby.start_match() {
+                    println!("Starting on {}", map);
+                }
+                match_events.push(event);
+            }
+        }
+
+        for bot_hit in bots.update(duration.as_secs_f32(), match_controller.get_state(), &mut client_states) {
+            let match_state = match_controller.get_state();
+            if match_state.can_play(bot_hit.attacker) && match_state.can_play(bot_hit.victim) {
+                landed_hits.push((bot_hit.attacker, bot_hit.victim, bot_hit.hit));
+            }
+        }
+
+        for (attacker, victim, hit) in landed_hits {
+            let Some(attacker_facing) = client_states.get(&attacker).map(|sc| sc.facing.clone()) else {
+                continue;
+            };
+
+            let Some(victim_state) = client_states.get_mut(&victim) else {
+                continue;
+            };
+
+            // already down or fresh off a respawn
+            if victim_state.health <= 0 || victim_state.is_invulnerable {
+                continue;
+            }
+
+            victim_state.health = (victim_state.health - hit.damage).max(0);
+            let is_fatal = victim_state.health == 0;
+
+            last_attackers.insert(victim, attacker);
+
+            if is_fatal {
+                broadcast_death_effect(&mut server, victim_state, config);
+                last_attackers.remove(&victim);
+                match_events.extend(match_controller.on_ko(victim, Some(attacker)));
+            }
+
+            // bots take the knockback here, clients get told and work it out themselves
+            if bots.is_bot(victim) {
+                bots.on_hit(victim, &hit, &attacker_facing);
+                continue;
+            }
+
+            let hit_event = ClientEventType::Hit { attacker, hit, attacker_facing, is_fatal };
+            match bincode::encode_to_vec(&hit_event, config) {
+                Ok(encoded) => server.send_message(victim, DefaultChannel::ReliableOrdered, encoded),
+                Err(e) => println!("Error encoding hit event: {:?}", e),
+            }
+        }
+
+        // bots are always ready, they have to be told again after every match
+        for client_id in bots.get_client_ids() {
+            lobby.set_ready(client_id, true);
+        }
+
+        match_events.extend(match_controller.update(duration.as_secs_f32()));
+
+        for match_event in match_events {
+            match &match_event {
+                MatchEvent::CountdownStarted => {
+                    let map = lobby.get_state().current_map.clone().unwrap_or_default();
+                    spawn_points = map_spawn_points.get(&map).cloned().unwrap_or_else(|| vec![FALLBACK_SPAWN_POINT]);
+                    bots.reset(&mut client_states, &spawn_points);
+
+                    if replay_dir.is_some() {
+                        let mode = match_controller.get_state().mode.clone();
+                        replay_recorder = Some(ReplayRecorder::new(map, mode, TICK_DURATION.as_secs_f32()));
+                    }
+                }
+                MatchEvent::Finished(results) => {
+                    if let (Some(recorder), Some(replay_dir)) = (replay_recorder.take(), replay_dir.as_ref()) {
+                        save_replay(recorder, replay_dir, results.clone());
+                    }
+                }
+                MatchEvent::ReturnedToLobby => {
+                    lobby.return_from_match();
+                    replay_recorder = None;
+                }
+                _ => {}
             }
+
+            handle_match_event(match_event, &mut server, config);
         }
 
+        let lobby_event = ClientEventType::LobbyUpdate(lobby.get_state().clone());
+        if sent_lobby_state.as_ref() != Some(lobby.get_state()) {
+            broadcast(&mut server, &lobby_event, config);
+            sent_lobby_state = Some(lobby.get_state().clone());
+        } else {
+            for client_id in &newly_joined {
+                send(&mut server, *client_id, &lobby_event, config);
+            }
+        }
+
+        let match_state_event = ClientEventType::MatchStateUpdate(match_controller.get_state().clone());
+        if !sent_match_state.as_ref().is_some_and(|sent| sent.looks_same(match_controller.get_state())) {
+            broadcast(&mut server, &match_state_event, config);
+            sent_match_state = Some(match_controller.get_state().clone());
+        } else {
+            for client_id in &newly_joined {
+                send(&mut server, *client_id, &match_state_event, config);
+            }
+        }
 
-        let client_mapping_event = ClientEventType::ClientCharacterUpdate(client_states.clone());
+        hurtbox_history.record(tick, &client_states);
+
+        // once an attacker's hitbox is gone their next attack can hit everyone again
+        attack_victims.retain(|client_id, _| {
+            client_states
+                .get(client_id)
+                .is_some_and(|sc| get_hit_at(&sc.character_type, &sc.anim_type, sc.sprite_frame).is_some())
+        });
+
+        if let Some(recorder) = replay_recorder.as_mut() {
+            recorder.record(&client_states);
+        }
+
+        let client_mapping_event = ClientEventType::ClientCharacterUpdate {
+            tick,
+            tick_ms: tick_time.as_secs_f32() * 1000.0,
+            clients: client_states.clone(),
+        };
         let encoded_client_mapping_event = match bincode::encode_to_vec(&client_mapping_event, config) {
             Ok(encoded_client_mapping_event) => encoded_client_mapping_event,
             Err(e) => {
@@ -98,6 +614,116 @@ fn server(public_addr: SocketAddr) {
 
         server.broadcast_message(DefaultChannel::ReliableOrdered, encoded_client_mapping_event);
         transport.send_packets(&mut server);
-        thread::sleep(Duration::from_millis(16));
+        tick_time = now.elapsed();
+        thread::sleep(TICK_DURATION);
+    }
+}
+
+fn handle_match_event(event: MatchEvent, server: &mut RenetServer, config: Configuration) {
+    match event {
+        // clients load the map and ask for a spawn point on their own when the countdown starts
+        MatchEvent::CountdownStarted => {
+            println!("Match starting");
+        }
+        MatchEvent::Started => {
+            println!("Match started");
+        }
+        MatchEvent::Finished(results) => {
+            println!("Match finished, winner: {:?}", results.winner);
+
+            match bincode::encode_to_vec(&ClientEventType::MatchFinished(results), config) {
+                Ok(encoded) => server.broadcast_message(DefaultChannel::ReliableOrdered, encoded),
+                Err(e) => println!("Error encoding match results: {:?}", e),
+            }
+        }
+        MatchEvent::ReturnedToLobby => {
+            println!("Back to the lobby");
+        }
+    }
+}
+
+fn save_replay(recorder: ReplayRecorder, replay_dir: &Path, results: MatchResults) {
+    if let Err(e) = std::fs::create_dir_all(replay_dir) {
+        println!("Couldn't create {}: {}", replay_dir.display(), e);
+        return;
     }
+
+    let finished = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+    let path = replay_dir.join(format!("match-{}.{}", finished, REPLAY_EXTENSION));
+
+    match recorder.save(&path, Some(results)) {
+        Ok(()) => println!("Saved the replay to {}", path.display()),
+        Err(e) => println!("{}", e),
+    }
+}
+
+fn broadcast(server: &mut RenetServer, event: &ClientEventType, config: Configuration) {
+    match bincode::encode_to_vec(event, config) {
+        Ok(encoded) => server.broadcast_message(DefaultChannel::ReliableOrdered, encoded),
+        Err(e) => println!("Error encoding event: {:?}", e),
+    }
+}
+
+fn send(server: &mut RenetServer, client_id: ClientId, event: &ClientEventType, config: Configuration) {
+    match bincode::encode_to_vec(event, config) {
+        Ok(encoded) => server.send_message(client_id, DefaultChannel::ReliableOrdered, encoded),
+        Err(e) => println!("Error encoding event: {:?}", e),
+    }
+}
+
+/// Everyone sees the burst where `sc` went down, clients can't tell a knockout from a respawn on their own
+fn broadcast_death_effect(server: &mut RenetServer, sc: &ServerClient, config: Configuration) {
+    let feet = (sc.x_pos + sc.width as f32 / 2.0, sc.y_pos + sc.height as f32);
+    broadcast(server, &ClientEventType::Effect { name: DEATH_EFFECT.to_string(), position: feet }, config);
+}
+
+fn send_spawn(server: &mut RenetServer, client_id: ClientId, spawn: SpawnPoint, config: Configuration) {
+    let spawn_event = ClientEventType::SpawnAssigned(spawn);
+    match bincode::encode_to_vec(&spawn_event, config) {
+        Ok(encoded) => server.send_message(client_id, DefaultChannel::ReliableOrdered, encoded),
+        Err(e) => println!("Error encoding spawn event: {:?}", e),
+    }
+}
+
+/// Adds or removes bots until humans and bots add up to the `--bots` count again
+fn balance_bots(
+    bots: &mut Bots,
+    server: &mut RenetServer,
+    match_controller: &mut MatchController,
+    lobby: &mut Lobby,
+    client_states: &mut HashMap<ClientId, ServerClient>,
+    last_attackers: &mut HashMap<ClientId, ClientId>,
+    config: Configuration,
+) -> Vec<MatchEvent> {
+    let mut match_events = vec![];
+    let human_count = match_controller.get_player_count() - bots.get_client_ids().len();
+    let change = bots.get_wanted_change(human_count);
+
+    for _ in 0..change.max(0) {
+        let (client_id, state) = bots.add();
+        println!("Bot {} joined as {}", client_id, state.name);
+
+        broadcast(server, &ClientEventType::PlayerJoined { client_id, name: state.name.clone() }, config);
+        match_controller.add_player(client_id, state.name.clone());
+        lobby.add_player(client_id, state.name.clone(), true);
+        lobby.select_character(client_id, state.character_type.clone());
+        client_states.insert(client_id, state);
+    }
+
+    for _ in 0..(-change).max(0) {
+        let Some(client_id) = bots.remove() else {
+            break;
+        };
+
+        if let Some(state) = client_states.remove(&client_id) {
+            println!("Bot {} made way for a player", state.name);
+            broadcast(server, &ClientEventType::PlayerLeft { client_id, name: state.name }, config);
+        }
+
+        last_attackers.remove(&client_id);
+        match_events.extend(match_controller.remove_player(client_id));
+        lobby.remove_player(client_id);
+    }
+
+    match_events
 }
@@ -0,0 +1,361 @@
+use std::{cmp::Reverse, collections::HashSet};
+
+use common::match_rules::{MatchMode, MatchPhase, MatchResults, MatchState, PlayerScore};
+
+const COUNTDOWN_SECONDS: f32 = 3.0;
+// how long the results stay up before going back to the lobby
+const RESULTS_SECONDS: f32 = 8.0;
+pub const MIN_PLAYERS: usize = 2;
+
+/// Things the server loop has to react to, like respawning everyone when a match starts
+pub enum MatchEvent {
+    CountdownStarted,
+    Started,
+    Finished(MatchResults),
+    ReturnedToLobby,
+}
+
+/// Runs the rules of a match: countdown, scoring, sudden death and working out who won
+pub struct MatchController {
+    state: MatchState,
+    /// Players in the order they were knocked out of the match, used for placements
+    elimination_order: Vec<u64>,
+    /// Everyone who was there when the countdown started, late joiners only watch
+    participants: HashSet<u64>,
+}
+
+impl MatchController {
+    pub fn new(mode: MatchMode) -> Self {
+        Self {
+            state: MatchState::new(mode),
+            elimination_order: vec![],
+            participants: HashSet::new(),
+        }
+    }
+
+    pub fn get_state(&self) -> &MatchState {
+        &self.state
+    }
+
+    pub fn get_player_count(&self) -> usize {
+        self.state.scores.len()
+    }
+
+    pub fn set_mode(&mut self, mode: MatchMode) {
+        if self.state.phase == MatchPhase::Lobby {
+            self.state.mode = mode;
+        }
+    }
+
+    /// Players joining mid match sit it out until the next one
+    pub fn add_player(&mut self, client_id: u64, name: String) {
+        let mut score = PlayerScore::new(name, &self.state.mode);
+        score.is_eliminated = self.state.phase != MatchPhase::Lobby;
+        self.state.scores.insert(client_id, score);
+    }
+
+    pub fn remove_player(&mut self, client_id: u64) -> Option<MatchEvent> {
+        self.state.scores.remove(&client_id);
+        self.elimination_order.retain(|id| *id != client_id);
+        self.participants.remove(&client_id);
+        self.check_for_end()
+    }
+
+    pub fn can_spawn(&self, client_id: u64) -> bool {
+        self.state.can_spawn(client_id)
+    }
+
+    /// Starts the countdown if we're in the lobby and have enough players
+    pub fn start_countdown(&mut self) -> Option<MatchEvent> {
+        if self.state.phase != MatchPhase::Lobby || self.state.scores.len() < MIN_PLAYERS {
+            return None;
+        }
+
+        for score in self.state.scores.values_mut() {
+            *score = PlayerScore::new(score.name.clone(), &self.state.mode);
+        }
+
+        self.elimination_order.clear();
+        self.participants = self.state.scores.keys().copied().collect();
+        self.state.time_left = match self.state.mode {
+            MatchMode::Timed { seconds } => Some(seconds as f32),
+            _ => None,
+        };
+        self.state.phase = MatchPhase::Countdown { seconds_left: COUNTDOWN_SECONDS };
+
+        Some(MatchEvent::CountdownStarted)
+    }
+
+    /// Someone died, `attacker` gets the credit if there was one
+    pub fn on_ko(&mut self, victim: u64, attacker: Option<u64>) -> Option<MatchEvent> {
+        if !self.state.is_running() {
+            return None;
+        }
+
+        if let Some(attacker) = attacker.filter(|attacker| *attacker != victim) {
+            if let Some(score) = self.state.scores.get_mut(&attacker) {
+                score.kos += 1;
+            }
+        }
+
+        let score = self.state.scores.get_mut(&victim)?;
+        score.falls += 1;
+
+        if let Some(stocks_left) = score.stocks_left.as_mut() {
+            *stocks_left = stocks_left.saturating_sub(1);
+
+            if *stocks_left == 0 && !score.is_eliminated {
+                score.is_eliminated = true;
+                self.elimination_order.push(victim);
+            }
+        }
+
+        self.check_for_end()
+    }
+
+    pub fn update(&mut self, dt: f32) -> Option<MatchEvent> {
+        match &mut self.state.phase {
+            MatchPhase::Lobby => None,
+            MatchPhase::Countdown { seconds_left } => {
+                *seconds_left -= dt;
+
+                if *seconds_left <= 0.0 {
+                    self.state.phase = MatchPhase::InProgress;
+                    return Some(MatchEvent::Started);
+                }
+
+                None
+            }
+            MatchPhase::InProgress => {
+                if let Some(time_left) = self.state.time_left.as_mut() {
+                    *time_left = (*time_left - dt).max(0.0);
+
+                    if *time_left == 0.0 {
+                        return self.on_time_up();
+                    }
+                }
+
+                None
+            }
+            MatchPhase::SuddenDeath => None,
+            MatchPhase::Finished { seconds_left } => {
+                *seconds_left -= dt;
+
+                if *seconds_left <= 0.0 {
+                    self.state.phase = MatchPhase::Lobby;
+                    self.state.time_left = None;
+
+                    // everyone is back in for free play
+                    for score in self.state.scores.values_mut() {
+                        score.is_eliminated = false;
+                    }
+
+                    return Some(MatchEvent::ReturnedToLobby);
+                }
+
+                None
+            }
+        }
+    }
+
+    /// The clock ran out, a clear leader wins otherwise the leaders go to sudden death
+    fn on_time_up(&mut self) -> Option<MatchEvent> {
+        let best = self.get_participant_scores().map(|(_, score)| score.get_points()).max()?;
+
+        let mut losers: Vec<(u64, i32)> = self
+            .get_participant_scores()
+            .filter(|(_, score)| score.get_points() < best)
+            .map(|(client_id, score)| (client_id, score.get_points()))
+            .collect();
+
+        if self.participants.len() - losers.len() <= 1 {
+            return Some(self.finish());
+        }
+
+        // worst goes out first so they place last
+        losers.sort_by_key(|(_, points)| *points);
+        for (client_id, _) in losers {
+            if let Some(score) = self.state.scores.get_mut(&client_id) {
+                score.is_eliminated = true;
+            }
+            self.elimination_order.push(client_id);
+        }
+
+        for (client_id, score) in self.state.scores.iter_mut() {
+            if self.participants.contains(client_id) && !score.is_eliminated {
+                score.stocks_left = Some(1);
+            }
+        }
+
+        self.state.phase = MatchPhase::SuddenDeath;
+        None
+    }
+
+    fn check_for_end(&mut self) -> Option<MatchEvent> {
+        if !self.state.is_running() && !matches!(self.state.phase, MatchPhase::Countdown { .. }) {
+            return None;
+        }
+
+        let remaining = self.get_participant_scores().filter(|(_, score)| !score.is_eliminated).count();
+
+        let reached_target = match self.state.mode {
+            MatchMode::FirstToKos { kos } => self.state.scores.values().any(|score| score.kos >= kos),
+            MatchMode::Stock { .. } | MatchMode::Timed { .. } => false,
+        };
+
+        if remaining <= 1 || reached_target {
+            return Some(self.finish());
+        }
+
+        None
+    }
+
+    fn get_participant_scores(&self) -> impl Iterator<Item = (u64, &PlayerScore)> {
+        self.state.scores
+            .iter()
+            .filter(|(client_id, _)| self.participants.contains(*client_id))
+            .map(|(client_id, score)| (*client_id, score))
+    }
+
+    fn finish(&mut self) -> MatchEvent {
+        self.state.phase = MatchPhase::Finished { seconds_left: RESULTS_SECONDS };
+
+        let mut standings: Vec<(u64, PlayerScore)> = self.state.scores
+            .iter()
+            .map(|(client_id, score)| (*client_id, score.clone()))
+            .collect();
+
+        // survivors first, then whoever was knocked out last, then by points. anyone who joined after the
+        // start comes last, None sorts below every knockout
+        standings.sort_by_key(|(client_id, score)| {
+            let knocked_out_at = self.participants.contains(client_id).then(|| {
+                self.elimination_order
+                    .iter()
+                    .position(|id| id == client_id)
+                    .unwrap_or(usize::MAX)
+            });
+
+            Reverse((knocked_out_at, score.get_points(), score.kos))
+        });
+
+        let winner = standings.first().map(|(client_id, _)| *client_id);
+
+        MatchEvent::Finished(MatchResults {
+            mode: self.state.mode.clone(),
+            standings,
+            winner,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // adds the players and plays through the countdown
+    fn start_match(mode: MatchMode, client_ids: &[u64]) -> MatchController {
+        let mut controller = MatchController::new(mode);
+
+        for client_id in client_ids {
+            controller.add_player(*client_id, format!("Player {}", client_id));
+        }
+
+        assert!(matches!(controller.start_countdown(), Some(MatchEvent::CountdownStarted)));
+        assert!(matches!(controller.update(COUNTDOWN_SECONDS), Some(MatchEvent::Started)));
+        controller
+    }
+
+    fn get_results(event: Option<MatchEvent>) -> MatchResults {
+        match event {
+            Some(MatchEvent::Finished(results)) => results,
+            _ => panic!("the match should have finished"),
+        }
+    }
+
+    fn get_placings(results: &MatchResults) -> Vec<u64> {
+        results.standings.iter().map(|(client_id, _)| *client_id).collect()
+    }
+
+    #[test]
+    fn stock_match_ends_with_the_last_one_standing() {
+        let mut controller = start_match(MatchMode::Stock { lives: 1 }, &[1, 2, 3]);
+
+        assert!(controller.on_ko(2, Some(1)).is_none());
+        let results = get_results(controller.on_ko(3, Some(1)));
+
+        assert_eq!(results.winner, Some(1));
+        assert_eq!(get_placings(&results), vec![1, 3, 2]);
+    }
+
+    #[test]
+    fn timed_match_goes_to_the_leader() {
+        let mut controller = start_match(MatchMode::Timed { seconds: 60 }, &[1, 2, 3]);
+
+        controller.on_ko(2, Some(1));
+        controller.on_ko(3, None);
+        let results = get_results(controller.update(60.0));
+
+        assert_eq!(results.winner, Some(1));
+    }
+
+    #[test]
+    fn timed_match_tie_goes_to_sudden_death() {
+        let mut controller = start_match(MatchMode::Timed { seconds: 60 }, &[1, 2, 3]);
+
+        controller.on_ko(3, Some(1));
+        controller.on_ko(3, Some(2));
+        assert!(controller.update(60.0).is_none());
+        assert_eq!(controller.get_state().phase, MatchPhase::SuddenDeath);
+        assert!(!controller.can_spawn(3));
+
+        let results = get_results(controller.on_ko(2, Some(1)));
+        assert_eq!(get_placings(&results), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn first_to_kos_ends_when_someone_gets_there() {
+        let mut controller = start_match(MatchMode::FirstToKos { kos: 2 }, &[1, 2]);
+
+        assert!(controller.on_ko(1, Some(2)).is_none());
+        let results = get_results(controller.on_ko(1, Some(2)));
+
+        assert_eq!(results.winner, Some(2));
+    }
+
+    #[test]
+    fn late_joiners_place_below_everyone_who_played() {
+        let mut controller = start_match(MatchMode::Stock { lives: 1 }, &[1, 2, 3]);
+        controller.add_player(4, "Late".to_string());
+        assert!(!controller.can_spawn(4));
+
+        controller.on_ko(2, None);
+        let results = get_results(controller.on_ko(3, None));
+
+        assert_eq!(results.winner, Some(1));
+        assert_eq!(get_placings(&results), vec![1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn late_joiners_cant_win_on_time() {
+        // both players fell without anyone knocking them out, so they're below the late joiner's 0 points
+        let mut controller = start_match(MatchMode::Timed { seconds: 60 }, &[1, 2]);
+        controller.add_player(3, "Late".to_string());
+
+        controller.on_ko(1, None);
+        controller.on_ko(2, None);
+        controller.on_ko(2, None);
+        let results = get_results(controller.update(60.0));
+
+        assert_eq!(results.winner, Some(1));
+        assert_eq!(get_placings(&results), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn late_joiners_dont_keep_a_match_going() {
+        let mut controller = start_match(MatchMode::Stock { lives: 1 }, &[1, 2]);
+        controller.add_player(3, "Late".to_string());
+
+        let results = get_results(controller.remove_player(2));
+        assert_eq!(results.winner, Some(1));
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use common::types::ServerClient;
+use renet::ClientId;
+
+// long enough for the old connection to time out and the client to get a few attempts in
+pub const RECONNECT_GRACE: Duration = Duration::from_secs(45);
+
+/// Players who dropped out of a running match, their slot waits here in case they come back
+pub struct HeldSlots {
+    slots: HashMap<ClientId, (Instant, ServerClient)>,
+}
+
+impl HeldSlots {
+    pub fn new() -> Self {
+        Self {
+            slots: HashMap::new(),
+        }
+    }
+
+    pub fn hold(&mut self, client_id: ClientId, state: ServerClient) {
+        self.slots.insert(client_id, (Instant::now(), state));
+    }
+
+    /// The player's state from before they dropped, if they made it back in time
+    pub fn take(&mut self, client_id: ClientId) -> Option<ServerClient> {
+        self.slots.remove(&client_id).map(|(_, state)| state)
+    }
+
+    /// Forgets slots nobody came back for, the caller still has to remove those players from the match
+    pub fn expire(&mut self) -> Vec<ClientId> {
+        let expired: Vec<ClientId> = self
+            .slots
+            .iter()
+            .filter(|(_, (dropped_at, _))| dropped_at.elapsed() > RECONNECT_GRACE)
+            .map(|(client_id, _)| *client_id)
+            .collect();
+
+        for client_id in &expired {
+            self.slots.remove(client_id);
+        }
+
+        expired
+    }
+}